@@ -0,0 +1,464 @@
+//! Computer opponent: picks the commands of one team by searching ahead on copies of the game.
+//!
+//! The search looks a number of commands ahead, alternating between the bot and the other
+//! teams at every `NextTurn`. All other teams are assumed to play against the bot. Only the
+//! most promising commands of every position are searched further. A [`BotSearch`] can be
+//! spread over several frames, so the game keeps rendering while the bot thinks.
+
+use std::fmt::{Display, Formatter};
+
+use game_model::{
+    GameError, GameResult,
+    game::Game,
+    outcome::GameOutcome,
+    piece::{Piece, PieceKind},
+};
+
+use crate::{
+    game_controller::{GameCommand, GameController},
+    outcome,
+};
+
+/// Score of a decided game, larger than any difference in material.
+const WIN: f32 = 10_000.;
+
+/// Unused pieces are worth less than placed ones, otherwise the bot would hoard them
+/// and end its turns right away.
+const UNUSED_PIECE_VALUE: f32 = 0.25;
+
+/// Bonus for a piece that can still move or use its power.
+const READY_PIECE_VALUE: f32 = 0.1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    /// The difficulty with this name, ignoring case, e.g. `hard`.
+    pub fn parse(name: &str) -> GameResult<Difficulty> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| GameError::InvalidDefinition(format!("Unknown difficulty '{}'", name)))
+    }
+
+    /// Number of commands looked ahead, including the one that is chosen.
+    fn depth(&self) -> u8 {
+        match self {
+            Difficulty::Easy => 1,
+            Difficulty::Medium => 2,
+            Difficulty::Hard => 3,
+        }
+    }
+
+    /// Number of commands searched further in every position.
+    fn width(&self) -> usize {
+        match self {
+            Difficulty::Easy => usize::MAX,
+            Difficulty::Medium => 12,
+            Difficulty::Hard => 8,
+        }
+    }
+
+    /// Commands that score at most this much below the best one are picked as well.
+    fn tolerance(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 1.,
+            Difficulty::Medium | Difficulty::Hard => 0.,
+        }
+    }
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Plays one team. Its commands are meant to be executed like those of a human player,
+/// e.g. with [`CommandHandler::handle_new_command`](crate::command_handler::CommandHandler::handle_new_command).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Bot {
+    pub team_id: usize,
+    pub difficulty: Difficulty,
+}
+
+impl Bot {
+    pub fn new(team_id: usize, difficulty: Difficulty) -> Self {
+        Bot {
+            team_id,
+            difficulty,
+        }
+    }
+
+    /// The next command for the bot's team, `NextTurn` once nothing else improves its position.
+    /// Must only be called while it is the bot's turn.
+    pub fn next_command(&self, game: &Game) -> GameCommand {
+        let mut search = self.start_search(game);
+        loop {
+            if let Some(command) = search.step(usize::MAX) {
+                return command;
+            }
+        }
+    }
+
+    /// A search for [`Self::next_command`] that is run step by step with [`BotSearch::step`].
+    pub fn start_search(&self, game: &Game) -> BotSearch {
+        let mut nodes = 0;
+        let mut pending = self.candidates(game, &mut nodes);
+        pending.reverse();
+
+        BotSearch {
+            bot: *self,
+            hash: game.hash,
+            pending,
+            scored: vec![],
+            best_score: f32::NEG_INFINITY,
+        }
+    }
+
+    /// Alpha-beta search where the bot maximizes and every other team minimizes the score.
+    /// Scores at or below `alpha` and at or above `beta` are only bounds of the real score.
+    /// Counts the searched positions in `nodes`.
+    fn search(
+        &self,
+        game: &Game,
+        depth: u8,
+        mut alpha: f32,
+        mut beta: f32,
+        nodes: &mut usize,
+    ) -> f32 {
+        if depth == 0 {
+            return evaluate(game, self.team_id);
+        }
+
+        if let Some(score) = decided(game, self.team_id) {
+            return score;
+        }
+
+        let maximizing = game.current_team_index == self.team_id;
+        let mut best = if maximizing {
+            f32::NEG_INFINITY
+        } else {
+            f32::INFINITY
+        };
+
+        for (_, child) in self.candidates(game, nodes) {
+            let score = self.search(&child, depth - 1, alpha, beta, nodes);
+            if maximizing {
+                best = best.max(score);
+                alpha = alpha.max(best);
+            } else {
+                best = best.min(score);
+                beta = beta.min(best);
+            }
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best
+    }
+
+    /// The legal commands of the current team together with the game after them, the best
+    /// ones for that team first and cut down to the difficulty's width.
+    fn candidates(&self, game: &Game, nodes: &mut usize) -> Vec<(GameCommand, Game)> {
+        let mover = game.current_team_index;
+
+        let mut children: Vec<(GameCommand, Game, f32)> = GameController::legal_commands(game)
+            .into_iter()
+            .filter_map(|command| {
+                let mut child = game.clone();
+                GameController::execute(&mut child, &command).ok()?;
+                let score = evaluate(&child, mover);
+                Some((command, child, score))
+            })
+            .collect();
+        *nodes += children.len();
+        children.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        // Ending the turn is always searched, it's the only way to see the other teams answer
+        let width = self.difficulty.width();
+        if let Some(next_turn) = children
+            .iter()
+            .position(|(command, _, _)| *command == GameCommand::NextTurn)
+            && next_turn >= width
+        {
+            let next_turn = children.remove(next_turn);
+            children.truncate(width - 1);
+            children.push(next_turn);
+        } else {
+            children.truncate(width);
+        }
+
+        children
+            .into_iter()
+            .map(|(command, child, _)| (command, child))
+            .collect()
+    }
+}
+
+/// The search of a [`Bot`] for its next command, spread over several calls to
+/// [`BotSearch::step`], e.g. one per frame.
+#[derive(Debug, Clone)]
+pub struct BotSearch {
+    bot: Bot,
+    /// Hash of the game the search started on
+    hash: u64,
+    /// The commands still to score with the game after them, the most promising one last
+    pending: Vec<(GameCommand, Game)>,
+    /// The commands scored so far with the game after them. Scores below the best one
+    /// minus the tolerance are only upper bounds.
+    scored: Vec<(GameCommand, Game, f32)>,
+    best_score: f32,
+}
+
+impl BotSearch {
+    /// Whether the search is still about `game`, which hasn't changed since it started.
+    pub fn is_for(&self, game: &Game) -> bool {
+        self.hash == game.hash && self.bot.team_id == game.current_team_index
+    }
+
+    /// Scores the next commands until at least `max_nodes` positions were searched or
+    /// every command is scored. A started command is always scored completely. Returns the
+    /// chosen command once the search is done.
+    pub fn step(&mut self, max_nodes: usize) -> Option<GameCommand> {
+        let depth = self.bot.difficulty.depth();
+        let tolerance = self.bot.difficulty.tolerance();
+
+        let mut nodes = 0;
+        while nodes < max_nodes {
+            let Some((command, child)) = self.pending.pop() else {
+                return Some(self.choose(&mut nodes));
+            };
+
+            let alpha = self.best_score - tolerance;
+            let score = self
+                .bot
+                .search(&child, depth - 1, alpha, f32::INFINITY, &mut nodes);
+            self.best_score = self.best_score.max(score);
+            self.scored.push((command, child, score));
+        }
+
+        None
+    }
+
+    /// Picks among the scored commands once all of them are scored.
+    fn choose(&mut self, nodes: &mut usize) -> GameCommand {
+        let depth = self.bot.difficulty.depth();
+        let tolerance = self.bot.difficulty.tolerance();
+
+        let mut choices: Vec<(GameCommand, f32)> = self
+            .scored
+            .iter()
+            .filter(|(_, _, score)| *score >= self.best_score - tolerance)
+            .map(|(command, child, score)| {
+                // Scores that failed low are bounds, the tolerated ones need exact scores
+                let score = if tolerance > 0. {
+                    let (alpha, beta) = (f32::NEG_INFINITY, f32::INFINITY);
+                    self.bot.search(child, depth - 1, alpha, beta, nodes)
+                } else {
+                    *score
+                };
+                (*command, score)
+            })
+            .collect();
+        choices.retain(|(_, score)| *score >= self.best_score - tolerance);
+
+        match choices.len() {
+            0 => GameCommand::NextTurn,
+            1 => choices[0].0,
+            _ if tolerance == 0. => choices[0].0,
+            len => choices[quad_rand::gen_range(0, len)].0,
+        }
+    }
+}
+
+/// How good `game` is for the team: its strength minus that of the strongest other team.
+fn evaluate(game: &Game, team_id: usize) -> f32 {
+    if let Some(score) = decided(game, team_id) {
+        return score;
+    }
+
+    let strongest_opponent = game
+        .active_teams()
+        .into_iter()
+        .filter(|other| *other != team_id)
+        .map(|other| strength(game, other))
+        .fold(0., f32::max);
+
+    strength(game, team_id) - strongest_opponent
+}
+
+/// `WIN` or `-WIN` if the game is over for the team. Draws need the course of the game
+/// and are left to the evaluation.
+fn decided(game: &Game, team_id: usize) -> Option<f32> {
+    match outcome::winner(game) {
+        Some(GameOutcome::Win {
+            team_id: winner, ..
+        }) if winner == team_id => Some(WIN),
+        Some(_) => Some(-WIN),
+        None if !game.active_teams().contains(&team_id) => Some(-WIN),
+        None => None,
+    }
+}
+
+fn strength(game: &Game, team_id: usize) -> f32 {
+    let mut strength = game.get_team(team_id).unused_pieces as f32 * UNUSED_PIECE_VALUE;
+
+    game.board.for_each_placed_piece(|_, piece| {
+        if piece.team_id == team_id {
+            strength += piece_value(piece);
+        }
+    });
+
+    strength
+}
+
+fn piece_value(piece: &Piece) -> f32 {
+    let value = match piece.piece_kind {
+        PieceKind::Simple => 1.,
+        PieceKind::HorizontalBar | PieceKind::VerticalBar => 4.,
+        PieceKind::Cross | PieceKind::Castle => 5.,
+        PieceKind::Sniper => 6.,
+        PieceKind::Queen => 8.,
+    };
+
+    if piece.can_move() || piece.can_use_special() {
+        value + READY_PIECE_VALUE
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setup::set_up_pieces;
+    use game_model::{Point2, game::Team};
+
+    fn setup_game(num_teams: usize, size: u8) -> Game {
+        let teams = (0..num_teams)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 0,
+            })
+            .collect();
+
+        Game::new(teams, size, size)
+    }
+
+    fn ready_piece(team_id: usize, kind: PieceKind) -> Piece {
+        let mut piece = Piece::new(team_id, kind);
+        piece.exhaustion.reset();
+        piece
+    }
+
+    #[test]
+    fn bot_captures_an_undefended_piece() {
+        let mut game = setup_game(2, 8);
+        game.board
+            .place_piece_at(ready_piece(0, PieceKind::Simple), &Point2::new(1, 1))
+            .unwrap();
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(2, 2))
+            .unwrap();
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(6, 6))
+            .unwrap();
+
+        for difficulty in [Difficulty::Medium, Difficulty::Hard] {
+            assert_eq!(
+                Bot::new(0, difficulty).next_command(&game),
+                GameCommand::MovePiece(Point2::new(1, 1), Point2::new(2, 2)),
+                "{} bot",
+                difficulty
+            );
+        }
+    }
+
+    #[test]
+    fn search_can_be_split_into_steps() {
+        let mut game = setup_game(2, 6);
+        for command in set_up_pieces(2, &game.clone()).unwrap() {
+            GameController::execute(&mut game, &command).unwrap();
+        }
+
+        for difficulty in [Difficulty::Medium, Difficulty::Hard] {
+            let bot = Bot::new(game.current_team_index, difficulty);
+            let mut search = bot.start_search(&game);
+            assert!(search.is_for(&game));
+
+            let mut steps = 0;
+            let command = loop {
+                steps += 1;
+                if let Some(command) = search.step(1) {
+                    break command;
+                }
+            };
+            assert!(steps > 1);
+            assert_eq!(command, bot.next_command(&game));
+        }
+
+        let search = Bot::new(game.current_team_index, Difficulty::Easy).start_search(&game);
+        GameController::execute(&mut game, &GameCommand::NextTurn).unwrap();
+        assert!(!search.is_for(&game));
+    }
+
+    #[test]
+    fn bot_ends_its_turn_when_it_cant_do_anything() {
+        let mut game = setup_game(2, 8);
+        game.board
+            .place_piece_at(Piece::new(0, PieceKind::Simple), &Point2::new(1, 1))
+            .unwrap();
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(6, 6))
+            .unwrap();
+
+        for difficulty in Difficulty::ALL {
+            assert_eq!(
+                Bot::new(0, difficulty).next_command(&game),
+                GameCommand::NextTurn
+            );
+        }
+    }
+
+    #[test]
+    fn bots_only_issue_legal_commands() {
+        quad_rand::srand(7);
+
+        for (num_teams, difficulty) in [(2, Difficulty::Easy), (3, Difficulty::Medium)] {
+            let mut game = setup_game(num_teams, 6);
+            for command in set_up_pieces(num_teams, &game.clone()).unwrap() {
+                GameController::execute(&mut game, &command).unwrap();
+            }
+
+            let bots: Vec<Bot> = (0..num_teams)
+                .map(|team_id| Bot::new(team_id, difficulty))
+                .collect();
+
+            for _ in 0..60 {
+                if decided(&game, 0).is_some() {
+                    break;
+                }
+
+                let command = bots[game.current_team_index].next_command(&game);
+                assert!(GameController::legal_commands(&game).contains(&command));
+                GameController::execute(&mut game, &command).unwrap();
+            }
+        }
+    }
+}
@@ -3,7 +3,7 @@ use game_events::{
     atomic_events::AtomicEvent,
     event_broker::EventConsumer,
 };
-use game_model::{GameError, GameResult, game::Game};
+use game_model::{GameError, GameResult, game::Game, zobrist};
 use log::{debug, warn};
 use std::{cell::RefCell, rc::Rc};
 
@@ -12,18 +12,10 @@ pub struct BoardEventConsumer {
 }
 
 impl EventConsumer for BoardEventConsumer {
-    fn handle_event(&mut self, event: &GameAction) {
+    fn handle_event(&mut self, event: &GameAction) -> GameResult<()> {
         debug!("Handling event {}", event);
 
-        event
-            .get_compound_event()
-            .get_events()
-            .iter()
-            .for_each(|e| {
-                BoardEventConsumer::handle_event_internal(&mut (*self.game).borrow_mut(), e)
-                    .unwrap_or_else(|e| panic!("Failed to handle board event: {:?}", e))
-                // TODO: propagate error
-            });
+        BoardEventConsumer::apply(&mut (*self.game).borrow_mut(), event)
     }
 }
 
@@ -32,55 +24,302 @@ impl BoardEventConsumer {
         BoardEventConsumer { game }
     }
 
-    pub fn flush(game: &mut Game, action: Box<dyn CompoundEventBuilder>) -> FlushResult {
-        action.flush(&mut |event| {
-            BoardEventConsumer::handle_event_internal(game, event)
-                .unwrap_or_else(|e| panic!("Failed to handle board event: {:?}", e));
-            // TODO: propagate error
+    pub fn flush(
+        game: &mut Game,
+        action: Box<dyn CompoundEventBuilder>,
+    ) -> GameResult<FlushResult> {
+        BoardEventConsumer::apply_or_roll_back(game, |game, reverts| {
+            BoardEventConsumer::flush_logged(game, action, reverts)
+        })
+    }
+
+    /// Applies all atomic events of an already built action, e.g. one created by
+    /// [`GameController::next_turn`](crate::game_controller::GameController::next_turn).
+    pub fn apply(game: &mut Game, action: &GameAction) -> GameResult<()> {
+        BoardEventConsumer::apply_or_roll_back(game, |game, reverts| {
+            action
+                .get_compound_event()
+                .get_events()
+                .iter()
+                .try_for_each(|e| BoardEventConsumer::apply_logged(game, e, reverts))
         })
     }
 
-    fn handle_event_internal(game: &mut Game, event: &AtomicEvent) -> GameResult<()> {
+    /// Runs `apply`, which logs the events that revert the atomic events it applied via
+    /// [`Self::flush_logged`] or [`Self::apply_logged`]. If it fails, the logged events
+    /// are applied in reverse order, so atomic events applied before the failing one
+    /// don't linger.
+    pub fn apply_or_roll_back<T>(
+        game: &mut Game,
+        apply: impl FnOnce(&mut Game, &mut Vec<AtomicEvent>) -> GameResult<T>,
+    ) -> GameResult<T> {
+        let mut reverts = vec![];
+        apply(game, &mut reverts).inspect_err(|e| {
+            warn!("Rolling back rejected event: {}", e);
+            for revert in reverts.iter().rev() {
+                BoardEventConsumer::handle_event_internal(game, revert)
+                    .expect("Reverting an applied event can't fail");
+            }
+        })
+    }
+
+    /// Flushes `action` without rolling back, see [`Self::apply_or_roll_back`].
+    pub(crate) fn flush_logged(
+        game: &mut Game,
+        action: Box<dyn CompoundEventBuilder>,
+        reverts: &mut Vec<AtomicEvent>,
+    ) -> GameResult<FlushResult> {
+        action.flush(&mut |event| BoardEventConsumer::apply_logged(game, event, reverts))
+    }
+
+    fn apply_logged(
+        game: &mut Game,
+        event: &AtomicEvent,
+        reverts: &mut Vec<AtomicEvent>,
+    ) -> GameResult<()> {
+        reverts.extend(BoardEventConsumer::handle_event_internal(game, event)?);
+        Ok(())
+    }
+
+    /// Applies a single atomic event and updates the game's hash with the keys of the
+    /// changed feature, so applying the anti-event restores the previous hash.
+    ///
+    /// Returns the event that reverts exactly what was changed, `None` if nothing was.
+    /// Unlike the anti-event, it accounts for events that found nothing to change, e.g. a
+    /// team that already lost.
+    fn handle_event_internal(
+        game: &mut Game,
+        event: &AtomicEvent,
+    ) -> GameResult<Option<AtomicEvent>> {
         let board = &mut game.board;
 
-        match event {
+        let revert = match event {
             AtomicEvent::Place(at, piece) => {
                 board.place_piece_at(*piece, at)?;
+                game.hash ^= zobrist::piece_key(at, piece);
+                Some(AtomicEvent::Remove(*at, *piece))
             }
-            AtomicEvent::Remove(at, _) => {
-                board.remove_piece_at(at)?;
+            AtomicEvent::Remove(at, expected) => {
+                let actual = board.remove_piece_at(at)?.ok_or(GameError::NoPiece(*at))?;
+                if actual != *expected {
+                    board.place_piece_at(actual, at)?;
+                    return Err(GameError::PieceMismatch {
+                        at: *at,
+                        expected: *expected,
+                        actual,
+                    });
+                }
+                game.hash ^= zobrist::piece_key(at, &actual);
+                Some(AtomicEvent::Place(*at, actual))
             }
             AtomicEvent::AddUnusedPiece(team_id) => {
+                let before = game.num_unused_pieces_of(*team_id);
                 game.add_unused_piece_for(*team_id);
+                game.hash ^= zobrist::unused_pieces_key(*team_id, before)
+                    ^ zobrist::unused_pieces_key(*team_id, before + 1);
+                Some(AtomicEvent::RemoveUnusedPiece(*team_id))
             }
             AtomicEvent::RemoveUnusedPiece(team_id) => {
-                game.remove_unused_piece(*team_id);
+                let before = game.num_unused_pieces_of(*team_id);
+                if !game.remove_unused_piece(*team_id) {
+                    return Err(GameError::NoUnusedPiece(*team_id));
+                }
+                game.hash ^= zobrist::unused_pieces_key(*team_id, before)
+                    ^ zobrist::unused_pieces_key(*team_id, before - 1);
+                Some(AtomicEvent::AddUnusedPiece(*team_id))
             }
             AtomicEvent::ChangeExhaustion(from, to, point) => {
-                let piece = &mut board.get_piece_mut_at(point).ok_or(GameError::new(format!(
-                    "Can't execute {:?} for non-existing piece at {:?}",
-                    event, point
-                )))?;
+                let piece = &mut board
+                    .get_piece_mut_at(point)
+                    .ok_or(GameError::NoPiece(*point))?;
 
                 if from != &piece.exhaustion {
-                    return Err(GameError::new(format!(
-                        "Expected piece at {:?} to have exhaustion state {:?} but it had {:?}",
-                        point, from, piece.exhaustion
-                    )));
+                    return Err(GameError::ExhaustionMismatch {
+                        at: *point,
+                        expected: *from,
+                        actual: piece.exhaustion,
+                    });
                 }
 
+                let before = zobrist::piece_key(point, piece);
                 piece.exhaustion = *to;
+                game.hash ^= before ^ zobrist::piece_key(point, piece);
+                Some(event.anti_event())
+            }
+            AtomicEvent::AddEffect(effect, at) => {
+                let before = board.effect_count(effect, at);
+                board.add_effect(*effect, at)?;
+                game.hash ^= zobrist::effects_key(at, effect, before)
+                    ^ zobrist::effects_key(at, effect, before + 1);
+                Some(event.anti_event())
+            }
+            AtomicEvent::RemoveEffect(effect, at) => {
+                let before = board.effect_count(effect, at);
+                board.remove_effect(effect, at)?;
+                game.hash ^= zobrist::effects_key(at, effect, before)
+                    ^ zobrist::effects_key(at, effect, before - 1);
+                Some(event.anti_event())
             }
-            AtomicEvent::AddEffect(kind, at) => {
-                board.add_effect(*kind, at)?;
+            AtomicEvent::EliminateTeam(team_id) => {
+                let before = game.get_team(*team_id).lost;
+                game.mark_team_as_lost(*team_id);
+                game.hash ^=
+                    zobrist::lost_key(*team_id, before) ^ zobrist::lost_key(*team_id, true);
+                (!before).then_some(AtomicEvent::ReviveTeam(*team_id))
             }
-            AtomicEvent::RemoveEffect(kind, at) => board.remove_effect(kind, at)?,
-            AtomicEvent::NextTurn => {
-                warn!("NEXT TURN");
+            AtomicEvent::ReviveTeam(team_id) => {
+                let before = game.get_team(*team_id).lost;
+                game.revive_team(*team_id);
+                game.hash ^=
+                    zobrist::lost_key(*team_id, before) ^ zobrist::lost_key(*team_id, false);
+                before.then_some(AtomicEvent::EliminateTeam(*team_id))
+            }
+            AtomicEvent::NextTurn(team_id) => {
+                debug!("NEXT TURN");
+                if game.current_team_index != *team_id {
+                    return Err(GameError::NotTheTurnOf(*team_id));
+                }
                 game.next_team();
+                game.hash ^= zobrist::current_team_key(*team_id)
+                    ^ zobrist::current_team_key(game.current_team_index);
+                Some(AtomicEvent::PreviousTurn(*team_id))
+            }
+            AtomicEvent::PreviousTurn(team_id) => {
+                debug!("PREVIOUS TURN");
+                let before = game.current_team_index;
+                game.current_team_index = *team_id;
+                game.hash ^=
+                    zobrist::current_team_key(before) ^ zobrist::current_team_key(*team_id);
+                Some(AtomicEvent::PreviousTurn(before))
             }
+        };
+
+        Ok(revert)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game_controller::{GameCommand, GameController},
+        setup::set_up_pieces,
+    };
+    use game_model::{
+        Point2,
+        game::Team,
+        piece::{Piece, PieceKind},
+    };
+
+    fn setup_game() -> Game {
+        let teams = (0..2)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 0,
+            })
+            .collect();
+        Game::new(teams, 8, 8)
+    }
+
+    #[test]
+    fn rejected_event_is_rolled_back() {
+        let mut game = setup_game();
+        let at = Point2::new(2, 2);
+        game.board
+            .place_piece_at(Piece::new(0, PieceKind::Simple), &at)
+            .unwrap();
+
+        // Ending the turn refreshes the piece, which is gone by the time the event arrives
+        let finish_turn = GameController::next_turn(&game);
+        game.board.remove_piece_at(&at).unwrap();
+        let expected = game.clone();
+
+        let game = Rc::new(RefCell::new(game));
+        let mut consumer = BoardEventConsumer::new(Rc::clone(&game));
+
+        assert_eq!(
+            consumer.handle_event(&finish_turn),
+            Err(GameError::NoPiece(at))
+        );
+        assert_eq!(*game.borrow(), expected);
+
+        // The capture is applied before the moving piece turns out to have changed
+        let mut game = expected;
+        let to = Point2::new(2, 3);
+        let mut piece = Piece::new(0, PieceKind::Simple);
+        piece.exhaustion.reset();
+        game.board.place_piece_at(piece, &at).unwrap();
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &to)
+            .unwrap();
+        game.rehash();
+        let capture =
+            GameController::execute(&mut game.clone(), &GameCommand::MovePiece(at, to)).unwrap();
+        let stale = game.board.remove_piece_at(&at).unwrap().unwrap();
+        let actual = Piece::new(0, PieceKind::Cross);
+        game.board.place_piece_at(actual, &at).unwrap();
+        game.rehash();
+        let expected = game.clone();
+
+        let game = Rc::new(RefCell::new(game));
+        let mut consumer = BoardEventConsumer::new(Rc::clone(&game));
+
+        assert_eq!(
+            consumer.handle_event(&capture),
+            Err(GameError::PieceMismatch {
+                at,
+                expected: stale,
+                actual
+            })
+        );
+        assert_eq!(*game.borrow(), expected);
+    }
+
+    #[test]
+    fn roll_back_reverts_only_what_was_changed() {
+        let mut game = setup_game();
+        game.mark_team_as_lost(1);
+        game.add_unused_piece_for(0);
+        game.rehash();
+        let expected = game.clone();
+
+        let result = BoardEventConsumer::apply_or_roll_back(&mut game, |game, reverts| {
+            [
+                AtomicEvent::EliminateTeam(1),
+                AtomicEvent::RemoveUnusedPiece(0),
+                AtomicEvent::AddUnusedPiece(0),
+                AtomicEvent::PreviousTurn(1),
+                AtomicEvent::NextTurn(0),
+            ]
+            .iter()
+            .try_for_each(|e| BoardEventConsumer::apply_logged(game, e, reverts))
+        });
+
+        assert_eq!(result, Err(GameError::NotTheTurnOf(0)));
+        assert_eq!(game, expected);
+    }
+
+    #[test]
+    fn hash_follows_events_and_anti_events() {
+        quad_rand::srand(3);
+        let mut game = setup_game();
+        for command in set_up_pieces(2, &game.clone()).unwrap() {
+            GameController::execute(&mut game, &command).unwrap();
         }
 
-        Ok(())
+        for _ in 0..20 {
+            let before = game.clone();
+            let command = GameController::legal_commands(&game)
+                .into_iter()
+                .find(|command| *command != GameCommand::NextTurn)
+                .unwrap_or(GameCommand::NextTurn);
+            let action = GameController::execute(&mut game, &command).unwrap();
+            assert_eq!(game.hash, zobrist::hash(&game), "after {}", command);
+
+            let mut undone = game.clone();
+            BoardEventConsumer::apply(&mut undone, &action.anti_event()).unwrap();
+            assert_eq!(undone, before, "undoing {}", command);
+        }
     }
 }
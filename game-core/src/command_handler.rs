@@ -6,17 +6,44 @@ use std::{
 };
 
 use crate::{
-    game_controller::{GameCommand, GameController},
+    board_event_consumer::BoardEventConsumer,
+    game_controller::{GameCommand, GameController, MoveError},
     game_events::{Event, GameEventObject, PlayerAction},
     multiplayer_connector::MultiplayerConector,
+    notation::MoveHistory,
+    outcome::{self, OutcomeTracker},
 };
-use game_events::{event_broker::EventBroker, undo_manager::UndoManager};
-use game_model::game::Game;
+use game_events::{
+    actions::compound_events::GameAction, event_broker::EventBroker, undo_manager::UndoManager,
+};
+use game_model::{game::Game, outcome::GameOutcome};
+
+/// Where an undo or redo request of an online game stands, see
+/// [`CommandHandler::requested_command`]. Offline, undo and redo need no consent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndoRequest {
+    /// The local player asked the others and waits until all of them accepted
+    Sent,
+    /// The team with this index asked, the local player has to accept or decline
+    Received(usize),
+    /// The local player accepted the request of the team with this index, which undoes
+    /// once every other player accepted too
+    Accepted(usize),
+    /// The request of the local player was declined
+    Declined,
+}
 
 pub struct CommandHandler {
     past_commands: Arc<Mutex<Vec<GameCommand>>>,
     undo_manager: UndoManager,
+    history: MoveHistory,
     event_broker: EventBroker,
+    outcome_tracker: OutcomeTracker,
+    undo_request: Option<UndoRequest>,
+    /// Whether the pending request is about an `Undo` or a `Redo`
+    requested_command: GameCommand,
+    /// The teams that accepted the undo request of the local player so far
+    undo_accepted_by: Vec<usize>,
     pub multiplayer_connector: Option<Rc<RefCell<MultiplayerConector>>>,
 }
 
@@ -25,17 +52,135 @@ impl CommandHandler {
         CommandHandler {
             past_commands,
             undo_manager: UndoManager::new(),
+            history: MoveHistory::new(),
             event_broker,
+            outcome_tracker: OutcomeTracker::new(),
+            undo_request: None,
+            requested_command: GameCommand::Undo,
+            undo_accepted_by: vec![],
             multiplayer_connector: None,
         }
     }
 
-    pub fn handle_new_command(&mut self, game: Game, command: &GameCommand) {
-        self.handle_command_internal(game, command);
+    /// Lets `Undo` rewind past the start of the current turn, see [`UndoManager::with_turn_rewind`].
+    pub fn with_turn_rewind(mut self) -> Self {
+        self.undo_manager = self.undo_manager.with_turn_rewind();
+        self
+    }
+
+    /// Executes a command of the local player and sends it to the other players.
+    /// A rejected command changes nothing and isn't sent. Online, an `Undo` or `Redo` is
+    /// only executed once all other players accepted it, see [`Self::request_undo`].
+    pub fn handle_new_command(
+        &mut self,
+        game: Game,
+        command: &GameCommand,
+    ) -> Result<(), MoveError> {
+        if let GameCommand::Undo | GameCommand::Redo = command
+            && !self.agreed_on(&game, command)
+        {
+            return Err(MoveError::UndoNotAllowed);
+        }
+
+        self.handle_command_internal(game, command)?;
 
         if let Some(multiplayer_connector) = self.multiplayer_connector.as_mut() {
             (*multiplayer_connector).borrow_mut().handle_event(command);
         }
+
+        Ok(())
+    }
+
+    /// Undoes the last action. Online, the other players are asked first and the action
+    /// is only undone once every one of them accepted, see [`Self::answer_undo_request`].
+    pub fn request_undo(&mut self, game: Game) -> Result<(), MoveError> {
+        self.request(game, GameCommand::Undo)
+    }
+
+    /// Redoes the last undone action. Online, this needs the consent of every other player
+    /// just like [`Self::request_undo`], as they agreed to take the action back.
+    pub fn request_redo(&mut self, game: Game) -> Result<(), MoveError> {
+        self.request(game, GameCommand::Redo)
+    }
+
+    fn request(&mut self, game: Game, command: GameCommand) -> Result<(), MoveError> {
+        if !game.rules.allow_undo {
+            return Err(MoveError::UndoNotAllowed);
+        }
+        if self.outcome().is_some() {
+            return Err(MoveError::GameOver);
+        }
+
+        let Some(multiplayer_connector) = self.multiplayer_connector.as_ref() else {
+            return self.handle_new_command(game, &command);
+        };
+
+        let team_id = self.own_team_id().ok_or(MoveError::UndoNotAllowed)?;
+        let request = match command {
+            GameCommand::Redo => PlayerAction::RequestRedo(team_id),
+            _ => PlayerAction::RequestUndo(team_id),
+        };
+        (**multiplayer_connector)
+            .borrow_mut()
+            .send_player_action(request);
+        self.undo_request = Some(UndoRequest::Sent);
+        self.requested_command = command;
+        self.undo_accepted_by.clear();
+
+        self.execute_if_agreed(game)
+    }
+
+    /// Answers the undo request of another player. The requester undoes the action for all
+    /// players once everybody accepted, a single decline cancels the request.
+    pub fn answer_undo_request(&mut self, accept: bool) -> Result<(), MoveError> {
+        let Some(UndoRequest::Received(requester)) = self.undo_request else {
+            return Err(MoveError::UndoNotAllowed);
+        };
+
+        let answer = if accept {
+            PlayerAction::AcceptUndo(self.own_team_id().ok_or(MoveError::UndoNotAllowed)?)
+        } else {
+            PlayerAction::DeclineUndo
+        };
+        if let Some(multiplayer_connector) = self.multiplayer_connector.as_mut() {
+            (*multiplayer_connector)
+                .borrow_mut()
+                .send_player_action(answer);
+        }
+        self.undo_request = accept.then_some(UndoRequest::Accepted(requester));
+
+        Ok(())
+    }
+
+    /// The undo request that is waiting for an answer or was just declined, if any.
+    pub fn undo_request(&self) -> Option<UndoRequest> {
+        self.undo_request
+    }
+
+    /// Whether the [`Self::undo_request`] asks for an `Undo` or a `Redo`.
+    pub fn requested_command(&self) -> GameCommand {
+        self.requested_command
+    }
+
+    /// Whether an `Undo` would take an action back. One that wouldn't is still accepted and
+    /// changes nothing, as exported games contain such undos.
+    pub fn can_undo(&self) -> bool {
+        self.undo_manager.can_undo()
+    }
+
+    /// Whether there is an undone action for `Redo`.
+    pub fn can_redo(&self) -> bool {
+        self.undo_manager.can_redo()
+    }
+
+    /// How the game ended, `None` while it is still going on.
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        self.outcome_tracker.outcome()
+    }
+
+    /// The actions in effect, written in [`notation`](crate::notation).
+    pub fn history(&self) -> &MoveHistory {
+        &self.history
     }
 
     pub fn get_past_commands(&self) -> Vec<GameCommand> {
@@ -45,19 +190,49 @@ impl CommandHandler {
             .to_vec()
     }
 
-    pub fn handle_remote_command(&mut self, game: Game, event_object: &GameEventObject) {
+    /// Executes a command received from another player. An error means that the games
+    /// got out of sync.
+    pub fn handle_remote_command(
+        &mut self,
+        game: Game,
+        event_object: &GameEventObject,
+    ) -> Result<(), MoveError> {
         match &event_object.event {
             Event::PlayerAction(PlayerAction::Connect(_, _)) => {
                 let client = self.multiplayer_connector.take().unwrap();
-                client.borrow_mut().signal_new_game();
+                client.borrow_mut().signal_new_game(game.rules);
                 client.borrow_mut().resend_game_events();
                 let _ = self.multiplayer_connector.insert(client);
             }
+            Event::PlayerAction(PlayerAction::RequestUndo(team_id)) => {
+                self.receive_request(*team_id, GameCommand::Undo);
+            }
+            Event::PlayerAction(PlayerAction::RequestRedo(team_id)) => {
+                self.receive_request(*team_id, GameCommand::Redo);
+            }
+            // Only the requester counts the answers
+            Event::PlayerAction(PlayerAction::AcceptUndo(team_id))
+                if self.undo_request == Some(UndoRequest::Sent) =>
+            {
+                self.undo_accepted_by.push(*team_id);
+                return self.execute_if_agreed(game);
+            }
+            Event::PlayerAction(PlayerAction::DeclineUndo) => {
+                self.undo_request = match self.undo_request {
+                    Some(UndoRequest::Sent) => Some(UndoRequest::Declined),
+                    // The request is void for everybody else
+                    _ => None,
+                };
+            }
+            // Remote undos and redos were agreed on by the requester, rejecting them would
+            // desync the game
             Event::GameCommand(game_action) => {
-                self.handle_command_internal(game, game_action);
+                return self.handle_command_internal(game, game_action);
             }
             _ => {}
         }
+
+        Ok(())
     }
 
     fn log_command(&self, command: &GameCommand) {
@@ -68,23 +243,522 @@ impl CommandHandler {
         }
     }
 
-    fn handle_command_internal(&mut self, game: Game, command: &GameCommand) {
-        self.log_command(command);
+    fn handle_command_internal(
+        &mut self,
+        mut game: Game,
+        command: &GameCommand,
+    ) -> Result<(), MoveError> {
+        let was_decided = self.outcome_tracker.outcome().is_some();
+
+        // The GameOver action isn't undoable, undoing past it would leave the losers lost
+        if let GameCommand::Undo | GameCommand::Redo = command
+            && was_decided
+        {
+            return Err(MoveError::GameOver);
+        }
 
+        // Undo and redo are checked on the game copy before any subscriber sees them, like
+        // the actions GameController::execute builds, so a rejected one changes nothing.
+        // Either way the action is kept where it was, so it can be retried.
         if let GameCommand::Undo = command {
             if let Some(anti_event) = self.undo_manager.undo() {
-                self.event_broker.dispatch(&anti_event);
+                let applied = BoardEventConsumer::apply(&mut game, &anti_event)
+                    .and_then(|()| self.event_broker.dispatch(&anti_event));
+                if let Err(e) = applied {
+                    self.undo_manager.redo();
+                    return Err(e.into());
+                }
+
+                self.outcome_tracker.record(&game, &anti_event);
+                self.history.undo();
+            }
+        } else if let GameCommand::Redo = command {
+            if let Some(action) = self.undo_manager.redo() {
+                let applied = BoardEventConsumer::apply(&mut game, &action)
+                    .and_then(|()| self.event_broker.dispatch(&action));
+                if let Err(e) = applied {
+                    self.undo_manager.undo();
+                    return Err(e.into());
+                }
+
+                self.outcome_tracker.record(&game, &action);
+                self.history.redo();
+
+                if let GameAction::FinishTurn(_) = action {
+                    self.undo_manager.mark_turn_boundary();
+                }
             }
         } else {
-            let action = GameController::handle_command(game, command)
-                .unwrap_or_else(|e| panic!("Could not handle command {:?}: {:?}", command, e));
+            let action = GameController::execute(&mut game, command)?;
 
-            self.undo_manager.push(action.clone());
-            self.event_broker.dispatch(&action);
+            self.event_broker.dispatch(&action)?;
+            self.outcome_tracker.record(&game, &action);
+            self.history.push(command, &action);
+            self.undo_manager.push(action);
 
             if let GameCommand::NextTurn = command {
                 self.undo_manager.mark_turn_boundary();
             }
         }
+
+        self.log_command(command);
+        // A request that wasn't answered before the game went on is void
+        self.undo_request = None;
+        self.undo_accepted_by.clear();
+
+        self.end_game_if_decided(&game, was_decided)
+    }
+
+    fn receive_request(&mut self, team_id: usize, command: GameCommand) {
+        self.undo_request = Some(UndoRequest::Received(team_id));
+        self.requested_command = command;
+        self.undo_accepted_by.clear();
+    }
+
+    /// Whether the rules allow undo and, online, every other team that is still playing
+    /// accepted the local player's request for this `Undo` or `Redo`.
+    fn agreed_on(&self, game: &Game, command: &GameCommand) -> bool {
+        if !game.rules.allow_undo {
+            return false;
+        }
+        if self.multiplayer_connector.is_none() {
+            return true;
+        }
+
+        let own_team_id = self.own_team_id();
+        self.undo_request == Some(UndoRequest::Sent)
+            && self.requested_command == *command
+            && game
+                .teams
+                .iter()
+                .filter(|team| !team.lost && Some(team.id) != own_team_id)
+                .all(|team| self.undo_accepted_by.contains(&team.id))
+    }
+
+    /// Executes the requested undo or redo once everybody agreed to it.
+    fn execute_if_agreed(&mut self, game: Game) -> Result<(), MoveError> {
+        let command = self.requested_command;
+        if self.agreed_on(&game, &command) {
+            return self.handle_new_command(game, &command);
+        }
+
+        Ok(())
+    }
+
+    fn own_team_id(&self) -> Option<usize> {
+        (**self.multiplayer_connector.as_ref()?)
+            .borrow()
+            .get_own_player_index()
+    }
+
+    /// Sends a `GameOver` action once the outcome is known, so the consumers only have
+    /// to react to it.
+    fn end_game_if_decided(&mut self, game: &Game, was_decided: bool) -> Result<(), MoveError> {
+        if let Some(outcome) = self.outcome_tracker.outcome()
+            && !was_decided
+        {
+            self.event_broker
+                .dispatch(&outcome::game_over(game, outcome))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multiplayer_connector::MultiplayerClient;
+    use game_model::{
+        Point2,
+        game::Team,
+        piece::{Piece, PieceKind},
+    };
+    use std::collections::{HashMap, VecDeque};
+
+    type Inboxes = Rc<RefCell<HashMap<String, VecDeque<GameEventObject>>>>;
+
+    /// A peer of an in-memory network that delivers events on [`deliver_all`].
+    struct TestClient {
+        id: String,
+        inboxes: Inboxes,
+    }
+
+    impl MultiplayerClient for TestClient {
+        fn is_ready(&self) -> bool {
+            true
+        }
+
+        fn accept_new_connections(&mut self) -> Vec<String> {
+            let inboxes = self.inboxes.borrow();
+            let mut peers: Vec<String> = inboxes
+                .keys()
+                .filter(|id| **id != self.id)
+                .cloned()
+                .collect();
+            peers.sort();
+            peers
+        }
+
+        fn recieved_events(&mut self) -> Vec<GameEventObject> {
+            self.inboxes
+                .borrow_mut()
+                .get_mut(&self.id)
+                .map(|inbox| inbox.drain(..).collect())
+                .unwrap_or_default()
+        }
+
+        fn send(&mut self, game_object: &GameEventObject, opponent_id: &str) {
+            if let Some(inbox) = self.inboxes.borrow_mut().get_mut(opponent_id) {
+                inbox.push_back(game_object.clone());
+            }
+        }
+
+        fn own_player_id(&self) -> Option<String> {
+            Some(self.id.clone())
+        }
+    }
+
+    /// Handlers of an online game whose teams are all played on their own device.
+    fn setup_online(num_teams: usize) -> Vec<(Rc<RefCell<Game>>, CommandHandler)> {
+        let inboxes: Inboxes = Rc::new(RefCell::new(
+            (0..num_teams)
+                .map(|id| (id.to_string(), VecDeque::new()))
+                .collect(),
+        ));
+
+        (0..num_teams)
+            .map(|id| {
+                let (game, mut handler) = setup();
+                let teams = (0..num_teams)
+                    .map(|id| Team {
+                        id,
+                        lost: false,
+                        unused_pieces: 3,
+                    })
+                    .collect();
+                *game.borrow_mut() = Game::new(teams, 8, 8);
+
+                let mut connector = MultiplayerConector::new(Box::new(TestClient {
+                    id: id.to_string(),
+                    inboxes: inboxes.clone(),
+                }))
+                .with_num_players(num_teams);
+                connector.matchmaking();
+                connector.override_own_player_index = Some(id);
+                handler.multiplayer_connector = Some(Rc::new(RefCell::new(connector)));
+
+                (game, handler)
+            })
+            .collect()
+    }
+
+    /// Delivers events until nobody sends anything new. Every remote command must be accepted.
+    fn deliver_all(peers: &mut [(Rc<RefCell<Game>>, CommandHandler)]) {
+        loop {
+            let mut delivered = false;
+            for (game, handler) in peers.iter_mut() {
+                let events = (**handler.multiplayer_connector.as_ref().unwrap())
+                    .borrow_mut()
+                    .try_recieve();
+                for event in events {
+                    delivered = true;
+                    let game_clone = game.borrow().clone();
+                    handler.handle_remote_command(game_clone, &event).unwrap();
+                }
+            }
+            if !delivered {
+                return;
+            }
+        }
+    }
+
+    fn setup() -> (Rc<RefCell<Game>>, CommandHandler) {
+        let teams = (0..2)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 3,
+            })
+            .collect();
+        let game = Rc::new(RefCell::new(Game::new(teams, 8, 8)));
+
+        let mut event_broker = EventBroker::new();
+        event_broker.subscribe(Box::new(BoardEventConsumer::new(Rc::clone(&game))));
+        let command_handler = CommandHandler::new(event_broker, Arc::new(Mutex::new(vec![])));
+
+        (game, command_handler)
+    }
+
+    fn handle(game: &Rc<RefCell<Game>>, handler: &mut CommandHandler, command: GameCommand) {
+        let game_clone = game.borrow().clone();
+        handler.handle_new_command(game_clone, &command).unwrap();
+    }
+
+    #[test]
+    fn redo_restores_undone_actions_until_a_new_command() {
+        let (game, mut handler) = setup();
+        handle(
+            &game,
+            &mut handler,
+            GameCommand::PlacePiece(Point2::new(1, 1)),
+        );
+        handle(
+            &game,
+            &mut handler,
+            GameCommand::PlacePiece(Point2::new(5, 5)),
+        );
+        let placed = game.borrow().clone();
+
+        handle(&game, &mut handler, GameCommand::Undo);
+        handle(&game, &mut handler, GameCommand::Undo);
+        assert!(game.borrow().board.placed_pieces(0).is_empty());
+
+        handle(&game, &mut handler, GameCommand::Redo);
+        handle(&game, &mut handler, GameCommand::Redo);
+        assert_eq!(*game.borrow(), placed);
+
+        handle(&game, &mut handler, GameCommand::Undo);
+        handle(
+            &game,
+            &mut handler,
+            GameCommand::PlacePiece(Point2::new(6, 6)),
+        );
+        let after_new_command = game.borrow().clone();
+        handle(&game, &mut handler, GameCommand::Redo);
+        assert_eq!(
+            *game.borrow(),
+            after_new_command,
+            "a new command clears the redo stack"
+        );
+    }
+
+    #[test]
+    fn rejected_undos_change_nothing() {
+        let (game, mut handler) = setup();
+        handle(
+            &game,
+            &mut handler,
+            GameCommand::PlacePiece(Point2::new(1, 1)),
+        );
+        let placed = game.borrow().clone();
+
+        // A copy that is out of sync has no piece for the undo to take back
+        let mut out_of_sync = placed.clone();
+        out_of_sync
+            .board
+            .remove_piece_at(&Point2::new(1, 1))
+            .unwrap();
+        assert!(
+            handler
+                .handle_new_command(out_of_sync, &GameCommand::Undo)
+                .is_err()
+        );
+        assert_eq!(*game.borrow(), placed);
+
+        handle(&game, &mut handler, GameCommand::Undo);
+        assert!(game.borrow().board.placed_pieces(0).is_empty());
+    }
+
+    #[test]
+    fn new_games_carry_the_rules_of_their_initiator() {
+        let mut peers = setup_online(2);
+        peers[0].0.borrow_mut().rules.allow_undo = false;
+        let connector =
+            |handler: &CommandHandler| Rc::clone(handler.multiplayer_connector.as_ref().unwrap());
+
+        connector(&peers[1].1).borrow_mut().signal_connect();
+        let (game, handler) = &mut peers[0];
+        let events = connector(handler).borrow_mut().try_recieve();
+        for event in events {
+            let game_clone = game.borrow().clone();
+            handler.handle_remote_command(game_clone, &event).unwrap();
+        }
+
+        let events = connector(&peers[1].1).borrow_mut().try_recieve();
+        assert!(events.iter().any(|e| matches!(
+            &e.event,
+            Event::PlayerAction(PlayerAction::NewGame(_, rules)) if !rules.allow_undo
+        )));
+    }
+
+    #[test]
+    fn undo_needs_the_consent_of_every_other_player() {
+        let mut peers = setup_online(3);
+        let (game, handler) = &mut peers[0];
+        handle(game, handler, GameCommand::PlacePiece(Point2::new(1, 1)));
+        deliver_all(&mut peers);
+
+        // B declines while C accepts before either answer arrived
+        let game_clone = peers[0].0.borrow().clone();
+        peers[0].1.request_undo(game_clone).unwrap();
+        deliver_all(&mut peers);
+        peers[1].1.answer_undo_request(false).unwrap();
+        peers[2].1.answer_undo_request(true).unwrap();
+        deliver_all(&mut peers);
+
+        assert_eq!(peers[0].1.undo_request(), Some(UndoRequest::Declined));
+        for (game, handler) in &peers {
+            assert_eq!(game.borrow().board.placed_pieces(0).len(), 1);
+            assert_ne!(handler.undo_request(), Some(UndoRequest::Accepted(0)));
+        }
+
+        let game_clone = peers[0].0.borrow().clone();
+        peers[0].1.request_undo(game_clone).unwrap();
+        deliver_all(&mut peers);
+        peers[1].1.answer_undo_request(true).unwrap();
+        deliver_all(&mut peers);
+        assert_eq!(
+            peers[0].0.borrow().board.placed_pieces(0).len(),
+            1,
+            "one of two players accepted"
+        );
+
+        peers[2].1.answer_undo_request(true).unwrap();
+        deliver_all(&mut peers);
+        for (game, handler) in &peers {
+            assert!(game.borrow().board.placed_pieces(0).is_empty());
+            assert_eq!(game.borrow().hash, peers[0].0.borrow().hash);
+            assert_eq!(handler.undo_request(), None);
+        }
+    }
+
+    #[test]
+    fn redo_needs_the_consent_of_every_other_player() {
+        let mut peers = setup_online(2);
+        let (game, handler) = &mut peers[0];
+        handle(game, handler, GameCommand::PlacePiece(Point2::new(1, 1)));
+        deliver_all(&mut peers);
+        let game_clone = peers[0].0.borrow().clone();
+        peers[0].1.request_undo(game_clone).unwrap();
+        deliver_all(&mut peers);
+        peers[1].1.answer_undo_request(true).unwrap();
+        deliver_all(&mut peers);
+
+        let game_clone = peers[0].0.borrow().clone();
+        assert!(matches!(
+            peers[0]
+                .1
+                .handle_new_command(game_clone, &GameCommand::Redo),
+            Err(MoveError::UndoNotAllowed)
+        ));
+
+        let game_clone = peers[0].0.borrow().clone();
+        peers[0].1.request_redo(game_clone).unwrap();
+        deliver_all(&mut peers);
+        assert_eq!(peers[1].1.undo_request(), Some(UndoRequest::Received(0)));
+        assert_eq!(peers[1].1.requested_command(), GameCommand::Redo);
+        for (game, _) in &peers {
+            assert!(game.borrow().board.placed_pieces(0).is_empty());
+        }
+
+        peers[1].1.answer_undo_request(true).unwrap();
+        deliver_all(&mut peers);
+        for (game, handler) in &peers {
+            assert_eq!(game.borrow().board.placed_pieces(0).len(), 1);
+            assert_eq!(game.borrow().hash, peers[0].0.borrow().hash);
+            assert_eq!(handler.undo_request(), None);
+        }
+    }
+
+    #[test]
+    fn house_rules_can_switch_off_undo() {
+        let (game, mut handler) = setup();
+        game.borrow_mut().rules.allow_undo = false;
+        handle(
+            &game,
+            &mut handler,
+            GameCommand::PlacePiece(Point2::new(1, 1)),
+        );
+
+        let game_clone = game.borrow().clone();
+        assert!(matches!(
+            handler.request_undo(game_clone),
+            Err(MoveError::UndoNotAllowed)
+        ));
+        assert_eq!(game.borrow().board.placed_pieces(0).len(), 1);
+    }
+
+    #[test]
+    fn decided_game_can_not_be_undone() {
+        let (game, handler) = setup();
+        let mut handler = handler.with_turn_rewind();
+        let (from, to) = (Point2::new(1, 1), Point2::new(1, 2));
+        {
+            let mut game = game.borrow_mut();
+            let mut piece = Piece::new(0, PieceKind::Simple);
+            piece.exhaustion.reset();
+            game.board.place_piece_at(piece, &from).unwrap();
+            game.board
+                .place_piece_at(Piece::new(1, PieceKind::Simple), &to)
+                .unwrap();
+            game.teams[1].unused_pieces = 0;
+            game.rehash();
+        }
+
+        handle(&game, &mut handler, GameCommand::MovePiece(from, to));
+        let decided = game.borrow().clone();
+        assert_eq!(handler.outcome().and_then(|o| o.winner()), Some(0));
+
+        for command in [GameCommand::Undo, GameCommand::Redo] {
+            let game_clone = game.borrow().clone();
+            assert!(matches!(
+                handler.handle_new_command(game_clone, &command),
+                Err(MoveError::GameOver)
+            ));
+        }
+        let game_clone = game.borrow().clone();
+        assert!(matches!(
+            handler.request_undo(game_clone),
+            Err(MoveError::GameOver)
+        ));
+        assert_eq!(*game.borrow(), decided);
+        assert!(game.borrow().teams[1].lost);
+    }
+
+    #[test]
+    fn turn_rewind_undoes_and_redoes_finished_turns() {
+        let (game, handler) = setup();
+        let mut handler = handler.with_turn_rewind();
+        let start = game.borrow().clone();
+        handle(
+            &game,
+            &mut handler,
+            GameCommand::PlacePiece(Point2::new(1, 1)),
+        );
+        handle(&game, &mut handler, GameCommand::NextTurn);
+        let next_turn = game.borrow().clone();
+        assert_eq!(next_turn.current_team_index, 1);
+
+        handle(&game, &mut handler, GameCommand::Undo);
+        handle(&game, &mut handler, GameCommand::Undo);
+        assert_eq!(*game.borrow(), start);
+        assert!(handler.history().lines().is_empty());
+
+        handle(&game, &mut handler, GameCommand::Redo);
+        handle(&game, &mut handler, GameCommand::Redo);
+        assert_eq!(*game.borrow(), next_turn);
+        assert_eq!(handler.history().lines(), ["1. P b2 /"]);
+    }
+
+    #[test]
+    fn undo_and_redo_tell_whether_they_have_an_action() {
+        let (game, mut handler) = setup();
+        assert!(!handler.can_undo() && !handler.can_redo());
+
+        handle(
+            &game,
+            &mut handler,
+            GameCommand::PlacePiece(Point2::new(1, 1)),
+        );
+        assert!(handler.can_undo());
+
+        handle(&game, &mut handler, GameCommand::Undo);
+        assert!(!handler.can_undo() && handler.can_redo());
+
+        handle(&game, &mut handler, GameCommand::Redo);
+        handle(&game, &mut handler, GameCommand::NextTurn);
+        assert!(!handler.can_undo(), "undo stops at the turn boundary");
+        assert!(handler.with_turn_rewind().can_undo());
     }
 }
@@ -1,6 +1,7 @@
 use game_model::{
     Point2,
     game::Game,
+    outcome::GameOutcome,
     piece::{Piece, Power},
 };
 
@@ -8,13 +9,15 @@ use crate::{
     command_handler::CommandHandler,
     game_controller::{GameCommand, GameController, MoveError},
 };
+use log::error;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CoreGameSubstate {
     Place,
     Move(Point2),
     Activate(Point2),
-    Won(usize),
+    /// The game was won or drawn, nothing can be clicked anymore
+    Over(GameOutcome),
     Wait,
 }
 
@@ -35,7 +38,7 @@ impl CoreGameSubstate {
                 let place_command = GameCommand::PlacePiece(*target_point);
                 match GameController::handle_command(game_clone.clone(), &place_command) {
                     Ok(_event) => {
-                        command_handler.handle_new_command(game_clone, &place_command);
+                        handle_or_log(command_handler, game_clone, &place_command);
                     }
                     Err(MoveError::PieceAlreadyPresent(target_piece)) => {
                         if target_piece.team_id == game_clone.current_team_index {
@@ -50,7 +53,7 @@ impl CoreGameSubstate {
                         return CoreGameSubstate::Place;
                     }
                     Err(error) => {
-                        panic!("Unexpected error {:?}", error)
+                        error!("Unexpected error {}", error)
                     }
                 };
             }
@@ -68,12 +71,12 @@ impl CoreGameSubstate {
                                     game_clone.clone(),
                                     &blast_command,
                                 ) {
-                                    command_handler.handle_new_command(game_clone, &blast_command);
+                                    handle_or_log(command_handler, game_clone, &blast_command);
                                 }
 
                                 CoreGameSubstate::Place
                             }
-                            Power::TargetedShoot => CoreGameSubstate::Activate(*target_point),
+                            _ => CoreGameSubstate::Activate(*target_point),
                         };
                     }
                     if target_piece.team_id == game_clone.current_team_index
@@ -86,17 +89,26 @@ impl CoreGameSubstate {
                 let move_command = GameCommand::MovePiece(*itself, *target_point);
 
                 if GameController::handle_command(game_clone.clone(), &move_command).is_ok() {
-                    command_handler.handle_new_command(game_clone, &move_command);
+                    handle_or_log(command_handler, game_clone, &move_command);
                 }
             }
             CoreGameSubstate::Activate(active_piece_pos) => {
-                let shoot_command = GameCommand::TargetedShoot(*active_piece_pos, *target_point);
-                if GameController::handle_command(game_clone.clone(), &shoot_command).is_ok() {
-                    command_handler.handle_new_command(game_clone, &shoot_command);
+                let shooting = board
+                    .get_piece_at(active_piece_pos)
+                    .and_then(|piece| piece.activatable)
+                    .is_some_and(|activatable| activatable.kind == Power::TargetedShoot);
+                let power_command = if shooting {
+                    GameCommand::TargetedShoot(*active_piece_pos, *target_point)
+                } else {
+                    GameCommand::UsePower(*active_piece_pos, *target_point)
+                };
+
+                if GameController::handle_command(game_clone.clone(), &power_command).is_ok() {
+                    handle_or_log(command_handler, game_clone, &power_command);
                 }
             }
-            CoreGameSubstate::Won(team) => {
-                return CoreGameSubstate::Won(*team);
+            CoreGameSubstate::Over(outcome) => {
+                return CoreGameSubstate::Over(*outcome);
             }
             CoreGameSubstate::Wait => return CoreGameSubstate::Wait,
         }
@@ -105,6 +117,14 @@ impl CoreGameSubstate {
     }
 }
 
+/// Executes an already validated command. It can still be rejected if the
+/// game state is inconsistent, which is logged rather than crashing the game.
+fn handle_or_log(command_handler: &mut CommandHandler, game: Game, command: &GameCommand) {
+    if let Err(e) = command_handler.handle_new_command(game, command) {
+        error!("Could not handle command {}: {}", command, e);
+    }
+}
+
 fn can_blast(piece: &Piece) -> bool {
     if let Some(activatable) = piece.activatable {
         return piece.can_use_special() && activatable.kind == Power::Blast;
@@ -123,6 +143,7 @@ mod tests {
     };
 
     use game_model::{
+        GameResult,
         game::{Game, Team},
         piece::PieceKind,
     };
@@ -181,8 +202,9 @@ mod tests {
     }
 
     impl EventConsumer for EventLogger {
-        fn handle_event(&mut self, event: &GameAction) {
+        fn handle_event(&mut self, event: &GameAction) -> GameResult<()> {
             (*self.events).borrow_mut().push_back(event.clone());
+            Ok(())
         }
     }
 }
@@ -1,3 +1,4 @@
+use indexmap::IndexSet;
 use nanoserde::{DeJson, SerJson};
 use std::{
     collections::HashSet,
@@ -5,10 +6,10 @@ use std::{
 };
 
 use game_model::{
-    Point2,
+    GameError, Point2,
     board::Board,
     game::Game,
-    piece::{Piece, PieceKind},
+    piece::{CellEffect, EffectKind, Piece, PieceKind, Power},
 };
 
 use crate::board_event_consumer::BoardEventConsumer;
@@ -21,26 +22,65 @@ use game_events::actions::{
 };
 use game_model::pattern::Pattern;
 
-pub(crate) struct GameController {}
+pub struct GameController {}
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub enum MoveError {
     PieceAlreadyPresent(Piece),
     NoPiecePresent,
     NotSupportedByPiece,
     NoPieceAvailable,
     IllegalMove,
+    /// Undo is switched off by the rules, or the other players didn't agree to it
+    UndoNotAllowed,
+    /// `Undo` and `Redo` replay the history, so only a `CommandHandler` executes them
+    NotAMove,
+    /// The game has an outcome, so its history can't be undone or redone any more
+    GameOver,
+    /// The model rejected one of the command's events, e.g. because the game got out of sync
+    Rejected(GameError),
 }
 
-#[derive(Debug, Copy, Clone, SerJson, DeJson)]
+impl Display for MoveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::PieceAlreadyPresent(piece) => {
+                write!(f, "There already is a {:?}", piece.piece_kind)
+            }
+            MoveError::NoPiecePresent => write!(f, "There is no piece"),
+            MoveError::NotSupportedByPiece => write!(f, "The piece can't do that"),
+            MoveError::NoPieceAvailable => write!(f, "No unused piece left"),
+            MoveError::IllegalMove => write!(f, "Illegal move"),
+            MoveError::UndoNotAllowed => write!(f, "Undo is not allowed"),
+            MoveError::NotAMove => write!(f, "Undo and redo need the game's history"),
+            MoveError::GameOver => write!(f, "The game is over"),
+            MoveError::Rejected(e) => write!(f, "Rejected: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+impl From<GameError> for MoveError {
+    fn from(error: GameError) -> Self {
+        MoveError::Rejected(error)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
 pub enum GameCommand {
     InitPlayer(u8),
     PlacePiece(Point2),
     MovePiece(Point2, Point2),
     Blast(Point2),
     TargetedShoot(Point2, Point2),
+    /// Uses the power of the piece at the first point on the second one. Works for every
+    /// power, a blast targets the blasting piece itself.
+    UsePower(Point2, Point2),
     NextTurn,
     Undo,
+    /// Executes the last undone action again
+    Redo,
 }
 
 impl Display for GameCommand {
@@ -51,8 +91,10 @@ impl Display for GameCommand {
             GameCommand::MovePiece(from, to) => write!(f, "Move{}{}", from, to),
             GameCommand::Blast(at) => write!(f, "Blast{}", at),
             GameCommand::TargetedShoot(from, to) => write!(f, "Shoot{}{}", from, to),
+            GameCommand::UsePower(from, to) => write!(f, "Power{}{}", from, to),
             GameCommand::NextTurn => write!(f, "NextTurn"),
             GameCommand::Undo => write!(f, "Undo"),
+            GameCommand::Redo => write!(f, "Redo"),
         }
     }
 }
@@ -61,9 +103,19 @@ type MoveResult = Result<GameAction, MoveError>;
 
 impl GameController {
     pub fn handle_command(mut game: Game, command: &GameCommand) -> MoveResult {
-        let game = &mut game;
+        Self::execute(&mut game, command)
+    }
+
+    /// Like [`Self::handle_command`], but leaves `game` in the state after the command,
+    /// e.g. to look ahead without going through a [`CommandHandler`](crate::command_handler::CommandHandler).
+    /// A rejected command leaves `game` untouched.
+    pub fn execute(game: &mut Game, command: &GameCommand) -> MoveResult {
         match command {
-            GameCommand::InitPlayer(add_unused) => Self::init_player(game, *add_unused),
+            GameCommand::InitPlayer(add_unused) => {
+                let action = Self::init_player(game, *add_unused)?;
+                BoardEventConsumer::apply(game, &action)?;
+                Ok(action)
+            }
             GameCommand::PlacePiece(pos) => Self::place_piece(game, pos),
             GameCommand::MovePiece(from, target_point) => {
                 Self::move_piece(game, from, target_point)
@@ -72,8 +124,15 @@ impl GameController {
             GameCommand::TargetedShoot(attacking_piece_pos, target_pos) => {
                 Self::targeted_shoot(game, attacking_piece_pos, target_pos)
             }
-            GameCommand::NextTurn => Ok(Self::next_turn(game)),
-            GameCommand::Undo => todo!(),
+            GameCommand::UsePower(piece_pos, target_pos) => {
+                Self::use_power(game, piece_pos, target_pos)
+            }
+            GameCommand::NextTurn => {
+                let action = Self::next_turn(game);
+                BoardEventConsumer::apply(game, &action)?;
+                Ok(action)
+            }
+            GameCommand::Undo | GameCommand::Redo => Err(MoveError::NotAMove),
         }
     }
 
@@ -85,7 +144,7 @@ impl GameController {
             return Err(MoveError::IllegalMove);
         }
 
-        let mut builder = GameAction::finish_turn();
+        let mut builder = GameAction::finish_turn(game.current_team_index);
         for _ in 0..add_unused {
             builder.add_unused_piece(game.current_team_index);
         }
@@ -94,35 +153,281 @@ impl GameController {
     }
 
     pub fn place_piece(game: &mut Game, pos: &Point2) -> MoveResult {
-        if let Some(target_piece) = game.board.get_piece_at(pos) {
-            return MoveResult::Err(MoveError::PieceAlreadyPresent(*target_piece));
-        }
+        Self::check_place(game, pos)?;
 
-        if !game.unused_piece_available() {
-            return MoveResult::Err(MoveError::NoPieceAvailable);
-        }
-
-        let new_piece = Piece::new(game.current_team_index, PieceKind::Simple);
+        let new_piece = game.new_piece(game.current_team_index, PieceKind::Simple);
         let mut place_event = GameAction::place(*pos, new_piece, game.current_team_index);
 
         push_effects_if_present(&mut place_event, &game.board, &new_piece, pos);
 
-        MoveResult::Ok(flush_and_merge(game, Box::new(place_event)))
+        flush_and_merge(game, Box::new(place_event))
     }
 
     pub fn move_piece(game: &mut Game, from: &Point2, target_point: &Point2) -> MoveResult {
-        let selected_piece = game
+        let selected_piece = Self::check_move(game, from, target_point)?;
+
+        let mut move_event = MoveBuilder::new(*from, *target_point, selected_piece);
+
+        if let Some(target_piece) = game.board.get_piece_at(target_point) {
+            move_event.remove_piece(*target_piece);
+
+            for team_id in defeated_teams(&game.board, &[*target_piece]) {
+                move_event.eliminate_team(team_id);
+            }
+
+            remove_effects_if_present(&mut move_event, &game.board, target_piece, target_point);
+        }
+
+        flush_and_merge(game, Box::new(move_event))
+    }
+
+    pub fn blast(game: &mut Game, piece_pos: &Point2) -> MoveResult {
+        let (attacking_piece, reachable_points) = Self::check_blast(game, piece_pos)?;
+
+        let mut attack_event = AttackBuilder::new(&attacking_piece, *piece_pos, Power::Blast);
+
+        let mut removed_pieces = vec![];
+        for point in reachable_points {
+            if let Some(piece) = game.board.get_piece_at(&point) {
+                attack_event.remove_piece(point, *piece);
+                remove_effects_if_present(&mut attack_event, &game.board, piece, &point);
+                removed_pieces.push(*piece);
+            }
+        }
+
+        for team_id in defeated_teams(&game.board, &removed_pieces) {
+            attack_event.eliminate_team(team_id);
+        }
+
+        flush_and_merge(game, Box::new(attack_event))
+    }
+
+    pub fn targeted_shoot(
+        game: &mut Game,
+        attacking_piece_pos: &Point2,
+        target_pos: &Point2,
+    ) -> MoveResult {
+        let (active_piece, target_piece) =
+            Self::check_targeted_shoot(game, attacking_piece_pos, target_pos)?;
+
+        let mut attack_event =
+            AttackBuilder::new(&active_piece, *attacking_piece_pos, Power::TargetedShoot);
+        attack_event.remove_piece(*target_pos, target_piece);
+
+        remove_effects_if_present(&mut attack_event, &game.board, &target_piece, target_pos);
+
+        for team_id in defeated_teams(&game.board, &[target_piece]) {
+            attack_event.eliminate_team(team_id);
+        }
+
+        flush_and_merge(game, Box::new(attack_event))
+    }
+
+    /// Uses whatever power the piece at `piece_pos` has on `target_pos`.
+    pub fn use_power(game: &mut Game, piece_pos: &Point2, target_pos: &Point2) -> MoveResult {
+        let power = Self::own_piece_at(game, piece_pos)?
+            .activatable
+            .ok_or(MoveError::NotSupportedByPiece)?
+            .kind;
+
+        match power {
+            Power::Blast if piece_pos == target_pos => Self::blast(game, piece_pos),
+            Power::Blast => Err(MoveError::IllegalMove),
+            Power::TargetedShoot => Self::targeted_shoot(game, piece_pos, target_pos),
+            Power::Push => Self::push(game, piece_pos, target_pos),
+            Power::Swap => Self::swap(game, piece_pos, target_pos),
+            Power::Convert => Self::convert(game, piece_pos, target_pos),
+            Power::Enchant => Self::enchant(game, piece_pos, target_pos),
+        }
+    }
+
+    fn push(game: &mut Game, piece_pos: &Point2, target_pos: &Point2) -> MoveResult {
+        let (active_piece, target_piece) =
+            Self::check_power(game, Power::Push, piece_pos, target_pos)?;
+        let destination = push_destination(game, piece_pos, target_pos)?;
+
+        let mut attack_event = AttackBuilder::new(&active_piece, *piece_pos, Power::Push);
+        attack_event.displace_piece(*target_pos, target_piece, destination, target_piece);
+
+        remove_effects_if_present(&mut attack_event, &game.board, &target_piece, target_pos);
+        push_effects_if_present(&mut attack_event, &game.board, &target_piece, &destination);
+
+        flush_and_merge(game, Box::new(attack_event))
+    }
+
+    fn swap(game: &mut Game, piece_pos: &Point2, target_pos: &Point2) -> MoveResult {
+        let (active_piece, target_piece) =
+            Self::check_power(game, Power::Swap, piece_pos, target_pos)?;
+
+        let mut exhausted_piece = active_piece;
+        exhausted_piece.exhaustion.on_attack();
+
+        let mut attack_event = AttackBuilder::new(&active_piece, *piece_pos, Power::Swap);
+        attack_event
+            .displace_piece(*piece_pos, exhausted_piece, *target_pos, exhausted_piece)
+            .displace_piece(*target_pos, target_piece, *piece_pos, target_piece);
+
+        remove_effects_if_present(&mut attack_event, &game.board, &active_piece, piece_pos);
+        remove_effects_if_present(&mut attack_event, &game.board, &target_piece, target_pos);
+        push_effects_if_present(&mut attack_event, &game.board, &active_piece, target_pos);
+        push_effects_if_present(&mut attack_event, &game.board, &target_piece, piece_pos);
+
+        flush_and_merge(game, Box::new(attack_event))
+    }
+
+    fn convert(game: &mut Game, piece_pos: &Point2, target_pos: &Point2) -> MoveResult {
+        let (active_piece, target_piece) =
+            Self::check_power(game, Power::Convert, piece_pos, target_pos)?;
+
+        let mut converted_piece = target_piece;
+        converted_piece.team_id = active_piece.team_id;
+
+        let mut attack_event = AttackBuilder::new(&active_piece, *piece_pos, Power::Convert);
+        attack_event.displace_piece(*target_pos, target_piece, *target_pos, converted_piece);
+
+        for team_id in defeated_teams(&game.board, &[target_piece]) {
+            attack_event.eliminate_team(team_id);
+        }
+
+        flush_and_merge(game, Box::new(attack_event))
+    }
+
+    fn enchant(game: &mut Game, piece_pos: &Point2, target_pos: &Point2) -> MoveResult {
+        let (active_piece, _) = Self::check_power(game, Power::Enchant, piece_pos, target_pos)?;
+        let effect = active_piece
+            .activatable
+            .and_then(|a| a.effect)
+            .ok_or(MoveError::NotSupportedByPiece)?;
+
+        let mut attack_event = AttackBuilder::new(&active_piece, *piece_pos, Power::Enchant);
+        attack_event.add_effect(*target_pos, effect);
+
+        flush_and_merge(game, Box::new(attack_event))
+    }
+
+    /// Every command the current team can issue right now: exactly the place, move,
+    /// blast, targeted shoot and power commands [`Self::handle_command`] accepts, plus
+    /// `NextTurn`. Blasts and shots are listed with their own commands rather than `UsePower`.
+    pub fn legal_commands(game: &Game) -> Vec<GameCommand> {
+        let mut commands = vec![];
+        Self::find_legal_command(game, |command| {
+            commands.push(command);
+            false
+        });
+        commands.push(GameCommand::NextTurn);
+
+        commands
+    }
+
+    /// Whether the current team can issue any command but `NextTurn`. Unlike
+    /// [`Self::legal_commands`], this stops at the first legal command.
+    pub fn has_legal_command(game: &Game) -> bool {
+        Self::find_legal_command(game, |_| true)
+    }
+
+    /// Passes the legal commands but `NextTurn` to `found` in the order of
+    /// [`Self::legal_commands`], until `found` returns true.
+    fn find_legal_command(game: &Game, mut found: impl FnMut(GameCommand) -> bool) -> bool {
+        let points =
+            || (0..game.board.w).flat_map(|x| (0..game.board.h).map(move |y| Point2::new(x, y)));
+
+        for point in points() {
+            if Self::check_place(game, &point).is_ok() && found(GameCommand::PlacePiece(point)) {
+                return true;
+            }
+        }
+
+        for point in points() {
+            let Some(piece) = game.board.get_piece_at(&point) else {
+                continue;
+            };
+            if piece.team_id != game.current_team_index {
+                continue;
+            }
+
+            if let Some(movement) = piece.movement {
+                for target in movement
+                    .range
+                    .reachable_points_for_piece(&point, piece, &game.board)
+                {
+                    if Self::check_move(game, &point, &target).is_ok()
+                        && found(GameCommand::MovePiece(point, target))
+                    {
+                        return true;
+                    }
+                }
+            }
+
+            if Self::check_blast(game, &point).is_ok() && found(GameCommand::Blast(point)) {
+                return true;
+            }
+
+            if let Some(activatable) = piece.activatable {
+                for target in
+                    activatable
+                        .range
+                        .reachable_points_for_piece(&point, piece, &game.board)
+                {
+                    let command = if Self::check_targeted_shoot(game, &point, &target).is_ok() {
+                        GameCommand::TargetedShoot(point, target)
+                    } else if Self::check_power(game, activatable.kind, &point, &target).is_ok() {
+                        GameCommand::UsePower(point, target)
+                    } else {
+                        continue;
+                    };
+                    if found(command) {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    fn check_place(game: &Game, pos: &Point2) -> Result<(), MoveError> {
+        if let Some(target_piece) = game.board.get_piece_at(pos) {
+            return Err(MoveError::PieceAlreadyPresent(*target_piece));
+        }
+
+        if !game.board.is_floor(pos) {
+            return Err(MoveError::IllegalMove);
+        }
+
+        if !game.unused_piece_available() {
+            return Err(MoveError::NoPieceAvailable);
+        }
+
+        Ok(())
+    }
+
+    /// The current team's piece at `pos`.
+    fn own_piece_at(game: &Game, pos: &Point2) -> Result<Piece, MoveError> {
+        let piece = game
             .board
-            .get_piece_at(from)
+            .get_piece_at(pos)
             .ok_or(MoveError::NoPiecePresent)?;
 
+        if piece.team_id != game.current_team_index {
+            return Err(MoveError::IllegalMove);
+        }
+
+        Ok(*piece)
+    }
+
+    fn check_move(game: &Game, from: &Point2, target_point: &Point2) -> Result<Piece, MoveError> {
+        let selected_piece = Self::own_piece_at(game, from)?;
+
         let m = selected_piece
             .movement
             .as_ref()
             .ok_or(MoveError::NotSupportedByPiece)?;
 
-        if !selected_piece.can_move() || *from == *target_point {
-            return MoveResult::Err(MoveError::IllegalMove);
+        if !selected_piece.can_move()
+            || *from == *target_point
+            || game.board.has_effect_at(&EffectKind::Slow, from)
+        {
+            return Err(MoveError::IllegalMove);
         }
 
         if !m
@@ -130,65 +435,53 @@ impl GameController {
             .reachable_points(from, &game.board)
             .contains(target_point)
         {
-            return MoveResult::Err(MoveError::NotSupportedByPiece);
+            return Err(MoveError::NotSupportedByPiece);
         }
 
-        let mut move_event = MoveBuilder::new(*from, *target_point, *selected_piece);
-
-        if let Some(target_piece) = game.board.get_piece_at(target_point) {
-            move_event.remove_piece(*target_piece);
-
-            if target_piece.team_id == game.current_team_index {
-                // is actually atm already checked by reachable_points but not sure if this should be relied on
-                return MoveResult::Err(MoveError::IllegalMove);
-            }
-
-            remove_effects_if_present(&mut move_event, &game.board, target_piece, target_point);
+        if let Some(target_piece) = game.board.get_piece_at(target_point)
+            && target_piece.team_id == game.current_team_index
+        {
+            // is actually atm already checked by reachable_points but not sure if this should be relied on
+            return Err(MoveError::IllegalMove);
         }
 
-        MoveResult::Ok(flush_and_merge(game, Box::new(move_event)))
+        Ok(selected_piece)
     }
 
-    pub fn blast(game: &mut Game, piece_pos: &Point2) -> MoveResult {
-        let attacking_piece = game
-            .board
-            .get_piece_at(piece_pos)
-            .ok_or(MoveError::NoPiecePresent)?;
+    fn check_blast(
+        game: &Game,
+        piece_pos: &Point2,
+    ) -> Result<(Piece, IndexSet<Point2>), MoveError> {
+        let attacking_piece = Self::own_piece_at(game, piece_pos)?;
 
         if !attacking_piece.can_use_special() {
-            return MoveResult::Err(MoveError::IllegalMove);
+            return Err(MoveError::IllegalMove);
         }
 
         let activatable = attacking_piece
             .activatable
+            .filter(|a| a.kind == Power::Blast)
             .ok_or(MoveError::NotSupportedByPiece)?;
 
-        let mut attack_event = AttackBuilder::new(attacking_piece, *piece_pos);
         let reachable_points = activatable.range.reachable_points(piece_pos, &game.board);
 
-        if reachable_points.is_empty() {
-            return MoveResult::Err(MoveError::IllegalMove);
-        }
-
-        for point in reachable_points {
-            if let Some(piece) = game.board.get_piece_at(&point) {
-                attack_event.remove_piece(point, *piece);
-                remove_effects_if_present(&mut attack_event, &game.board, piece, &point);
-            }
+        // a blast has to hit something, an attack without removed pieces can't be built
+        if !reachable_points
+            .iter()
+            .any(|point| game.board.get_piece_at(point).is_some())
+        {
+            return Err(MoveError::IllegalMove);
         }
 
-        MoveResult::Ok(flush_and_merge(game, Box::new(attack_event)))
+        Ok((attacking_piece, reachable_points))
     }
 
-    pub fn targeted_shoot(
-        game: &mut Game,
+    fn check_targeted_shoot(
+        game: &Game,
         attacking_piece_pos: &Point2,
         target_pos: &Point2,
-    ) -> MoveResult {
-        let active_piece = game
-            .board
-            .get_piece_at(attacking_piece_pos)
-            .ok_or(MoveError::NoPiecePresent)?;
+    ) -> Result<(Piece, Piece), MoveError> {
+        let active_piece = Self::own_piece_at(game, attacking_piece_pos)?;
 
         let target_piece = game
             .board
@@ -196,43 +489,117 @@ impl GameController {
             .ok_or(MoveError::NoPiecePresent)?;
 
         if !active_piece.can_use_special() {
-            return MoveResult::Err(MoveError::IllegalMove);
+            return Err(MoveError::IllegalMove);
         }
 
-        if let Some(activatable) = active_piece.activatable {
+        if let Some(activatable) = active_piece
+            .activatable
+            .filter(|a| a.kind == Power::TargetedShoot)
+        {
             if !activatable
                 .range
-                .reachable_points_for_piece(attacking_piece_pos, active_piece, &game.board)
+                .reachable_points_for_piece(attacking_piece_pos, &active_piece, &game.board)
                 .contains(target_pos)
             {
-                return MoveResult::Err(MoveError::IllegalMove);
+                return Err(MoveError::IllegalMove);
             }
         } else {
-            return MoveResult::Err(MoveError::NotSupportedByPiece);
+            return Err(MoveError::NotSupportedByPiece);
+        }
+
+        Ok((active_piece, *target_piece))
+    }
+
+    /// Checks the powers that act on another piece than the ones a blast or shot removes:
+    /// the target has to be in range and fit the power.
+    fn check_power(
+        game: &Game,
+        power: Power,
+        piece_pos: &Point2,
+        target_pos: &Point2,
+    ) -> Result<(Piece, Piece), MoveError> {
+        let active_piece = Self::own_piece_at(game, piece_pos)?;
+
+        if !active_piece.can_use_special() {
+            return Err(MoveError::IllegalMove);
         }
 
-        let mut attack_event = AttackBuilder::new(active_piece, *attacking_piece_pos);
-        attack_event.remove_piece(*target_pos, *target_piece);
+        let activatable = active_piece
+            .activatable
+            .filter(|a| a.kind == power)
+            .ok_or(MoveError::NotSupportedByPiece)?;
 
-        remove_effects_if_present(&mut attack_event, &game.board, target_piece, target_pos);
+        let target_piece = *game
+            .board
+            .get_piece_at(target_pos)
+            .ok_or(MoveError::NoPiecePresent)?;
 
-        Ok(flush_and_merge(game, Box::new(attack_event)))
+        let own_target = target_piece.team_id == active_piece.team_id;
+        let fits = match power {
+            Power::Push => !own_target && push_destination(game, piece_pos, target_pos).is_ok(),
+            Power::Swap => own_target && piece_pos != target_pos,
+            Power::Convert => !own_target && target_piece.piece_kind == PieceKind::Simple,
+            Power::Enchant => true,
+            Power::Blast | Power::TargetedShoot => false,
+        };
+
+        if !fits
+            || !activatable
+                .range
+                .reachable_points_for_piece(piece_pos, &active_piece, &game.board)
+                .contains(target_pos)
+        {
+            return Err(MoveError::IllegalMove);
+        }
+
+        Ok((active_piece, target_piece))
     }
 
+    /// Ends the turn: counts down the timed effects and lets poison that wears off kill
+    /// the piece it lies under, then gives the pieces their actions back.
     pub fn next_turn(game: &Game) -> GameAction {
-        let mut finish_turn = GameAction::finish_turn();
+        let current_team_index = game.current_team_index;
+        let mut finish_turn = GameAction::finish_turn(current_team_index);
         {
-            let current_team_index = game.current_team_index;
+            for _ in 0..game.rules.pieces_per_turn {
+                finish_turn.add_unused_piece(current_team_index);
+            }
+
+            let mut poisoned = vec![];
+            game.board.for_each_cell(|cell| {
+                for effect in cell.effects.iter().filter(|e| e.turns.is_some()) {
+                    finish_turn.count_down_effect(cell.point, *effect);
 
-            finish_turn
-                .add_unused_piece(current_team_index)
-                .add_unused_piece(current_team_index);
+                    if effect.kind == EffectKind::Poison
+                        && effect.turns == Some(1)
+                        && let Some(piece) = cell.piece
+                        && !poisoned.contains(&(cell.point, piece))
+                    {
+                        poisoned.push((cell.point, piece));
+                    }
+                }
+            });
+
+            for (point, piece) in poisoned.iter() {
+                finish_turn.remove_piece(*point, *piece);
+                remove_effects_if_present(&mut finish_turn, &game.board, piece, point);
+            }
+
+            let killed: Vec<Piece> = poisoned.iter().map(|(_, piece)| *piece).collect();
+            let eliminated = defeated_teams(&game.board, &killed);
+            for team_id in eliminated.iter() {
+                finish_turn.eliminate_team(*team_id);
+            }
 
             game.board.for_each_placed_piece(|point, piece| {
                 if piece.movement.is_none() && piece.activatable.is_none() {
                     return;
                 }
 
+                if poisoned.iter().any(|(p, _)| *p == point) {
+                    return;
+                }
+
                 let mut exhaustion_clone = piece.exhaustion;
                 exhaustion_clone.reset();
 
@@ -240,11 +607,47 @@ impl GameController {
                     finish_turn.change_exhaustion(piece.exhaustion, exhaustion_clone, point);
                 }
             });
+
+            if let Some(team_id) = next_team_index(game)
+                && eliminated.contains(&team_id)
+            {
+                finish_turn.next_turn(team_id);
+            }
         }
         finish_turn.build()
     }
 }
 
+/// The team whose turn it is after the current one, skipping the teams that already lost.
+fn next_team_index(game: &Game) -> Option<usize> {
+    let team_count = game.teams.len();
+
+    (1..team_count)
+        .map(|offset| (game.current_team_index + offset) % team_count)
+        .find(|team_id| !game.get_team(*team_id).lost)
+}
+
+/// The cell a pushed piece ends up on: one step further along the line from the pushing
+/// piece to the target. It has to be a free floor cell.
+fn push_destination(
+    game: &Game,
+    piece_pos: &Point2,
+    target_pos: &Point2,
+) -> Result<Point2, MoveError> {
+    let step = |from: u8, to: u8| (to as i16 - from as i16).signum();
+    let x = target_pos.x as i16 + step(piece_pos.x, target_pos.x);
+    let y = target_pos.y as i16 + step(piece_pos.y, target_pos.y);
+
+    let destination = u8::try_from(x)
+        .ok()
+        .zip(u8::try_from(y).ok())
+        .map(Point2::from)
+        .filter(|point| game.board.is_floor(point) && game.board.get_piece_at(point).is_none())
+        .ok_or(MoveError::IllegalMove)?;
+
+    Ok(destination)
+}
+
 fn push_effects_if_present(
     effect_builder: &mut dyn EffectBuilder,
     board: &Board,
@@ -257,7 +660,7 @@ fn push_effects_if_present(
             .reachable_points_for_piece(pos, new_piece, board)
             .iter()
             .for_each(|&point| {
-                effect_builder.add_effect(point);
+                effect_builder.add_effect(point, CellEffect::lasting(effect.kind));
             });
     }
 }
@@ -274,79 +677,111 @@ fn remove_effects_if_present(
             .reachable_points(pos, board)
             .iter()
             .for_each(|&point| {
-                effect_builder.remove_effect(point);
+                effect_builder.remove_effect(point, CellEffect::lasting(effect.kind));
             });
     }
 }
 
-fn merge_patterns(board: &Board, merge_builder: &mut MergeBuilder) {
-    let mut dying: HashSet<Point2> = HashSet::new();
-    for pattern in &Pattern::all_patterns() {
-        for x in 0..board.w as usize - pattern.components[0].len() + 1 {
-            for y in 0..board.h as usize - pattern.components.len() + 1 {
-                let matched = pattern.match_board(board, x as u8, y as u8);
-
-                if let Some(matched_entities) = matched {
-                    let any_team_id = board.get_piece_at(&matched_entities[0]).unwrap().team_id;
-                    if matched_entities
-                        .iter()
-                        .map(|point| board.get_piece_at(point).unwrap())
-                        .all(|piece| piece.team_id == any_team_id)
-                        && !matched_entities.iter().any(|p| dying.contains(p))
-                    {
-                        let new_piece = Piece::new(any_team_id, pattern.turn_into);
-
-                        let new_piece_x = x as u8 + pattern.new_piece_relative_position.x;
-                        let new_piece_y = y as u8 + pattern.new_piece_relative_position.y;
-
-                        let new_piece_pos = Point2::new(new_piece_x, new_piece_y);
-                        merge_builder.place_piece(new_piece_pos, new_piece);
+/// The teams that lose all of their pieces on the board if `removed_pieces` are taken.
+fn defeated_teams(board: &Board, removed_pieces: &[Piece]) -> Vec<usize> {
+    let mut teams: Vec<usize> = removed_pieces.iter().map(|p| p.team_id).collect();
+    teams.sort_unstable();
+    teams.dedup();
+
+    teams
+        .into_iter()
+        .filter(|team_id| {
+            let removed = removed_pieces
+                .iter()
+                .filter(|p| p.team_id == *team_id)
+                .count();
+            board.placed_pieces(*team_id).len() <= removed
+        })
+        .collect()
+}
 
-                        matched_entities.iter().for_each(|point| {
-                            // println!("Going to remove matched piece {:?}", matched_piece);
-                            {
-                                let matched_piece = board.get_piece_at(point).unwrap();
-                                merge_builder.remove_piece(*point, *matched_piece);
-                                dying.insert(*point);
-                            }
-                            let matched_piece = board.get_piece_at(point).unwrap();
+/// Finds all merges that happen in one resolution step.
+///
+/// Matches are collected in a fixed priority: pattern order, then the order of the pattern's
+/// variants, then by column and finally by row of the pattern's top left corner. A match is
+/// skipped if it would consume a piece or fill a cell already claimed by a match of higher
+/// priority. Merges enabled by the pieces created here are found in the next step.
+fn merge_patterns(game: &Game, merge_builder: &mut MergeBuilder) {
+    let board = &game.board;
+    let mut dying: HashSet<Point2> = HashSet::new();
+    let mut targets: HashSet<Point2> = HashSet::new();
+    for pattern in game.patterns.iter().flat_map(Pattern::variants) {
+        for x in 0..(board.w as usize + 1).saturating_sub(pattern.components[0].len()) {
+            for y in 0..(board.h as usize + 1).saturating_sub(pattern.components.len()) {
+                let Some(matched_entities) = pattern.match_board(board, x as u8, y as u8) else {
+                    continue;
+                };
+
+                let any_team_id = board.get_piece_at(&matched_entities[0]).unwrap().team_id;
+                let new_piece_pos = Point2::new(
+                    x as u8 + pattern.new_piece_relative_position.x,
+                    y as u8 + pattern.new_piece_relative_position.y,
+                );
+
+                if !matched_entities
+                    .iter()
+                    .map(|point| board.get_piece_at(point).unwrap())
+                    .all(|piece| piece.team_id == any_team_id)
+                    || matched_entities.iter().any(|p| dying.contains(p))
+                    || targets.contains(&new_piece_pos)
+                {
+                    continue;
+                }
 
-                            remove_effects_if_present(merge_builder, board, matched_piece, point);
-                        });
+                let new_piece = game.new_piece(any_team_id, pattern.turn_into);
 
-                        push_effects_if_present(merge_builder, board, &new_piece, &new_piece_pos);
+                let merged_from = matched_entities
+                    .iter()
+                    .map(|point| (*point, *board.get_piece_at(point).unwrap()))
+                    .collect::<Vec<_>>();
 
-                        /* println!(
-                            "Matched pattern at {}:{}; new piece at {}:{}",
-                            x, y, new_piece_x, new_piece_y
-                        );*/
+                merged_from.iter().for_each(|(point, matched_piece)| {
+                    dying.insert(*point);
+                    remove_effects_if_present(merge_builder, board, matched_piece, point);
+                });
+                targets.insert(new_piece_pos);
 
-                        return; // ensures merges are handled sequentially. In future parallel merges may be nice
-                    }
-                }
+                merge_builder.merge(new_piece_pos, new_piece, merged_from);
+                push_effects_if_present(merge_builder, board, &new_piece, &new_piece_pos);
             }
         }
     }
 }
 
-fn flush_and_merge(game: &mut Game, event_builder: Box<dyn CompoundEventBuilder>) -> GameAction {
-    let mut flush_result = BoardEventConsumer::flush(game, event_builder);
-    while let FlushResult::Merge(mut m) = flush_result {
-        merge_patterns(&game.board, &mut m);
-        flush_result = BoardEventConsumer::flush(game, Box::new(m));
-    }
-    if let FlushResult::Build(game_action) = flush_result {
-        // always true
-        game_action
-    } else {
-        panic!("unreachable code")
-    }
+fn flush_and_merge(game: &mut Game, event_builder: Box<dyn CompoundEventBuilder>) -> MoveResult {
+    let game_action = BoardEventConsumer::apply_or_roll_back(game, |game, reverts| {
+        let mut flush_result = BoardEventConsumer::flush_logged(game, event_builder, reverts)?;
+        while let FlushResult::Merge(mut m) = flush_result {
+            merge_patterns(game, &mut m);
+            flush_result = BoardEventConsumer::flush_logged(game, Box::new(m), reverts)?;
+        }
+        if let FlushResult::Build(game_action) = flush_result {
+            // always true
+            Ok(game_action)
+        } else {
+            panic!("unreachable code")
+        }
+    })?;
+
+    Ok(game_action)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use game_model::{game::Team, piece::PieceKind};
+    use crate::outcome::makes_progress;
+    use game_events::atomic_events::AtomicEvent;
+    use game_model::{
+        game::Team,
+        piece::{ActivatablePower, CellEffect, EffectKind, PieceDefinition, PieceKind},
+        ranges::{Direction, Range, RangeContext},
+        zobrist,
+    };
 
     fn setup_game() -> Game {
         Game::new(
@@ -367,6 +802,20 @@ mod tests {
         )
     }
 
+    #[test]
+    fn undo_and_redo_are_no_moves() {
+        let mut game = setup_game();
+        let before = game.clone();
+
+        for command in [GameCommand::Undo, GameCommand::Redo] {
+            assert!(matches!(
+                GameController::execute(&mut game, &command),
+                Err(MoveError::NotAMove)
+            ));
+        }
+        assert_eq!(game, before);
+    }
+
     #[test]
     fn blast_rejected_when_piece_is_exhausted() {
         let mut game = setup_game();
@@ -416,4 +865,584 @@ mod tests {
             "targeted shoot should be rejected on an exhausted piece"
         );
     }
+
+    #[test]
+    fn disjoint_merges_are_resolved_in_one_step() {
+        let mut game = setup_game();
+        game.teams[0].unused_pieces = 1;
+        for point in [(1, 1), (2, 1), (1, 5), (2, 5), (3, 5)] {
+            game.board
+                .place_piece_at(Piece::new(0, PieceKind::Simple), &point.into())
+                .unwrap();
+        }
+
+        let action = GameController::place_piece(&mut game, &Point2::new(3, 1)).unwrap();
+        let GameAction::Place(place_event) = action else {
+            panic!("expected a place action, got {}", action);
+        };
+
+        let merge_event = place_event.merge_events().as_ref().unwrap();
+        let merged_at: Vec<Point2> = merge_event.merges().iter().map(|m| *m.at()).collect();
+        assert_eq!(merged_at, vec![Point2::new(2, 1), Point2::new(2, 5)]);
+        // the pieces created here don't complete another pattern
+        assert!(
+            merge_event
+                .merge_events()
+                .as_ref()
+                .is_none_or(|chained| chained.merges().is_empty())
+        );
+        assert_eq!(game.board.placed_pieces(0).len(), 2);
+    }
+
+    #[test]
+    fn placed_and_merged_pieces_use_the_games_stats() {
+        let mut pieces = PieceDefinition::default_definitions();
+        for definition in &mut pieces {
+            match definition.kind {
+                PieceKind::Simple => definition.movement.as_mut().unwrap().jumps = true,
+                PieceKind::HorizontalBar => definition.power.as_mut().unwrap().range.steps = 2,
+                _ => {}
+            }
+        }
+        let mut game = setup_game().with_pieces(pieces);
+        game.teams[0].unused_pieces = 3;
+
+        GameController::place_piece(&mut game, &Point2::new(1, 1)).unwrap();
+        let simple = game.board.get_piece_at(&Point2::new(1, 1)).unwrap();
+        assert!(simple.movement.unwrap().range.jumps);
+
+        GameController::place_piece(&mut game, &Point2::new(2, 1)).unwrap();
+        GameController::place_piece(&mut game, &Point2::new(3, 1)).unwrap();
+        let bar = game.board.get_piece_at(&Point2::new(2, 1)).unwrap();
+        assert_eq!(bar.piece_kind, PieceKind::HorizontalBar);
+        assert_eq!(bar.activatable.unwrap().range.steps, 2);
+    }
+
+    /// A game in which Snipers have `power` with `range` instead of their shot, and a
+    /// ready Sniper of team 0 stands at (3, 3).
+    fn setup_power_game(power: Power, range: Range) -> Game {
+        let mut pieces = PieceDefinition::default_definitions();
+        for definition in &mut pieces {
+            if definition.kind == PieceKind::Sniper {
+                definition.power = Some(ActivatablePower {
+                    kind: power,
+                    range,
+                    effect: None,
+                });
+            }
+        }
+        let mut game = setup_game().with_pieces(pieces);
+
+        let mut sniper = game.new_piece(0, PieceKind::Sniper);
+        sniper.exhaustion.reset();
+        game.board
+            .place_piece_at(sniper, &Point2::new(3, 3))
+            .unwrap();
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(6, 6))
+            .unwrap();
+        game.rehash();
+        game
+    }
+
+    /// Executes `command`, checks that it is listed as legal and that its anti-event
+    /// restores the game.
+    fn execute_and_check_undo(game: &mut Game, command: GameCommand) -> GameAction {
+        assert!(GameController::legal_commands(game).contains(&command));
+
+        let before = game.clone();
+        let action = GameController::execute(game, &command).unwrap();
+        let mut undone = game.clone();
+        BoardEventConsumer::apply(&mut undone, &action.anti_event()).unwrap();
+        assert_eq!(undone, before, "undoing {}", command);
+
+        action
+    }
+
+    #[test]
+    fn push_shoves_the_target_one_cell_away() {
+        let mut game = setup_power_game(Power::Push, Range::new_moving(Direction::Star, 1));
+        let enemy = Piece::new(1, PieceKind::Castle);
+        game.board
+            .place_piece_at(enemy, &Point2::new(4, 4))
+            .unwrap();
+        let protected = enemy
+            .effect
+            .unwrap()
+            .range
+            .reachable_points(&Point2::new(4, 4), &game.board);
+        for point in protected {
+            game.board
+                .add_effect(CellEffect::lasting(EffectKind::Protection), &point)
+                .unwrap();
+        }
+        game.rehash();
+
+        let action = execute_and_check_undo(
+            &mut game,
+            GameCommand::UsePower(Point2::new(3, 3), Point2::new(4, 4)),
+        );
+
+        assert!(game.board.get_piece_at(&Point2::new(4, 4)).is_none());
+        assert_eq!(game.board.get_piece_at(&Point2::new(5, 5)), Some(&enemy));
+        // the Castle's protection moves along
+        assert!(
+            game.board
+                .has_effect_at(&EffectKind::Protection, &Point2::new(6, 6))
+        );
+        assert!(
+            !game
+                .board
+                .has_effect_at(&EffectKind::Protection, &Point2::new(3, 3))
+        );
+        assert!(
+            !game
+                .board
+                .get_piece_at(&Point2::new(3, 3))
+                .unwrap()
+                .can_use_special()
+        );
+        assert!(!makes_progress(&action));
+
+        // a second push would need a free cell behind the target
+        game.board
+            .get_piece_mut_at(&Point2::new(3, 3))
+            .unwrap()
+            .exhaustion
+            .reset();
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(3, 4))
+            .unwrap();
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(3, 5))
+            .unwrap();
+        assert!(
+            GameController::use_power(&mut game, &Point2::new(3, 3), &Point2::new(3, 4)).is_err()
+        );
+    }
+
+    #[test]
+    fn swap_exchanges_two_own_pieces() {
+        let area = Range {
+            direction: Direction::Star,
+            context: RangeContext::Area,
+            steps: 2,
+            jumps: false,
+            include_self: false,
+        };
+        let mut game = setup_power_game(Power::Swap, area);
+        let own = Piece::new(0, PieceKind::Cross);
+        game.board.place_piece_at(own, &Point2::new(1, 3)).unwrap();
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(5, 3))
+            .unwrap();
+        game.rehash();
+
+        assert!(
+            GameController::use_power(&mut game.clone(), &Point2::new(3, 3), &Point2::new(5, 3))
+                .is_err(),
+            "enemies can't be swapped with"
+        );
+        execute_and_check_undo(
+            &mut game,
+            GameCommand::UsePower(Point2::new(3, 3), Point2::new(1, 3)),
+        );
+
+        assert_eq!(game.board.get_piece_at(&Point2::new(3, 3)), Some(&own));
+        let sniper = game.board.get_piece_at(&Point2::new(1, 3)).unwrap();
+        assert_eq!(sniper.piece_kind, PieceKind::Sniper);
+        assert!(!sniper.can_use_special());
+    }
+
+    #[test]
+    fn convert_turns_an_enemy_simple_and_can_eliminate_its_team() {
+        let mut game = setup_power_game(
+            Power::Convert,
+            Range::new_unlimited(Direction::Anywhere, RangeContext::Special),
+        );
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Cross), &Point2::new(0, 0))
+            .unwrap();
+        game.rehash();
+
+        assert!(
+            GameController::use_power(&mut game.clone(), &Point2::new(3, 3), &Point2::new(0, 0))
+                .is_err(),
+            "only Simple pieces can be converted"
+        );
+        game.board.remove_piece_at(&Point2::new(0, 0)).unwrap();
+        game.rehash();
+
+        let action = execute_and_check_undo(
+            &mut game,
+            GameCommand::UsePower(Point2::new(3, 3), Point2::new(6, 6)),
+        );
+
+        assert_eq!(
+            game.board.get_piece_at(&Point2::new(6, 6)).unwrap().team_id,
+            0
+        );
+        assert!(game.teams[1].lost);
+        assert!(makes_progress(&action));
+    }
+
+    #[test]
+    fn enchant_lays_a_timed_effect_that_wears_off() {
+        let mut game = setup_power_game(
+            Power::Enchant,
+            Range::new_unlimited(Direction::Anywhere, RangeContext::Special),
+        );
+        let effect = CellEffect::timed(EffectKind::Protection, 2);
+        game.board
+            .get_piece_mut_at(&Point2::new(3, 3))
+            .unwrap()
+            .activatable
+            .as_mut()
+            .unwrap()
+            .effect = Some(effect);
+        game.rehash();
+
+        let action = execute_and_check_undo(
+            &mut game,
+            GameCommand::UsePower(Point2::new(3, 3), Point2::new(6, 6)),
+        );
+        assert_eq!(game.board.effect_count(&effect, &Point2::new(6, 6)), 1);
+        assert!(game.board.is_protected(&Point2::new(6, 6)));
+        assert!(!makes_progress(&action));
+
+        GameController::execute(&mut game, &GameCommand::NextTurn).unwrap();
+        assert_eq!(
+            game.board.effect_count(
+                &CellEffect::timed(EffectKind::Protection, 1),
+                &Point2::new(6, 6)
+            ),
+            1
+        );
+        assert_eq!(game.hash, zobrist::hash(&game));
+
+        GameController::execute(&mut game, &GameCommand::NextTurn).unwrap();
+        assert!(!game.board.is_protected(&Point2::new(6, 6)));
+        assert_eq!(game.hash, zobrist::hash(&game));
+    }
+
+    #[test]
+    fn slowed_pieces_cannot_move() {
+        let mut game = setup_game();
+        let mut piece = Piece::new(0, PieceKind::Simple);
+        piece.exhaustion.reset();
+        game.board
+            .place_piece_at(piece, &Point2::new(1, 1))
+            .unwrap();
+        game.board
+            .add_effect(CellEffect::timed(EffectKind::Slow, 1), &Point2::new(1, 1))
+            .unwrap();
+        game.rehash();
+
+        assert!(
+            GameController::move_piece(&mut game, &Point2::new(1, 1), &Point2::new(1, 2)).is_err()
+        );
+
+        GameController::execute(&mut game, &GameCommand::NextTurn).unwrap();
+        GameController::execute(&mut game, &GameCommand::NextTurn).unwrap();
+        assert!(
+            !game
+                .board
+                .has_effect_at(&EffectKind::Slow, &Point2::new(1, 1))
+        );
+        assert!(
+            GameController::move_piece(&mut game, &Point2::new(1, 1), &Point2::new(1, 2)).is_ok()
+        );
+    }
+
+    #[test]
+    fn poison_kills_when_it_wears_off() {
+        let mut game = setup_game();
+        game.teams.push(Team {
+            id: 2,
+            lost: false,
+            unused_pieces: 0,
+        });
+        game.board
+            .place_piece_at(Piece::new(0, PieceKind::Simple), &Point2::new(0, 0))
+            .unwrap();
+        game.board
+            .place_piece_at(Piece::new(2, PieceKind::Simple), &Point2::new(7, 7))
+            .unwrap();
+        let castle = Piece::new(1, PieceKind::Castle);
+        game.board
+            .place_piece_at(castle, &Point2::new(4, 4))
+            .unwrap();
+        let protected = castle
+            .effect
+            .unwrap()
+            .range
+            .reachable_points(&Point2::new(4, 4), &game.board);
+        for point in protected.iter() {
+            game.board
+                .add_effect(CellEffect::lasting(EffectKind::Protection), point)
+                .unwrap();
+        }
+        game.board
+            .add_effect(CellEffect::timed(EffectKind::Poison, 1), &Point2::new(4, 4))
+            .unwrap();
+        game.rehash();
+
+        let action = GameController::execute(&mut game, &GameCommand::NextTurn).unwrap();
+
+        assert!(game.board.get_piece_at(&Point2::new(4, 4)).is_none());
+        assert!(
+            protected
+                .iter()
+                .all(|point| !game.board.has_effect_at(&EffectKind::Protection, point)),
+            "the Castle's auras go with it"
+        );
+        assert!(game.teams[1].lost);
+        // team 1 was eliminated, so the turn passes on to team 2
+        assert_eq!(game.current_team_index, 2);
+        assert_eq!(game.hash, zobrist::hash(&game));
+        assert!(matches!(
+            action.get_compound_event().get_events()[0],
+            AtomicEvent::NextTurn(0)
+        ));
+    }
+
+    #[test]
+    fn use_power_also_blasts_and_shoots() {
+        let mut game = setup_game();
+        let mut sniper = Piece::new(0, PieceKind::Sniper);
+        sniper.exhaustion.reset();
+        game.board
+            .place_piece_at(sniper, &Point2::new(0, 0))
+            .unwrap();
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(5, 5))
+            .unwrap();
+
+        assert!(
+            GameController::use_power(&mut game.clone(), &Point2::new(0, 0), &Point2::new(0, 0))
+                .is_err()
+        );
+        let action =
+            GameController::use_power(&mut game, &Point2::new(0, 0), &Point2::new(5, 5)).unwrap();
+        let GameAction::Attack(attack) = action else {
+            panic!("expected an attack, got {}", action);
+        };
+        assert_eq!(*attack.power(), Power::TargetedShoot);
+        assert!(game.board.get_piece_at(&Point2::new(5, 5)).is_none());
+    }
+
+    #[test]
+    fn capturing_the_last_piece_eliminates_the_team() {
+        let mut game = setup_game();
+        game.teams.push(Team {
+            id: 2,
+            lost: false,
+            unused_pieces: 0,
+        });
+
+        let mut attacker = Piece::new(0, PieceKind::Simple);
+        attacker.exhaustion.reset();
+        game.board
+            .place_piece_at(attacker, &Point2::new(1, 1))
+            .unwrap();
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(2, 2))
+            .unwrap();
+        game.board
+            .place_piece_at(Piece::new(2, PieceKind::Simple), &Point2::new(6, 6))
+            .unwrap();
+
+        let action =
+            GameController::move_piece(&mut game, &Point2::new(1, 1), &Point2::new(2, 2)).unwrap();
+
+        let events = action.get_compound_event().get_events();
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, AtomicEvent::EliminateTeam(1)))
+        );
+        assert!(
+            !events
+                .iter()
+                .any(|e| matches!(e, AtomicEvent::EliminateTeam(2)))
+        );
+        assert!(game.get_team(1).lost);
+        assert_eq!(game.active_teams(), vec![0, 2]);
+
+        let undo_events = action.anti_event().get_compound_event().get_events();
+        assert!(
+            undo_events
+                .iter()
+                .any(|e| matches!(e, AtomicEvent::ReviveTeam(1)))
+        );
+    }
+
+    fn every_candidate_command(game: &Game) -> Vec<GameCommand> {
+        let mut points = vec![];
+        game.board.for_each_cell(|cell| points.push(cell.point));
+
+        let mut commands = vec![];
+        for from in &points {
+            commands.push(GameCommand::PlacePiece(*from));
+            commands.push(GameCommand::Blast(*from));
+            for to in &points {
+                commands.push(GameCommand::MovePiece(*from, *to));
+                commands.push(GameCommand::TargetedShoot(*from, *to));
+                commands.push(GameCommand::UsePower(*from, *to));
+            }
+        }
+        commands.push(GameCommand::NextTurn);
+        commands
+    }
+
+    /// `UsePower` of a blast or shot is listed as the `Blast` or `TargetedShoot` it stands for.
+    fn canonical(game: &Game, command: GameCommand) -> GameCommand {
+        let GameCommand::UsePower(from, to) = command else {
+            return command;
+        };
+
+        match game.board.get_piece_at(&from).and_then(|p| p.activatable) {
+            Some(ActivatablePower {
+                kind: Power::Blast, ..
+            }) => GameCommand::Blast(from),
+            Some(ActivatablePower {
+                kind: Power::TargetedShoot,
+                ..
+            }) => GameCommand::TargetedShoot(from, to),
+            _ => command,
+        }
+    }
+
+    fn assert_legal_commands_match_handle_command(game: &Game) {
+        let legal = GameController::legal_commands(game);
+
+        let mut accepted: Vec<GameCommand> = vec![];
+        for command in every_candidate_command(game) {
+            let command = canonical(game, command);
+            if !accepted.contains(&command)
+                && GameController::handle_command(game.clone(), &command).is_ok()
+            {
+                accepted.push(command);
+            }
+        }
+
+        assert_eq!(legal.len(), accepted.len(), "legal: {:?}", legal);
+        assert_eq!(GameController::has_legal_command(game), legal.len() > 1);
+        for command in &accepted {
+            assert!(legal.contains(command), "{} is missing", command);
+        }
+    }
+
+    #[test]
+    fn legal_commands_match_handle_command() {
+        let mut game = setup_game();
+        game.teams[0].unused_pieces = 2;
+
+        for (kind, point) in [
+            (PieceKind::Simple, (1, 1)),
+            (PieceKind::HorizontalBar, (4, 3)),
+            (PieceKind::Cross, (6, 6)),
+            (PieceKind::Sniper, (2, 6)),
+            (PieceKind::Queen, (5, 1)),
+        ] {
+            let mut piece = Piece::new(0, kind);
+            piece.exhaustion.reset();
+            game.board.place_piece_at(piece, &point.into()).unwrap();
+        }
+        // an exhausted piece contributes nothing but the places it blocks
+        game.board
+            .place_piece_at(Piece::new(0, PieceKind::VerticalBar), &Point2::new(7, 0))
+            .unwrap();
+        for (kind, point) in [
+            (PieceKind::Simple, (2, 2)),
+            (PieceKind::VerticalBar, (0, 3)),
+            (PieceKind::Sniper, (6, 4)),
+        ] {
+            let mut piece = Piece::new(1, kind);
+            piece.exhaustion.reset();
+            game.board.place_piece_at(piece, &point.into()).unwrap();
+        }
+
+        assert_legal_commands_match_handle_command(&game);
+
+        game.teams[0].unused_pieces = 0;
+        assert_legal_commands_match_handle_command(&game);
+
+        game.current_team_index = 1;
+        assert_legal_commands_match_handle_command(&game);
+    }
+
+    #[test]
+    fn legal_power_commands_match_handle_command() {
+        let mut game = setup_game().with_pieces(PieceDefinition::trickster_definitions());
+
+        for (team_id, kind, point) in [
+            (0, PieceKind::Cross, (1, 1)),
+            (0, PieceKind::Castle, (4, 4)),
+            (0, PieceKind::Sniper, (6, 1)),
+            (0, PieceKind::Simple, (4, 6)),
+            (1, PieceKind::Simple, (1, 2)),
+            (1, PieceKind::Simple, (6, 3)),
+            (1, PieceKind::Queen, (7, 2)),
+            // a push needs a free cell behind the target
+            (1, PieceKind::Simple, (2, 1)),
+            (1, PieceKind::Simple, (3, 1)),
+        ] {
+            let mut piece = game.new_piece(team_id, kind);
+            piece.exhaustion.reset();
+            game.board.place_piece_at(piece, &point.into()).unwrap();
+        }
+        // the protection the Castle lays down when it is placed, which the swap moves along
+        let castle_pos = Point2::new(4, 4);
+        let protection = game
+            .board
+            .get_piece_at(&castle_pos)
+            .unwrap()
+            .effect
+            .unwrap();
+        for point in protection.range.reachable_points(&castle_pos, &game.board) {
+            game.board
+                .add_effect(CellEffect::lasting(protection.kind), &point)
+                .unwrap();
+        }
+
+        let legal = GameController::legal_commands(&game);
+        for command in [
+            GameCommand::UsePower(Point2::new(1, 1), Point2::new(1, 2)),
+            GameCommand::UsePower(Point2::new(4, 4), Point2::new(4, 6)),
+            GameCommand::UsePower(Point2::new(6, 1), Point2::new(6, 3)),
+        ] {
+            assert!(legal.contains(&command), "{} is missing", command);
+        }
+        assert_legal_commands_match_handle_command(&game);
+
+        game.current_team_index = 1;
+        assert_legal_commands_match_handle_command(&game);
+    }
+
+    #[test]
+    fn pieces_of_other_teams_cannot_be_commanded() {
+        let mut game = setup_game();
+        let mut enemy = Piece::new(1, PieceKind::HorizontalBar);
+        enemy.exhaustion.reset();
+        game.board
+            .place_piece_at(enemy, &Point2::new(4, 3))
+            .unwrap();
+        game.board
+            .place_piece_at(Piece::new(0, PieceKind::Simple), &Point2::new(1, 3))
+            .unwrap();
+
+        assert!(matches!(
+            GameController::move_piece(&mut game, &Point2::new(4, 3), &Point2::new(4, 4)),
+            Err(MoveError::IllegalMove)
+        ));
+        assert!(matches!(
+            GameController::blast(&mut game, &Point2::new(4, 3)),
+            Err(MoveError::IllegalMove)
+        ));
+        assert_eq!(
+            GameController::legal_commands(&game),
+            vec![GameCommand::NextTurn]
+        );
+    }
 }
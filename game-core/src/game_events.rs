@@ -1,3 +1,4 @@
+use game_model::rules::RuleSet;
 use nanoserde::{DeJson, SerJson};
 
 use quad_rand::rand;
@@ -15,8 +16,16 @@ pub enum Event {
 pub enum PlayerAction {
     /// player name, index
     Connect(String, usize),
-    /// client ids of players in order
-    NewGame((String, String)),
+    /// client ids of players in order, and the rules of the player who started the game
+    NewGame(Vec<String>, RuleSet),
+    /// The team with this index asks the others to undo its last action
+    RequestUndo(usize),
+    /// The team with this index asks the others to redo the last undone action
+    RequestRedo(usize),
+    /// The team with this index agrees to the pending undo or redo request
+    AcceptUndo(usize),
+    /// The undo or redo request was declined
+    DeclineUndo,
 }
 
 #[derive(Debug, Clone, SerJson, DeJson)]
@@ -2,13 +2,22 @@
 //!
 //! [`game_controller::GameController`] enforces rules (placement, movement, attacks, merges);
 //! [`command_handler::CommandHandler`] orchestrates event creation, undo, and multiplayer sync;
-//! [`core_game::CoreGameSubstate`] models the turn-phase state machine (Place → Move → Activate).
+//! [`core_game::CoreGameSubstate`] models the turn-phase state machine (Place → Move → Activate);
+//! [`outcome::OutcomeTracker`] decides when a game is won or drawn;
+//! [`ai::Bot`] plays a team for single-player games;
+//! [`notation`] writes and reads moves as text, e.g. `M b2-b4`, and [`record::GameRecord`]
+//! whole games with their teams, rules and result.
 //!
 //! Depends on `game-model` and `game-events`; consumed by `game-render` and `game-main`.
 
+pub mod ai;
 pub mod board_event_consumer;
 pub mod command_handler;
 pub mod core_game;
 pub mod game_controller;
 pub mod game_events;
 pub mod multiplayer_connector;
+pub mod notation;
+pub mod outcome;
+pub mod record;
+pub mod setup;
@@ -3,6 +3,7 @@ use crate::{
     game_events::{Event, GameEventObject, PlayerAction},
 };
 
+use game_model::rules::RuleSet;
 use indexmap::IndexMap;
 use log::{debug, info};
 use std::{cell::RefCell, rc::Rc};
@@ -10,6 +11,10 @@ use std::{cell::RefCell, rc::Rc};
 pub trait MultiplayerClient {
     fn is_ready(&self) -> bool;
     fn accept_new_connections(&mut self) -> Vec<String>;
+    /// Peers that lost their connection since the last call
+    fn disconnected_peers(&mut self) -> Vec<String> {
+        vec![]
+    }
     fn recieved_events(&mut self) -> Vec<GameEventObject>;
     fn send(&mut self, game_object: &GameEventObject, opponent_id: &str);
     fn own_player_id(&self) -> Option<String>;
@@ -18,8 +23,13 @@ pub trait MultiplayerClient {
 pub struct MultiplayerConector {
     registered_events: IndexMap<String, GameEventObject>,
     client: Box<dyn MultiplayerClient>,
-    pub opponent_id: Option<String>,
+    /// Client ids of all other players in the room
+    pub peer_ids: Vec<String>,
+    /// Client ids of all players in turn order, known once a new game was signaled
+    pub player_order: Option<Vec<String>>,
     pub override_own_player_index: Option<usize>,
+    disconnected_peer_ids: Vec<String>,
+    num_players: usize,
 }
 
 impl MultiplayerConector {
@@ -27,31 +37,84 @@ impl MultiplayerConector {
         MultiplayerConector {
             registered_events: IndexMap::new(),
             client,
-            opponent_id: None,
+            peer_ids: vec![],
+            player_order: None,
             override_own_player_index: None,
+            disconnected_peer_ids: vec![],
+            num_players: 2,
         }
     }
 
+    /// The number of players (including this one) the game waits for.
+    pub fn with_num_players(mut self, num_players: usize) -> Self {
+        self.num_players = num_players;
+        self
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.num_players
+    }
+
     pub fn is_ready(&self) -> bool {
         self.client.is_ready()
             && self.client.own_player_id().is_some()
-            && self.opponent_id.is_some()
+            && self.peer_ids.len() + 1 == self.num_players
     }
 
     pub fn matchmaking(&mut self) {
         self.accept_connection();
     }
 
-    /// Polls for new peer connections. Updates `opponent_id` when a new peer is found.
+    /// Polls for new peer connections and adds them to `peer_ids`.
     /// Should be called every frame — both during matchmaking and while a game is in progress
-    /// so that a reconnecting opponent is detected and can resume the game.
+    /// so that a reconnecting player is detected and can resume the game.
     /// Returns `true` if a new peer was detected.
     pub fn accept_connection(&mut self) -> bool {
-        if let Some(peer) = self.client.accept_new_connections().into_iter().next() {
+        let new_peers = self.client.accept_new_connections();
+        self.disconnected_peer_ids
+            .extend(self.client.disconnected_peers());
+
+        let mut accepted = false;
+        for peer in new_peers {
+            accepted |= self.add_peer(peer);
+        }
+
+        accepted
+    }
+
+    /// Adds a peer, replacing a disconnected one if the room is already full.
+    fn add_peer(&mut self, peer: String) -> bool {
+        if self.peer_ids.contains(&peer) {
+            return false;
+        }
+
+        if self.peer_ids.len() + 1 < self.num_players {
             info!("New peer connection detected: {}", peer);
-            self.opponent_id = Some(peer);
+            self.peer_ids.push(peer);
+            return true;
+        }
+
+        let replaced = self
+            .peer_ids
+            .iter()
+            .position(|id| self.disconnected_peer_ids.contains(id))
+            // With only one opponent it is clear who is reconnecting
+            .or(if self.num_players == 2 { Some(0) } else { None });
+
+        if let Some(replaced) = replaced {
+            let old_peer = std::mem::replace(&mut self.peer_ids[replaced], peer.clone());
+            info!("Peer {} reconnected as {}", old_peer, peer);
+
+            self.disconnected_peer_ids.retain(|id| *id != old_peer);
+            if let Some(player_order) = self.player_order.as_mut() {
+                player_order
+                    .iter_mut()
+                    .filter(|id| **id == old_peer)
+                    .for_each(|id| *id = peer.clone());
+            }
             true
         } else {
+            info!("Ignoring peer {} - the room is full", peer);
             false
         }
     }
@@ -61,17 +124,21 @@ impl MultiplayerConector {
             return self.override_own_player_index;
         }
 
-        if let Some(opponent_id) = self.opponent_id.as_ref()
-            && let Some(own_player_id) = &self.client.own_player_id()
-        {
-            if opponent_id < own_player_id {
-                return Some(1);
-            }
+        let own_player_id = self.client.own_player_id()?;
 
-            return Some(0);
+        if let Some(player_order) = &self.player_order {
+            return player_order.iter().position(|id| *id == own_player_id);
         }
 
-        None
+        if self.peer_ids.len() + 1 < self.num_players {
+            return None;
+        }
+
+        let mut player_ids = self.peer_ids.clone();
+        player_ids.push(own_player_id.clone());
+        player_ids.sort();
+
+        player_ids.iter().position(|id| *id == own_player_id)
     }
 
     pub fn get_own_player_id(&self) -> Option<String> {
@@ -85,16 +152,19 @@ impl MultiplayerConector {
             if self.register_event(&event_object) {
                 debug!("Received event: {}", &event_object);
 
-                if let Event::PlayerAction(PlayerAction::Connect(name, index)) = &event_object.event
-                {
-                    debug!("Player {} connected with supposed index {}.", name, index);
+                match &event_object.event {
+                    Event::PlayerAction(PlayerAction::Connect(name, index)) => {
+                        debug!("Player {} connected with supposed index {}.", name, index);
 
-                    self.opponent_id = Some(name.clone());
-
-                    events.push(event_object);
-                } else {
-                    events.push(event_object);
+                        self.add_peer(name.clone());
+                    }
+                    Event::PlayerAction(PlayerAction::NewGame(player_order, _)) => {
+                        self.player_order = Some(player_order.clone());
+                    }
+                    _ => {}
                 }
+
+                events.push(event_object);
             } else {
                 debug!("Event already received before: {:?}", event_object);
             }
@@ -126,13 +196,22 @@ impl MultiplayerConector {
     }
 
     fn send(&mut self, event: &GameEventObject) {
-        let opponent_id = self.opponent_id.as_ref().unwrap().clone();
         self.register_event(event);
-        self.client.send(event, &opponent_id);
+        for peer_id in &self.peer_ids {
+            self.client.send(event, peer_id);
+        }
         //println!("Sent event: {}", event);
         //debug!("Sent event: {}", event);
     }
 
+    pub fn send_player_action(&mut self, player_action: PlayerAction) {
+        let sender = self.get_own_player_id().expect("Own player ID unknown");
+
+        let event = &GameEventObject::new(Event::PlayerAction(player_action), &sender);
+
+        self.send(event);
+    }
+
     pub fn signal_connect(&mut self) {
         let game_object = &GameEventObject::new(
             Event::PlayerAction(PlayerAction::Connect(
@@ -145,19 +224,24 @@ impl MultiplayerConector {
         self.send(game_object);
     }
 
-    pub fn signal_new_game(&mut self) {
+    /// Sends the player order and the `rules` every player has to use.
+    pub fn signal_new_game(&mut self, rules: RuleSet) {
         let own_player_id = self.get_own_player_id().unwrap();
-        let opponent_id = self.opponent_id.as_ref().unwrap().clone();
 
-        let player_order = if self.get_own_player_index().unwrap() == 0 {
-            (own_player_id.clone(), opponent_id)
-        } else {
-            (opponent_id, own_player_id.clone())
+        let player_order = match &self.player_order {
+            Some(player_order) => player_order.clone(),
+            None => {
+                let mut player_order = self.peer_ids.clone();
+                player_order.sort();
+                let own_index = self.get_own_player_index().unwrap().min(player_order.len());
+                player_order.insert(own_index, own_player_id.clone());
+                player_order
+            }
         };
+        self.player_order = Some(player_order.clone());
+
         let game_object = &GameEventObject::new(
-            Event::PlayerAction(
-                PlayerAction::NewGame(player_order), // you can only signal a new game if you are the first
-            ),
+            Event::PlayerAction(PlayerAction::NewGame(player_order, rules)),
             &own_player_id,
         );
 
@@ -185,6 +269,10 @@ impl<T: MultiplayerClient> MultiplayerClient for Rc<RefCell<T>> {
         (*self).borrow_mut().accept_new_connections()
     }
 
+    fn disconnected_peers(&mut self) -> Vec<String> {
+        (*self).borrow_mut().disconnected_peers()
+    }
+
     fn recieved_events(&mut self) -> Vec<GameEventObject> {
         (*self).borrow_mut().recieved_events()
     }
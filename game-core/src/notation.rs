@@ -0,0 +1,371 @@
+//! A compact text notation for Bugchess moves, so games can be read, discussed and shared.
+//!
+//! Cells are written like in chess, with the column as a letter and the row as a number.
+//! Columns start with `a` on the left and rows with `1` at the top, so `c3` is `(2, 2)`.
+//! Like in a spreadsheet, the columns after `z` are `aa`, `ab` and so on.
+//!
+//! | Command                 | Notation       |
+//! |-------------------------|----------------|
+//! | `PlacePiece(c3)`        | `P c3`         |
+//! | `MovePiece(b2, b4)`     | `M b2-b4`      |
+//! | `Blast(d4)`             | `B d4`         |
+//! | `TargetedShoot(e5, g7)` | `S e5xg7`      |
+//! | `UsePower(e5, g7)`      | `A e5>g7`      |
+//! | `NextTurn`              | `/`            |
+//! | `InitPlayer(6)`         | `I 6`          |
+//! | `Undo`, `Redo`          | `Undo`, `Redo` |
+//!
+//! An action that merged pieces is followed by one `+<piece>@<cell>` per merge, e.g.
+//! `M b2-b4 +Q@d4`. The pieces are `P` (Simple), `H` (HorizontalBar), `V` (VerticalBar),
+//! `X` (Cross), `Q` (Queen), `C` (Castle) and `S` (Sniper). Merges follow from the
+//! commands, so the parser skips them.
+//!
+//! [`MoveHistory::lines`] writes a game with one numbered line per turn, e.g.
+//! `3. P c3, M b2-b4 +Q@d4 /`, and [`read_commands`] reads such a text back.
+
+use game_events::actions::compound_events::GameAction;
+use game_model::{GameError, GameResult, Point2, piece::PieceKind};
+
+use crate::game_controller::GameCommand;
+
+/// The notation of a cell, e.g. `c3` for `(2, 2)` and `aa1` for `(26, 0)`.
+pub fn write_cell(at: Point2) -> String {
+    let mut column = vec![];
+    let mut rest = at.x as u16 + 1;
+    while rest > 0 {
+        rest -= 1;
+        column.push((b'a' + (rest % 26) as u8) as char);
+        rest /= 26;
+    }
+
+    format!(
+        "{}{}",
+        column.iter().rev().collect::<String>(),
+        at.y as u16 + 1
+    )
+}
+
+pub fn read_cell(text: &str) -> GameResult<Point2> {
+    let invalid = || GameError::InvalidDefinition(format!("'{}' is not a cell", text));
+
+    let row_start = text
+        .find(|c: char| !c.is_ascii_lowercase())
+        .filter(|start| *start > 0)
+        .ok_or_else(invalid)?;
+    let (column, row) = text.split_at(row_start);
+
+    let mut x: u16 = 0;
+    for letter in column.bytes() {
+        x = x * 26 + (letter - b'a') as u16 + 1;
+        if x > u8::MAX as u16 + 1 {
+            return Err(invalid());
+        }
+    }
+    let row = row
+        .parse::<u16>()
+        .ok()
+        .filter(|row| (1..=u8::MAX as u16 + 1).contains(row))
+        .ok_or_else(invalid)?;
+
+    Ok(Point2::new((x - 1) as u8, (row - 1) as u8))
+}
+
+pub fn write_command(command: &GameCommand) -> String {
+    match command {
+        GameCommand::InitPlayer(start_pieces) => format!("I {}", start_pieces),
+        GameCommand::PlacePiece(at) => format!("P {}", write_cell(*at)),
+        GameCommand::MovePiece(from, to) => format!("M {}-{}", write_cell(*from), write_cell(*to)),
+        GameCommand::Blast(at) => format!("B {}", write_cell(*at)),
+        GameCommand::TargetedShoot(from, to) => {
+            format!("S {}x{}", write_cell(*from), write_cell(*to))
+        }
+        GameCommand::UsePower(from, to) => format!("A {}>{}", write_cell(*from), write_cell(*to)),
+        GameCommand::NextTurn => "/".to_string(),
+        GameCommand::Undo => "Undo".to_string(),
+        GameCommand::Redo => "Redo".to_string(),
+    }
+}
+
+/// Reads one command, ignoring the merges written after it.
+pub fn read_command(text: &str) -> GameResult<GameCommand> {
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .filter(|word| !word.starts_with('+'))
+        .collect();
+
+    let command = match words.as_slice() {
+        ["/"] => GameCommand::NextTurn,
+        ["Undo"] => GameCommand::Undo,
+        ["Redo"] => GameCommand::Redo,
+        ["I", start_pieces] => GameCommand::InitPlayer(start_pieces.parse().map_err(|_| {
+            GameError::InvalidDefinition(format!("'{}' is not a number of pieces", start_pieces))
+        })?),
+        ["P", at] => GameCommand::PlacePiece(read_cell(at)?),
+        ["B", at] => GameCommand::Blast(read_cell(at)?),
+        ["M", cells] => {
+            let (from, to) = read_cell_pair(cells, '-')?;
+            GameCommand::MovePiece(from, to)
+        }
+        ["S", cells] => {
+            let (from, to) = read_cell_pair(cells, 'x')?;
+            GameCommand::TargetedShoot(from, to)
+        }
+        ["A", cells] => {
+            let (from, to) = read_cell_pair(cells, '>')?;
+            GameCommand::UsePower(from, to)
+        }
+        _ => {
+            return Err(GameError::InvalidDefinition(format!(
+                "'{}' is not a command",
+                text.trim()
+            )));
+        }
+    };
+
+    Ok(command)
+}
+
+fn read_cell_pair(text: &str, separator: char) -> GameResult<(Point2, Point2)> {
+    let (from, to) = text.split_once(separator).ok_or_else(|| {
+        GameError::InvalidDefinition(format!(
+            "'{}' are not two cells split by '{}'",
+            text, separator
+        ))
+    })?;
+
+    Ok((read_cell(from)?, read_cell(to)?))
+}
+
+/// Reads the commands of a whole game, e.g. as written by [`MoveHistory::lines`].
+/// Commands are separated by commas or line breaks and turn numbers like `3.` are skipped.
+pub fn read_commands(text: &str) -> GameResult<Vec<GameCommand>> {
+    let text = text.replace(',', " ");
+    let mut words = text
+        .split_whitespace()
+        .filter(|word| !word.starts_with('+') && !is_turn_number(word));
+
+    let mut commands = vec![];
+    while let Some(word) = words.next() {
+        let command = match word {
+            "/" | "Undo" | "Redo" => read_command(word)?,
+            _ => read_command(&format!("{} {}", word, words.next().unwrap_or_default()))?,
+        };
+        commands.push(command);
+    }
+
+    Ok(commands)
+}
+
+fn is_turn_number(word: &str) -> bool {
+    word.strip_suffix('.')
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// The notation of a command and the merges its action caused.
+pub fn write_action(command: &GameCommand, action: &GameAction) -> String {
+    let mut notation = write_command(command);
+
+    let mut merge_event = match action {
+        GameAction::Place(place) => place.merge_events().as_ref(),
+        GameAction::Move(moving) => moving.merge_events().as_ref(),
+        GameAction::Attack(attack) => attack.merge_events().as_ref(),
+        _ => None,
+    };
+    while let Some(event) = merge_event {
+        for merge in event.merges() {
+            notation.push_str(&format!(
+                " +{}@{}",
+                piece_letter(merge.piece().piece_kind),
+                write_cell(*merge.at())
+            ));
+        }
+        merge_event = event.merge_events().as_deref();
+    }
+
+    notation
+}
+
+fn piece_letter(piece_kind: PieceKind) -> char {
+    match piece_kind {
+        PieceKind::Simple => 'P',
+        PieceKind::HorizontalBar => 'H',
+        PieceKind::VerticalBar => 'V',
+        PieceKind::Cross => 'X',
+        PieceKind::Queen => 'Q',
+        PieceKind::Castle => 'C',
+        PieceKind::Sniper => 'S',
+    }
+}
+
+/// The notation of the actions that are in effect, in the order they were played.
+/// Like the [`UndoManager`](game_events::undo_manager::UndoManager), undone actions leave
+/// the history and come back when they are redone.
+#[derive(Debug, Clone, Default)]
+pub struct MoveHistory {
+    entries: Vec<(GameCommand, String)>,
+    undone: Vec<(GameCommand, String)>,
+}
+
+impl MoveHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a new action, which makes the undone ones impossible to redo.
+    pub fn push(&mut self, command: &GameCommand, action: &GameAction) {
+        self.entries.push((*command, write_action(command, action)));
+        self.undone.clear();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(entry) = self.entries.pop() {
+            self.undone.push(entry);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(entry) = self.undone.pop() {
+            self.entries.push(entry);
+        }
+    }
+
+    /// One numbered line per turn, e.g. `3. P c3, M b2-b4 +Q@d4 /`. The unused pieces
+    /// the teams get at the start aren't listed.
+    pub fn lines(&self) -> Vec<String> {
+        write_turns(
+            self.entries
+                .iter()
+                .filter(|(command, _)| !matches!(command, GameCommand::InitPlayer(_))),
+        )
+    }
+}
+
+/// Writes the notations of commands as one numbered line per turn.
+pub(crate) fn write_turns<'a>(
+    entries: impl IntoIterator<Item = &'a (GameCommand, String)>,
+) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+
+    for (command, notation) in entries {
+        if line.is_empty() {
+            line = format!("{}. {}", lines.len() + 1, notation);
+        } else if let GameCommand::NextTurn = command {
+            line = format!("{} {}", line, notation);
+        } else {
+            line = format!("{}, {}", line, notation);
+        }
+
+        if let GameCommand::NextTurn = command {
+            lines.push(std::mem::take(&mut line));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_controller::GameController;
+    use game_model::{
+        game::{Game, Team},
+        piece::{Piece, PieceKind},
+    };
+
+    #[test]
+    fn commands_survive_a_round_trip() {
+        let commands = [
+            GameCommand::InitPlayer(6),
+            GameCommand::PlacePiece(Point2::new(2, 2)),
+            GameCommand::MovePiece(Point2::new(1, 1), Point2::new(1, 3)),
+            GameCommand::Blast(Point2::new(3, 3)),
+            GameCommand::TargetedShoot(Point2::new(4, 4), Point2::new(6, 6)),
+            GameCommand::UsePower(Point2::new(0, 11), Point2::new(7, 0)),
+            GameCommand::NextTurn,
+            GameCommand::Undo,
+            GameCommand::Redo,
+        ];
+
+        let written: Vec<String> = commands.iter().map(write_command).collect();
+        assert_eq!(
+            written,
+            [
+                "I 6", "P c3", "M b2-b4", "B d4", "S e5xg7", "A a12>h1", "/", "Undo", "Redo"
+            ]
+        );
+
+        for (command, notation) in commands.iter().zip(&written) {
+            assert_eq!(read_command(notation).unwrap(), *command);
+        }
+    }
+
+    #[test]
+    fn columns_after_z_take_more_letters() {
+        for (x, column) in [(25, "z"), (26, "aa"), (27, "ab"), (254, "iu"), (255, "iv")] {
+            let at = Point2::new(x, 255);
+            let notation = write_cell(at);
+            assert_eq!(notation, format!("{}256", column));
+            assert_eq!(read_cell(&notation).unwrap(), at);
+        }
+
+        assert!(read_cell("iw1").is_err());
+        assert!(read_cell("a257").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_notation() {
+        assert!(read_command("P c0").is_err());
+        assert!(read_command("P C3").is_err());
+        assert!(read_command("M b2b4").is_err());
+        assert!(read_command("S e5-g7").is_err());
+        assert!(read_command("Q d4").is_err());
+        assert!(read_command("").is_err());
+        assert!(read_commands("P c3, M b2").is_err());
+    }
+
+    #[test]
+    fn history_lists_turns_with_their_merges() {
+        let teams = (0..2)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 3,
+            })
+            .collect();
+        let mut game = Game::new(teams, 8, 8);
+        for (x, y) in [(3, 3), (3, 5), (2, 4), (4, 4)] {
+            game.board
+                .place_piece_at(Piece::new(0, PieceKind::Simple), &Point2::new(x, y))
+                .unwrap();
+        }
+
+        let mut history = MoveHistory::new();
+        let mut play = |game: &mut Game, command: GameCommand| {
+            let action = GameController::execute(game, &command).unwrap();
+            history.push(&command, &action);
+        };
+        play(&mut game, GameCommand::PlacePiece(Point2::new(3, 4)));
+        play(&mut game, GameCommand::NextTurn);
+        play(&mut game, GameCommand::PlacePiece(Point2::new(6, 6)));
+
+        let lines = history.lines();
+        assert_eq!(lines, ["1. P d5 +X@d5 /", "2. P g7"]);
+        assert_eq!(
+            read_commands(&lines.join("\n")).unwrap(),
+            [
+                GameCommand::PlacePiece(Point2::new(3, 4)),
+                GameCommand::NextTurn,
+                GameCommand::PlacePiece(Point2::new(6, 6)),
+            ]
+        );
+
+        history.undo();
+        assert_eq!(history.lines(), ["1. P d5 +X@d5 /"]);
+        history.redo();
+        assert_eq!(history.lines().len(), 2);
+    }
+}
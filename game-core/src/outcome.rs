@@ -0,0 +1,363 @@
+//! End of the game: which team won, or why the game ended in a draw.
+//!
+//! The limits come from the game's [`RuleSet`](game_model::rules::RuleSet). A team wins once it
+//! has enough unused pieces or all other teams are out. The draw rules need the course of the
+//! game, so they are checked by an [`OutcomeTracker`] that sees every executed action.
+
+use game_events::actions::{compound_events::GameAction, merge::MergeCompoundEvent};
+use game_model::{
+    board::CellKind,
+    game::Game,
+    outcome::{DrawReason, GameOutcome, WinReason},
+    piece::Power,
+};
+
+use crate::game_controller::GameController;
+
+/// The winner of the game, if there is one. Unlike the draw rules, this only depends
+/// on the current position.
+///
+/// Teams are out once they are marked as lost, which happens when their last piece is
+/// removed. Teams that simply haven't placed a piece yet, e.g. while the game is set up,
+/// are still in.
+pub fn winner(game: &Game) -> Option<GameOutcome> {
+    let by_unused_pieces: Vec<usize> = game
+        .teams
+        .iter()
+        .filter(|team| team.unused_pieces >= game.rules.winning_unused_pieces)
+        .map(|team| team.id)
+        .collect();
+    let (winners, reason) = if by_unused_pieces.is_empty() {
+        let remaining = game.teams.iter().filter(|team| !team.lost);
+        (
+            remaining.map(|team| team.id).collect(),
+            WinReason::LastTeamStanding,
+        )
+    } else {
+        (by_unused_pieces, WinReason::UnusedPieces)
+    };
+
+    match winners[..] {
+        [team_id] => Some(GameOutcome::Win { team_id, reason }),
+        _ => None,
+    }
+}
+
+/// Follows a game action by action to decide when it is over.
+#[derive(Debug, Clone, Default)]
+pub struct OutcomeTracker {
+    /// Hashes of the positions at the end of every turn
+    positions: Vec<u64>,
+    turns_without_progress: u32,
+    /// Whether each action of the current turn captured or merged a piece
+    progress_in_turn: Vec<bool>,
+    /// What `progress_in_turn` and `turns_without_progress` were when each turn ended,
+    /// so undoing the end of a turn can restore them
+    finished_turns: Vec<(Vec<bool>, u32)>,
+    outcome: Option<GameOutcome>,
+}
+
+impl OutcomeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an action that was just applied to `game`, undos included.
+    pub fn record(&mut self, game: &Game, action: &GameAction) -> Option<GameOutcome> {
+        match action {
+            // Sent after the game was decided, marking the losers changes the winner
+            GameAction::GameOver(_) => return self.outcome,
+            GameAction::FinishTurn(_) => {
+                let progress_in_turn = std::mem::take(&mut self.progress_in_turn);
+                let made_progress = progress_in_turn.iter().any(|progress| *progress);
+                self.finished_turns
+                    .push((progress_in_turn, self.turns_without_progress));
+
+                if made_progress {
+                    self.turns_without_progress = 0;
+                } else {
+                    self.turns_without_progress += 1;
+                }
+                self.positions.push(game.hash);
+            }
+            GameAction::Undo(undo) => {
+                if let GameAction::FinishTurn(_) = *undo.undone {
+                    self.positions.pop();
+                    if let Some((progress_in_turn, turns_without_progress)) =
+                        self.finished_turns.pop()
+                    {
+                        self.progress_in_turn = progress_in_turn;
+                        self.turns_without_progress = turns_without_progress;
+                    }
+                } else {
+                    self.progress_in_turn.pop();
+                }
+            }
+            _ => self.progress_in_turn.push(makes_progress(action)),
+        }
+
+        self.outcome = winner(game).or_else(|| self.draw(game).map(GameOutcome::Draw));
+        self.outcome
+    }
+
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        self.outcome
+    }
+
+    fn draw(&self, game: &Game) -> Option<DrawReason> {
+        let repetitions = self.positions.last().map_or(0, |last| {
+            self.positions
+                .iter()
+                .filter(|position| *position == last)
+                .count()
+        });
+
+        if repetitions >= game.rules.repetitions as usize {
+            Some(DrawReason::Repetition)
+        } else if self.turns_without_progress >= game.rules.no_progress_turns {
+            Some(DrawReason::NoProgress)
+        } else if is_stalemate(game) {
+            Some(DrawReason::Stalemate)
+        } else {
+            None
+        }
+    }
+}
+
+/// The action that ends the game, it marks every team but the winner as lost.
+pub fn game_over(game: &Game, outcome: GameOutcome) -> GameAction {
+    let mut game_over = GameAction::game_over(outcome);
+
+    if let Some(winner) = outcome.winner() {
+        game.teams
+            .iter()
+            .filter(|team| !team.lost && team.id != winner)
+            .for_each(|team| {
+                game_over.eliminate_team(team.id);
+            });
+    }
+
+    game_over.build()
+}
+
+pub(crate) fn makes_progress(action: &GameAction) -> bool {
+    match action {
+        GameAction::Place(place) => merged(place.merge_events()),
+        GameAction::Move(moving) => {
+            moving.captured_piece().is_some() || merged(moving.merge_events())
+        }
+        GameAction::Attack(attack) => {
+            !attack.removed_pieces().is_empty()
+                || *attack.power() == Power::Convert
+                || merged(attack.merge_events())
+        }
+        GameAction::Undo(_) | GameAction::FinishTurn(_) | GameAction::GameOver(_) => false,
+    }
+}
+
+/// Every flushed action carries a merge event, it only counts if something merged.
+fn merged(merge_events: &Option<MergeCompoundEvent>) -> bool {
+    merge_events
+        .as_ref()
+        .is_some_and(|merge| !merge.merges().is_empty())
+}
+
+/// Whether no team that is still in the game could do anything but end its turn, even
+/// with an unused piece and all its pieces refreshed.
+fn is_stalemate(game: &Game) -> bool {
+    // Any team could place its unused piece on a free floor cell
+    let mut free_floor = false;
+    game.board.for_each_cell(|cell| {
+        free_floor |= cell.piece.is_none() && cell.kind == CellKind::Floor;
+    });
+    if free_floor {
+        return false;
+    }
+
+    let mut probe = game.clone();
+    probe.board.for_each_cell_mut(|cell| {
+        if let Some(piece) = &mut cell.piece {
+            piece.exhaustion.reset();
+        }
+    });
+
+    game.teams.iter().filter(|team| !team.lost).all(|team| {
+        probe.current_team_index = team.id;
+        probe.teams[team.id].unused_pieces = team.unused_pieces.max(1);
+
+        !GameController::has_legal_command(&probe)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board_event_consumer::BoardEventConsumer, game_controller::GameCommand};
+    use game_model::{
+        Point2,
+        board::Board,
+        game::Team,
+        piece::{Piece, PieceKind},
+    };
+
+    /// A game with one piece of each team on the board.
+    fn setup_game(board: Board, pieces: [(usize, Point2); 2]) -> Game {
+        let teams = (0..2)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 0,
+            })
+            .collect();
+
+        let mut game = Game::new(teams, board.w, board.h).with_board(board);
+        for (team_id, at) in pieces {
+            let mut piece = Piece::new(team_id, PieceKind::Simple);
+            piece.exhaustion.reset();
+            game.board.place_piece_at(piece, &at).unwrap();
+        }
+        game.rehash();
+        game
+    }
+
+    fn execute(game: &mut Game, tracker: &mut OutcomeTracker, command: GameCommand) {
+        let action = GameController::execute(game, &command).unwrap();
+        tracker.record(game, &action);
+    }
+
+    #[test]
+    fn collecting_unused_pieces_wins() {
+        let mut game = setup_game(
+            Board::new(8, 8),
+            [(0, Point2::new(1, 1)), (1, Point2::new(6, 6))],
+        );
+        assert_eq!(winner(&game), None);
+
+        game.teams[1].unused_pieces = game.rules.winning_unused_pieces;
+        assert_eq!(
+            winner(&game),
+            Some(GameOutcome::Win {
+                team_id: 1,
+                reason: WinReason::UnusedPieces
+            })
+        );
+    }
+
+    #[test]
+    fn repeated_position_draws() {
+        let mut game = setup_game(
+            Board::new(8, 8),
+            [(0, Point2::new(1, 1)), (1, Point2::new(6, 6))],
+        );
+        let mut tracker = OutcomeTracker::new();
+
+        for turn in 1..game.rules.repetitions * 2 {
+            assert_eq!(tracker.outcome(), None, "turn {}", turn);
+            execute(&mut game, &mut tracker, GameCommand::NextTurn);
+
+            // As if the gained pieces had been placed and lost again
+            game.teams
+                .iter_mut()
+                .for_each(|team| team.unused_pieces = 0);
+            game.rehash();
+        }
+
+        assert_eq!(
+            tracker.outcome(),
+            Some(GameOutcome::Draw(DrawReason::Repetition))
+        );
+    }
+
+    #[test]
+    fn capture_resets_turns_without_progress() {
+        let mut game = setup_game(
+            Board::new(8, 8),
+            [(0, Point2::new(1, 1)), (1, Point2::new(2, 2))],
+        );
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(6, 6))
+            .unwrap();
+        game.rehash();
+        let mut tracker = OutcomeTracker::new();
+
+        tracker.turns_without_progress = game.rules.no_progress_turns - 1;
+        execute(
+            &mut game,
+            &mut tracker,
+            GameCommand::MovePiece(Point2::new(1, 1), Point2::new(2, 2)),
+        );
+        execute(&mut game, &mut tracker, GameCommand::NextTurn);
+        assert_eq!(tracker.turns_without_progress, 0);
+        assert_eq!(tracker.outcome(), None);
+
+        tracker.turns_without_progress = game.rules.no_progress_turns - 2;
+        execute(
+            &mut game,
+            &mut tracker,
+            GameCommand::MovePiece(Point2::new(6, 6), Point2::new(6, 5)),
+        );
+        execute(&mut game, &mut tracker, GameCommand::NextTurn);
+        assert_eq!(tracker.outcome(), None);
+        execute(&mut game, &mut tracker, GameCommand::NextTurn);
+        assert_eq!(
+            tracker.outcome(),
+            Some(GameOutcome::Draw(DrawReason::NoProgress))
+        );
+    }
+
+    #[test]
+    fn undoing_the_end_of_a_turn_restores_its_progress() {
+        let mut game = setup_game(
+            Board::new(8, 8),
+            [(0, Point2::new(1, 1)), (1, Point2::new(2, 2))],
+        );
+        game.board
+            .place_piece_at(Piece::new(1, PieceKind::Simple), &Point2::new(6, 6))
+            .unwrap();
+        game.rehash();
+        let mut tracker = OutcomeTracker::new();
+        tracker.turns_without_progress = 3;
+
+        execute(
+            &mut game,
+            &mut tracker,
+            GameCommand::MovePiece(Point2::new(1, 1), Point2::new(2, 2)),
+        );
+        let finish_turn = GameController::execute(&mut game, &GameCommand::NextTurn).unwrap();
+        tracker.record(&game, &finish_turn);
+        assert_eq!(tracker.turns_without_progress, 0);
+
+        let undo = finish_turn.anti_event();
+        BoardEventConsumer::apply(&mut game, &undo).unwrap();
+        tracker.record(&game, &undo);
+        assert_eq!(game.current_team_index, 0);
+        assert_eq!(tracker.turns_without_progress, 3);
+        assert!(tracker.positions.is_empty());
+        assert_eq!(tracker.progress_in_turn, vec![true]);
+    }
+
+    #[test]
+    fn board_without_moves_is_a_stalemate() {
+        let mut game = setup_game(
+            Board::from_map(".#.").unwrap(),
+            [(0, Point2::new(0, 0)), (1, Point2::new(2, 0))],
+        );
+        let mut tracker = OutcomeTracker::new();
+
+        execute(&mut game, &mut tracker, GameCommand::NextTurn);
+        assert_eq!(
+            tracker.outcome(),
+            Some(GameOutcome::Draw(DrawReason::Stalemate))
+        );
+    }
+
+    #[test]
+    fn free_floor_is_no_stalemate() {
+        let game = setup_game(
+            Board::from_map(".#..").unwrap(),
+            [(0, Point2::new(0, 0)), (1, Point2::new(2, 0))],
+        );
+
+        assert!(!is_stalemate(&game));
+    }
+}
@@ -0,0 +1,507 @@
+//! Self-describing game records, modelled on chess' PGN.
+//!
+//! A record starts with `[Key "Value"]` headers, followed by an empty line and the moves
+//! in [`notation`](crate::notation), one numbered line per turn:
+//!
+//! ```text
+//! [Event "Club championship, round 3"]
+//! [Date "2026.10.17"]
+//! [Teams "2"]
+//! [Team0 "Red"]
+//! [Team1 "Yellow"]
+//! [Board "8x8"]
+//! [Map "~..~/..../..#./~..~"]
+//! [Rules "{\"start_pieces\":6,\"pieces_per_turn\":2,...}"]
+//! [Result "1"]
+//! [Termination "LastTeamStanding"]
+//!
+//! 1. I 6, I 6, P c3 /
+//! 2. P f6 /
+//! ```
+//!
+//! `Map` is the [`Board::from_map`] map with its rows separated by `/`, and `Pieces` and
+//! `Patterns` are the JSON piece and pattern tables, written and checked like the files the
+//! game loads them from. These three are only written when they differ from a plain board
+//! and the built-in tables.
+//!
+//! `Result` is the index of the winning team, `draw` or `*` for a game that is still going
+//! on, and `Termination` names the [`WinReason`] or [`DrawReason`]. Values escape `"` and
+//! `\` with a backslash and unknown headers are ignored.
+//!
+//! [`GameRecord::parse`] also reads the JSON command lists older versions exported, with
+//! their `//` comment lines.
+
+use std::fmt::{Display, Formatter};
+
+use game_model::{
+    GameError, GameResult,
+    board::Board,
+    game::{Game, Team},
+    outcome::{DrawReason, GameOutcome, WinReason},
+    pattern::{Pattern, PatternDefinition},
+    piece::PieceDefinition,
+    rules::RuleSet,
+};
+use nanoserde::{DeJson, SerJson};
+
+use crate::{
+    game_controller::GameCommand,
+    notation::{read_commands, write_command, write_turns},
+};
+
+/// The `Date` of a record when it isn't known.
+pub const UNKNOWN_DATE: &str = "????.??.??";
+
+/// A played game with everything needed to replay it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    /// What the game was, e.g. a tournament round or why it was exported
+    pub event: String,
+    /// When the game was played, as `YYYY.MM.DD`
+    pub date: String,
+    /// The number of teams that played
+    pub teams: usize,
+    /// The names of the teams by team id, empty if the record doesn't name them
+    pub team_names: Vec<String>,
+    /// The board the game started on, without pieces
+    pub board: Board,
+    pub rules: RuleSet,
+    pub pieces: Vec<PieceDefinition>,
+    pub patterns: Vec<Pattern>,
+    /// How the game ended, `None` while it is still going on
+    pub outcome: Option<GameOutcome>,
+    /// Every command in the order it was executed, from the set-up of the teams on
+    pub commands: Vec<GameCommand>,
+}
+
+impl GameRecord {
+    /// A record of the `commands` played on `game`'s board with `game`'s rules.
+    pub fn new(game: &Game, team_names: Vec<String>, commands: Vec<GameCommand>) -> Self {
+        GameRecord {
+            event: String::new(),
+            date: UNKNOWN_DATE.to_string(),
+            teams: game.teams.len(),
+            team_names,
+            board: game.board.layout(),
+            rules: game.rules,
+            pieces: game.pieces.clone(),
+            patterns: game.patterns.clone(),
+            outcome: None,
+            commands,
+        }
+    }
+
+    pub fn with_event(mut self, event: &str) -> Self {
+        self.event = event.to_string();
+        self
+    }
+
+    pub fn with_date(mut self, date: String) -> Self {
+        self.date = date;
+        self
+    }
+
+    pub fn with_outcome(mut self, outcome: Option<GameOutcome>) -> Self {
+        self.outcome = outcome;
+        self
+    }
+
+    /// The game as it was before the first command, with teams that haven't got their
+    /// unused pieces yet.
+    pub fn game(&self) -> Game {
+        let teams = (0..self.teams)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 0,
+            })
+            .collect();
+
+        Game::new(teams, self.board.w, self.board.h)
+            .with_board(self.board.clone())
+            .with_rules(self.rules)
+            .with_pieces(self.pieces.clone())
+            .with_patterns(self.patterns.clone())
+    }
+
+    /// Reads a record, or a JSON command list as exported by older versions. Such a list
+    /// is taken as a game of two teams on an 8x8 board with the default rules and its
+    /// first comment as the event.
+    pub fn parse(text: &str) -> GameResult<GameRecord> {
+        let (comments, content): (Vec<&str>, Vec<&str>) = text
+            .lines()
+            .partition(|line| line.trim_start().starts_with("//"));
+        let content = content.join("\n");
+
+        let mut record = GameRecord {
+            event: String::new(),
+            date: UNKNOWN_DATE.to_string(),
+            teams: 2,
+            team_names: vec![],
+            board: Board::new(8, 8),
+            rules: RuleSet::default(),
+            pieces: PieceDefinition::default_definitions(),
+            patterns: Pattern::default_patterns(),
+            outcome: None,
+            commands: vec![],
+        };
+
+        if is_command_list(&content) {
+            record.event = comments
+                .first()
+                .map(|comment| comment.trim_start()[2..].trim().to_string())
+                .unwrap_or_default();
+            record.commands = DeJson::deserialize_json(&content).map_err(|e| {
+                GameError::InvalidDefinition(format!("Can't parse exported game: {}", e))
+            })?;
+            return Ok(record);
+        }
+
+        let mut teams = None;
+        let mut map = None;
+        let mut result = None;
+        let mut termination = None;
+        let mut moves = vec![];
+        for line in content.lines().map(str::trim) {
+            let Some(header) = line.strip_prefix('[').and_then(|h| h.strip_suffix(']')) else {
+                moves.push(line);
+                continue;
+            };
+
+            let (key, value) = read_header(header)?;
+            match key {
+                "Event" => record.event = value,
+                "Date" => record.date = value,
+                "Teams" => {
+                    teams = Some(value.parse().map_err(|_| {
+                        GameError::InvalidDefinition(format!(
+                            "'{}' is not a number of teams",
+                            value
+                        ))
+                    })?);
+                }
+                "Board" => {
+                    let (width, height) = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .ok_or_else(|| {
+                            GameError::InvalidDefinition(format!("'{}' is not a board size", value))
+                        })?;
+                    record.board = Board::new(width, height);
+                }
+                "Map" => map = Some(Board::from_map(&value.replace('/', "\n"))?),
+                "Rules" => record.rules = RuleSet::parse(&value)?,
+                "Pieces" => record.pieces = PieceDefinition::parse_all(&value)?,
+                "Patterns" => record.patterns = Pattern::parse_all(&value)?,
+                "Result" => result = Some(value),
+                "Termination" => termination = Some(value),
+                _ => {
+                    if let Some(team_id) = key
+                        .strip_prefix("Team")
+                        .and_then(|id| id.parse::<usize>().ok())
+                    {
+                        if record.team_names.len() <= team_id {
+                            record.team_names.resize(team_id + 1, String::new());
+                        }
+                        record.team_names[team_id] = value;
+                    }
+                }
+            }
+        }
+
+        if let Some(map) = map {
+            record.board = map;
+        }
+        record.teams = teams.unwrap_or(record.team_names.len().max(2));
+        record.outcome = read_outcome(result.as_deref(), termination.as_deref())?;
+        record.commands = read_commands(&moves.join("\n"))?;
+
+        Ok(record)
+    }
+}
+
+/// Whether `content` is a JSON array rather than a record starting with a header.
+fn is_command_list(content: &str) -> bool {
+    let mut chars = content.trim_start().chars();
+    chars.next() == Some('[')
+        && !chars
+            .find(|c| !c.is_whitespace())
+            .is_some_and(|c| c.is_ascii_alphabetic())
+}
+
+fn read_header(header: &str) -> GameResult<(&str, String)> {
+    let invalid = || GameError::InvalidDefinition(format!("'[{}]' is not a header", header));
+
+    let (key, value) = header.split_once(' ').ok_or_else(invalid)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        unescaped.push(if c == '\\' {
+            chars.next().ok_or_else(invalid)?
+        } else {
+            c
+        });
+    }
+
+    Ok((key, unescaped))
+}
+
+fn write_header(f: &mut Formatter<'_>, key: &str, value: &str) -> std::fmt::Result {
+    writeln!(
+        f,
+        "[{} \"{}\"]",
+        key,
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+fn read_outcome(
+    result: Option<&str>,
+    termination: Option<&str>,
+) -> GameResult<Option<GameOutcome>> {
+    let invalid = || {
+        GameError::InvalidDefinition(format!(
+            "'{}' ended by '{}' is not a result",
+            result.unwrap_or_default(),
+            termination.unwrap_or_default()
+        ))
+    };
+
+    let outcome = match (result, termination) {
+        (None | Some("*"), _) => None,
+        (Some("draw"), Some("Repetition")) => Some(GameOutcome::Draw(DrawReason::Repetition)),
+        (Some("draw"), Some("NoProgress")) => Some(GameOutcome::Draw(DrawReason::NoProgress)),
+        (Some("draw"), Some("Stalemate")) => Some(GameOutcome::Draw(DrawReason::Stalemate)),
+        (Some(team_id), Some(reason)) => {
+            let team_id = team_id.parse().map_err(|_| invalid())?;
+            let reason = match reason {
+                "UnusedPieces" => WinReason::UnusedPieces,
+                "LastTeamStanding" => WinReason::LastTeamStanding,
+                _ => return Err(invalid()),
+            };
+            Some(GameOutcome::Win { team_id, reason })
+        }
+        _ => return Err(invalid()),
+    };
+
+    Ok(outcome)
+}
+
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write_header(f, "Event", &self.event)?;
+        write_header(f, "Date", &self.date)?;
+        write_header(f, "Teams", &self.teams.to_string())?;
+        for (team_id, name) in self.team_names.iter().enumerate() {
+            write_header(f, &format!("Team{}", team_id), name)?;
+        }
+        write_header(f, "Board", &format!("{}x{}", self.board.w, self.board.h))?;
+        if self.board != Board::new(self.board.w, self.board.h) {
+            let map = self.board.to_map();
+            write_header(f, "Map", &map.lines().collect::<Vec<_>>().join("/"))?;
+        }
+        write_header(f, "Rules", &self.rules.serialize_json())?;
+        if self.pieces != PieceDefinition::default_definitions() {
+            write_header(f, "Pieces", &self.pieces.serialize_json())?;
+        }
+        if self.patterns != Pattern::default_patterns() {
+            let definitions: Vec<PatternDefinition> =
+                self.patterns.iter().map(Pattern::definition).collect();
+            write_header(f, "Patterns", &definitions.serialize_json())?;
+        }
+
+        let (result, termination) = match self.outcome {
+            None => ("*".to_string(), None),
+            Some(GameOutcome::Win { team_id, reason }) => {
+                (team_id.to_string(), Some(format!("{:?}", reason)))
+            }
+            Some(GameOutcome::Draw(reason)) => ("draw".to_string(), Some(format!("{:?}", reason))),
+        };
+        write_header(f, "Result", &result)?;
+        if let Some(termination) = termination {
+            write_header(f, "Termination", &termination)?;
+        }
+
+        writeln!(f)?;
+        let entries: Vec<(GameCommand, String)> = self
+            .commands
+            .iter()
+            .map(|command| (*command, write_command(command)))
+            .collect();
+        for line in write_turns(&entries) {
+            writeln!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The `Date` of a record made `unix_seconds` after the start of 1970 (UTC).
+pub fn format_date(unix_seconds: u64) -> String {
+    // Converts days to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = unix_seconds / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_model::{Point2, piece::PieceKind};
+
+    fn game() -> Game {
+        let teams = (0..2)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 0,
+            })
+            .collect();
+        Game::new(teams, 10, 8)
+    }
+
+    #[test]
+    fn records_survive_a_round_trip() {
+        let mut game = game();
+        game.rules.allow_undo = false;
+        let record = GameRecord::new(
+            &game,
+            vec!["Red \"the bold\"".to_string(), "Yellow".to_string()],
+            vec![
+                GameCommand::InitPlayer(6),
+                GameCommand::InitPlayer(6),
+                GameCommand::PlacePiece(Point2::new(2, 2)),
+                GameCommand::NextTurn,
+                GameCommand::PlacePiece(Point2::new(7, 5)),
+                GameCommand::Undo,
+                GameCommand::TargetedShoot(Point2::new(7, 5), Point2::new(2, 2)),
+            ],
+        )
+        .with_event("Final")
+        .with_date(format_date(1_792_195_200))
+        .with_outcome(Some(GameOutcome::Win {
+            team_id: 1,
+            reason: WinReason::LastTeamStanding,
+        }));
+
+        let text = record.to_string();
+        assert!(text.starts_with("[Event \"Final\"]\n[Date \"2026.10.17\"]\n"));
+        assert!(text.contains("[Team0 \"Red \\\"the bold\\\"\"]\n"));
+        assert!(text.contains("[Teams \"2\"]\n"));
+        assert!(text.contains("[Board \"10x8\"]\n"));
+        assert!(
+            !text.contains("[Map ") && !text.contains("[Pieces ") && !text.contains("[Patterns ")
+        );
+        assert!(text.contains("[Result \"1\"]\n[Termination \"LastTeamStanding\"]\n"));
+        assert!(text.ends_with("\n\n1. I 6, I 6, P c3 /\n2. P h6, Undo, S h6xc3\n"));
+
+        assert_eq!(GameRecord::parse(&text).unwrap(), record);
+    }
+
+    #[test]
+    fn records_keep_the_setup_of_their_game() {
+        let teams = (0..3)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 0,
+            })
+            .collect();
+        let mut pieces = PieceDefinition::default_definitions();
+        pieces[0].shield = !pieces[0].shield;
+        let mut patterns = Pattern::default_patterns();
+        patterns.pop();
+        let mut game = Game::new(teams, 4, 3)
+            .with_board(Board::from_map("~..~\n.#..\n....").unwrap())
+            .with_pieces(pieces)
+            .with_patterns(patterns);
+        game.board
+            .place_piece_at(game.new_piece(0, PieceKind::Simple), &Point2::new(0, 2))
+            .unwrap();
+        let record = GameRecord::new(&game, vec![], vec![GameCommand::InitPlayer(6)]);
+
+        let text = record.to_string();
+        assert!(text.contains("[Teams \"3\"]\n[Board \"4x3\"]\n[Map \"~..~/.#../....\"]\n"));
+        assert!(text.contains("[Pieces ") && text.contains("[Patterns "));
+
+        let parsed = GameRecord::parse(&text).unwrap();
+        assert_eq!(parsed, record);
+
+        let replayed = parsed.game();
+        assert_eq!(replayed.num_teams(), 3);
+        assert_eq!(replayed.board, game.board.layout());
+        assert_eq!(replayed.pieces, game.pieces);
+        assert_eq!(replayed.patterns, game.patterns);
+    }
+
+    #[test]
+    fn reads_exported_command_lists() {
+        let record = GameRecord::parse(
+            "// Undo before making a move\n[{\"InitPlayer\":[6]},\n  \"NextTurn\",\"Undo\"]",
+        )
+        .unwrap();
+
+        assert_eq!(record.event, "Undo before making a move");
+        assert_eq!(record.teams, 2);
+        assert_eq!(record.board, Board::new(8, 8));
+        assert_eq!(record.outcome, None);
+        assert_eq!(
+            record.commands,
+            [
+                GameCommand::InitPlayer(6),
+                GameCommand::NextTurn,
+                GameCommand::Undo
+            ]
+        );
+        assert!(GameRecord::parse("[]").unwrap().commands.is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_records() {
+        assert!(GameRecord::parse("[Board \"big\"]").is_err());
+        assert!(GameRecord::parse("[Teams \"many\"]").is_err());
+        assert!(GameRecord::parse("[Map \"../.x\"]").is_err());
+        assert!(GameRecord::parse("[Pieces \"[{}]\"]").is_err());
+        assert!(GameRecord::parse("[Patterns \"[{}]\"]").is_err());
+        // A pattern without an own piece or without any cell would make the merges panic
+        let patterns = |grid: &str| {
+            GameRecord::parse(&format!(
+                r#"[Patterns "[{{\"turn_into\":\"Queen\",\"result\":{{\"x\":0,\"y\":0}},\"grid\":[{}]}}]"]"#,
+                grid
+            ))
+        };
+        assert!(patterns(r#"\"o\""#).is_ok());
+        assert!(patterns(r#"\"..\""#).is_err());
+        assert!(patterns("").is_err());
+        assert!(GameRecord::parse("[Result \"draw\"]").is_err());
+        assert!(GameRecord::parse("[Result \"0\"]\n[Termination \"Resignation\"]").is_err());
+        assert!(GameRecord::parse("[Event Final]").is_err());
+        assert!(GameRecord::parse("[Event \"Final\"]\n\n1. P c3, Q d4").is_err());
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(format_date(0), "1970.01.01");
+        assert_eq!(format_date(951_782_400), "2000.02.29");
+        assert_eq!(format_date(1_792_195_200 + 86_399), "2026.10.17");
+    }
+}
@@ -0,0 +1,142 @@
+//! The commands that start a game: every team gets its unused pieces and one piece on the board.
+
+use game_model::{GameError, GameResult, Point2, board::Board, game::Game};
+
+use crate::game_controller::GameCommand;
+
+/// Commands that give every team its start pieces and place the first one of each.
+///
+/// Fails if the board is smaller than [`Board::MIN_SIZE`] or has no separate floor cell
+/// for the first piece of every team.
+pub fn set_up_pieces(team_count: usize, game: &Game) -> GameResult<Vec<GameCommand>> {
+    // Corners, moved a quarter of the board towards the center. Each pair of
+    // teams starts in opposite corners so no team has a better start.
+    let (w, h) = (game.board.w, game.board.h);
+    let too_small = || GameError::BoardTooSmall(w, h);
+    if w < Board::MIN_SIZE || h < Board::MIN_SIZE {
+        return Err(too_small());
+    }
+
+    let (inset_x, inset_y) = (w / 4, h / 4);
+    let mut start_points = vec![];
+    for corner in [
+        Point2::new(inset_x, inset_y),
+        Point2::new(w - 1 - inset_x, inset_y),
+    ] {
+        let first = nearest_floor(&game.board, corner);
+        // Mirror the first start point if possible
+        let opposite = Point2::new(w - 1 - first.x, h - 1 - first.y);
+        let second = if game.board.is_floor(&opposite) {
+            opposite
+        } else {
+            nearest_floor(&game.board, Point2::new(w - 1 - corner.x, h - 1 - corner.y))
+        };
+        start_points.push(first);
+        start_points.push(second);
+    }
+    start_points.truncate(team_count);
+    for (i, start_point) in start_points.iter().enumerate() {
+        if !game.board.is_floor(start_point) || start_points[..i].contains(start_point) {
+            return Err(too_small());
+        }
+    }
+
+    let mut events = vec![];
+
+    for _ in 0..team_count {
+        events.push(GameCommand::InitPlayer(game.rules.start_pieces));
+    }
+
+    for start_point in start_points {
+        events.push(GameCommand::PlacePiece(start_point));
+        events.push(GameCommand::NextTurn);
+
+        /*let mut piece = Piece::new(team_id, PieceKind::Simple);
+        piece.exhaustion.reset();
+        finish_turn.place_piece(target_point, piece);
+
+        for _ in 0..start_pieces {
+            finish_turn.add_unused_piece(team_id);
+        }
+
+        let compound_event = finish_turn.build();
+        BoardEventConsumer::flush_unsafe(&mut game_ref.clone(), &compound_event);
+        events.push(compound_event);*/
+    }
+
+    Ok(events)
+}
+
+/// Reads a board as the binaries take it with `--board`: `<width>x<height>` for a board
+/// of floor cells or `arena` for [`Board::cross_arena`].
+pub fn parse_board(value: &str) -> GameResult<Board> {
+    if value == "arena" {
+        return Ok(Board::cross_arena());
+    }
+
+    let (width, height) = value
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .ok_or_else(|| GameError::InvalidDefinition(format!("Invalid board '{}'", value)))?;
+
+    Board::with_size(width, height)
+}
+
+/// The floor cell closest to `target`, so start pieces never land on walls or holes.
+fn nearest_floor(board: &Board, target: Point2) -> Point2 {
+    let mut nearest = None;
+    board.for_each_cell(|cell| {
+        if !board.is_floor(&cell.point) {
+            return;
+        }
+        let dx = cell.point.x as i32 - target.x as i32;
+        let dy = cell.point.y as i32 - target.y as i32;
+        let distance = dx * dx + dy * dy;
+        if nearest.is_none_or(|(_, nearest_distance)| distance < nearest_distance) {
+            nearest = Some((cell.point, distance));
+        }
+    });
+
+    nearest.map(|(point, _)| point).unwrap_or(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_model::game::Team;
+
+    fn setup_game(num_teams: usize, board: Board) -> Game {
+        let teams = (0..num_teams)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 0,
+            })
+            .collect();
+        Game::new(teams, board.w, board.h).with_board(board)
+    }
+
+    #[test]
+    fn parses_board_options() {
+        assert_eq!(parse_board("10x12"), Ok(Board::new(10, 12)));
+        assert_eq!(parse_board("arena"), Ok(Board::cross_arena()));
+        assert_eq!(parse_board("0x8"), Err(GameError::BoardTooSmall(0, 8)));
+        assert!(parse_board("big").is_err());
+        assert!(parse_board("8x").is_err());
+    }
+
+    #[test]
+    fn rejects_boards_without_room_for_every_team() {
+        let game = setup_game(4, Board::new(2, 2));
+        assert_eq!(set_up_pieces(4, &game).unwrap().len(), 12);
+
+        for (w, h) in [(0, 8), (8, 0), (1, 8)] {
+            let game = setup_game(2, Board::new(w, h));
+            assert_eq!(set_up_pieces(2, &game), Err(GameError::BoardTooSmall(w, h)));
+        }
+
+        let game = setup_game(4, Board::from_map("#.\n.#").unwrap());
+        assert_eq!(set_up_pieces(2, &game).unwrap().len(), 6);
+        assert_eq!(set_up_pieces(4, &game), Err(GameError::BoardTooSmall(2, 2)));
+    }
+}
@@ -10,22 +10,36 @@ use crate::{
 };
 use derive_getters::Getters;
 use game_model::{
-    Point2,
-    piece::{EffectKind, Exhaustion, Piece, PieceKind},
+    GameResult, Point2,
+    piece::{CellEffect, Exhaustion, Piece, PieceKind, Power},
 };
 use nanoserde::{DeJson, SerJson};
 
 use super::compound_events::FlushResult;
 
+/// A piece a power takes off the board and sets down again, elsewhere or changed.
+#[derive(Debug, Copy, Clone, SerJson, DeJson, Getters)]
+pub struct Displacement {
+    from: Point2,
+    piece: Piece,
+    to: Point2,
+    piece_afterwards: Piece,
+}
+
+/// The use of a piece's power. Blasts and shots remove pieces, the other powers
+/// displace them.
 #[derive(Debug, Clone, SerJson, DeJson, Getters)]
 pub struct AttackCompoundEvent {
     piece_kind: PieceKind,
+    power: Power,
     attacking_piece_pos: Point2,
     exhaustion_before: Exhaustion,
     exhaustion_afterwards: Exhaustion,
     removed_pieces: Vec<(Point2, Piece)>,
-    added_effects: Vec<Point2>,
-    removed_effects: Vec<Point2>,
+    displaced_pieces: Vec<Displacement>,
+    eliminated_teams: Vec<usize>,
+    added_effects: Vec<(Point2, CellEffect)>,
+    removed_effects: Vec<(Point2, CellEffect)>,
 
     merge_events: Option<MergeCompoundEvent>,
 }
@@ -35,16 +49,19 @@ pub struct AttackBuilder {
 }
 
 impl AttackBuilder {
-    pub fn new(piece: &Piece, piece_pos: Point2) -> Self {
+    pub fn new(piece: &Piece, piece_pos: Point2, power: Power) -> Self {
         let mut exhaustion_afterwards = piece.exhaustion;
         exhaustion_afterwards.on_attack();
         AttackBuilder {
             event: AttackCompoundEvent {
                 piece_kind: piece.piece_kind,
+                power,
                 attacking_piece_pos: piece_pos,
                 exhaustion_before: piece.exhaustion,
                 exhaustion_afterwards,
                 removed_pieces: vec![],
+                displaced_pieces: vec![],
+                eliminated_teams: vec![],
                 removed_effects: vec![],
                 added_effects: vec![],
 
@@ -57,6 +74,31 @@ impl AttackBuilder {
         self.event.removed_pieces.push((point, piece));
         self
     }
+
+    /// Takes `piece` from `from` and sets down `piece_afterwards` at `to`. All displaced pieces
+    /// are taken off before the first one is set down, so pieces can trade places. The acting
+    /// piece is already exhausted when it is taken off.
+    pub fn displace_piece(
+        &mut self,
+        from: Point2,
+        piece: Piece,
+        to: Point2,
+        piece_afterwards: Piece,
+    ) -> &mut Self {
+        self.event.displaced_pieces.push(Displacement {
+            from,
+            piece,
+            to,
+            piece_afterwards,
+        });
+        self
+    }
+
+    /// Marks a team as lost because this attack removes its last pieces.
+    pub fn eliminate_team(&mut self, team_id: usize) -> &mut Self {
+        self.event.eliminated_teams.push(team_id);
+        self
+    }
 }
 
 impl CompoundEvent for AttackCompoundEvent {
@@ -66,6 +108,9 @@ impl CompoundEvent for AttackCompoundEvent {
         for (at, piece) in self.removed_pieces.iter() {
             all_events.push(AtomicEvent::Remove(*at, *piece));
         }
+        for team_id in self.eliminated_teams.iter() {
+            all_events.push(AtomicEvent::EliminateTeam(*team_id));
+        }
 
         all_events.push(AtomicEvent::ChangeExhaustion(
             self.exhaustion_before,
@@ -73,11 +118,21 @@ impl CompoundEvent for AttackCompoundEvent {
             self.attacking_piece_pos,
         ));
 
-        for effect in self.removed_effects.iter() {
-            all_events.push(AtomicEvent::RemoveEffect(EffectKind::Protection, *effect));
+        for displacement in self.displaced_pieces.iter() {
+            all_events.push(AtomicEvent::Remove(displacement.from, displacement.piece));
         }
-        for effect in self.added_effects.iter() {
-            all_events.push(AtomicEvent::AddEffect(EffectKind::Protection, *effect));
+        for displacement in self.displaced_pieces.iter() {
+            all_events.push(AtomicEvent::Place(
+                displacement.to,
+                displacement.piece_afterwards,
+            ));
+        }
+
+        for (at, effect) in self.removed_effects.iter() {
+            all_events.push(AtomicEvent::RemoveEffect(*effect, *at));
+        }
+        for (at, effect) in self.added_effects.iter() {
+            all_events.push(AtomicEvent::AddEffect(*effect, *at));
         }
 
         if let Some(merge_events) = &self.merge_events {
@@ -94,9 +149,12 @@ impl CompoundEventBuilder for AttackBuilder {
     }
 
     fn build(self) -> GameAction {
-        if self.event.removed_pieces.is_empty() {
+        if self.event.removed_pieces.is_empty()
+            && self.event.displaced_pieces.is_empty()
+            && self.event.added_effects.is_empty()
+        {
             panic!(
-                "Can't build an AttackCompoundEvent without any removed pieces: {:?}",
+                "Can't build an AttackCompoundEvent that neither removes nor displaces pieces nor adds effects: {:?}",
                 self.event
             );
         }
@@ -104,27 +162,51 @@ impl CompoundEventBuilder for AttackBuilder {
         GameAction::Attack(self.event)
     }
 
-    fn flush(self: Box<Self>, consumer: &mut dyn FnMut(&AtomicEvent)) -> FlushResult {
-        self.event.get_events().iter().for_each(consumer);
+    fn flush(
+        self: Box<Self>,
+        consumer: &mut dyn FnMut(&AtomicEvent) -> GameResult<()>,
+    ) -> GameResult<FlushResult> {
+        self.event.get_events().iter().try_for_each(consumer)?;
 
-        FlushResult::Merge(MergeBuilder::new(self))
+        Ok(FlushResult::Merge(MergeBuilder::new(self)))
     }
 }
 
 impl EffectBuilder for AttackBuilder {
-    fn add_effect(&mut self, at: Point2) {
-        self.event.added_effects.push(at);
+    fn add_effect(&mut self, at: Point2, effect: CellEffect) {
+        self.event.added_effects.push((at, effect));
     }
 
-    fn remove_effect(&mut self, at: Point2) {
-        self.event.removed_effects.push(at);
+    fn remove_effect(&mut self, at: Point2, effect: CellEffect) {
+        self.event.removed_effects.push((at, effect));
     }
 }
 
 impl Display for AttackCompoundEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let targets: Vec<&Point2> = self.removed_pieces().iter().map(|(p, _)| p).collect();
-        write!(f, "{} Attacks {:?}", self.attacking_piece_pos(), targets)?;
+        if !self.removed_pieces.is_empty() {
+            let targets: Vec<&Point2> = self.removed_pieces().iter().map(|(p, _)| p).collect();
+            write!(f, "{} Attacks {:?}", self.attacking_piece_pos(), targets)?;
+        } else {
+            let moves: Vec<String> = if self.displaced_pieces.is_empty() {
+                self.added_effects
+                    .iter()
+                    .map(|(at, effect)| format!("{} with {:?}", at, effect.kind))
+                    .collect()
+            } else {
+                self.displaced_pieces
+                    .iter()
+                    .map(|d| format!("{} to {}", d.from, d.to))
+                    .collect()
+            };
+            write!(
+                f,
+                "{} uses {:?} on {}",
+                self.attacking_piece_pos(),
+                self.power,
+                moves.join(", ")
+            )?;
+        }
 
         if let Some(merge) = &self.merge_events
             && !merge.placed_pieces().is_empty()
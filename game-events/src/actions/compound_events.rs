@@ -2,6 +2,7 @@ use crate::{
     actions::{
         attack::AttackCompoundEvent,
         finish_turn::{FinishTurnBuilder, FinishTurnCompoundEvent},
+        game_over::{GameOverBuilder, GameOverCompoundEvent},
         merge::{MergeBuilder, MergeCompoundEvent},
         moving::MoveCompoundEvent,
         place::{PlaceBuilder, PlaceCompoundEvent},
@@ -9,7 +10,7 @@ use crate::{
     },
     atomic_events::AtomicEvent,
 };
-use game_model::{Point2, piece::Piece};
+use game_model::{GameResult, Point2, outcome::GameOutcome, piece::Piece};
 use nanoserde::{DeJson, SerJson};
 use std::fmt::{Debug, Display};
 
@@ -18,7 +19,11 @@ pub trait CompoundEventBuilder {
 
     fn build(self) -> GameAction;
 
-    fn flush(self: Box<Self>, consumer: &mut dyn FnMut(&AtomicEvent)) -> FlushResult;
+    /// Feeds all atomic events to `consumer`, stopping at the first one it rejects.
+    fn flush(
+        self: Box<Self>,
+        consumer: &mut dyn FnMut(&AtomicEvent) -> GameResult<()>,
+    ) -> GameResult<FlushResult>;
 }
 
 pub enum FlushResult {
@@ -37,6 +42,7 @@ pub enum GameAction {
     Move(MoveCompoundEvent),
     Undo(UndoCompoundEvent),
     FinishTurn(FinishTurnCompoundEvent),
+    GameOver(GameOverCompoundEvent),
 }
 
 impl Display for GameAction {
@@ -47,6 +53,7 @@ impl Display for GameAction {
             GameAction::Move(a) => Display::fmt(&a, f),
             GameAction::Undo(a) => Display::fmt(&a, f),
             GameAction::FinishTurn(a) => Display::fmt(&a, f),
+            GameAction::GameOver(a) => Display::fmt(&a, f),
         }
     }
 }
@@ -60,8 +67,13 @@ impl GameAction {
         UndoBuilder::new(undone).build()
     }
 
-    pub fn finish_turn() -> FinishTurnBuilder {
-        FinishTurnBuilder::new()
+    /// Ends the turn of `team_id`.
+    pub fn finish_turn(team_id: usize) -> FinishTurnBuilder {
+        FinishTurnBuilder::new(team_id)
+    }
+
+    pub fn game_over(outcome: GameOutcome) -> GameOverBuilder {
+        GameOverBuilder::new(outcome)
     }
 
     pub fn get_compound_event(&self) -> Box<&dyn CompoundEvent> {
@@ -71,6 +83,7 @@ impl GameAction {
             GameAction::Move(e) => Box::new(e),
             GameAction::Undo(e) => Box::new(e),
             GameAction::FinishTurn(e) => Box::new(e),
+            GameAction::GameOver(e) => Box::new(e),
         }
     }
 
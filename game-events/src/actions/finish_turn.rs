@@ -1,12 +1,15 @@
 use std::fmt::Display;
 
 use crate::{
-    actions::compound_events::{CompoundEvent, GameAction},
+    actions::{
+        compound_events::{CompoundEvent, GameAction},
+        place::EffectBuilder,
+    },
     atomic_events::AtomicEvent,
 };
 use game_model::{
     Point2,
-    piece::{Exhaustion, Piece},
+    piece::{CellEffect, Exhaustion, Piece},
 };
 use nanoserde::{DeJson, SerJson};
 
@@ -27,10 +30,10 @@ pub struct FinishTurnBuilder {
 }
 
 impl FinishTurnBuilder {
-    pub(crate) fn new() -> FinishTurnBuilder {
+    pub(crate) fn new(team_id: usize) -> FinishTurnBuilder {
         FinishTurnBuilder {
             event: FinishTurnCompoundEvent {
-                events: vec![AtomicEvent::NextTurn],
+                events: vec![AtomicEvent::NextTurn(team_id)],
                 was_flushed: false,
             },
         }
@@ -54,6 +57,45 @@ impl FinishTurnBuilder {
         self
     }
 
+    /// Passes the turn on once more, e.g. because `team_id`, whose turn it would be,
+    /// was eliminated while the turn ended.
+    pub fn next_turn(&mut self, team_id: usize) -> &mut Self {
+        self.event.events.push(AtomicEvent::NextTurn(team_id));
+
+        self
+    }
+
+    /// Replaces a timed effect by one with a turn less, or removes it if it wears off.
+    pub fn count_down_effect(&mut self, at: Point2, effect: CellEffect) -> &mut Self {
+        self.event
+            .events
+            .push(AtomicEvent::RemoveEffect(effect, at));
+        if let Some(turns) = effect.turns.filter(|turns| *turns > 1) {
+            let counted_down = CellEffect {
+                turns: Some(turns - 1),
+                ..effect
+            };
+            self.event
+                .events
+                .push(AtomicEvent::AddEffect(counted_down, at));
+        }
+
+        self
+    }
+
+    /// Removes a piece killed by an effect that wore off.
+    pub fn remove_piece(&mut self, point: Point2, piece: Piece) -> &mut Self {
+        self.event.events.push(AtomicEvent::Remove(point, piece));
+
+        self
+    }
+
+    pub fn eliminate_team(&mut self, team_id: usize) -> &mut Self {
+        self.event.events.push(AtomicEvent::EliminateTeam(team_id));
+
+        self
+    }
+
     pub fn change_exhaustion(&mut self, from: Exhaustion, to: Exhaustion, at: Point2) -> &mut Self {
         self.event
             .events
@@ -63,6 +105,18 @@ impl FinishTurnBuilder {
     }
 }
 
+impl EffectBuilder for FinishTurnBuilder {
+    fn add_effect(&mut self, at: Point2, effect: CellEffect) {
+        self.event.events.push(AtomicEvent::AddEffect(effect, at));
+    }
+
+    fn remove_effect(&mut self, at: Point2, effect: CellEffect) {
+        self.event
+            .events
+            .push(AtomicEvent::RemoveEffect(effect, at));
+    }
+}
+
 impl Display for FinishTurnCompoundEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "FinishTurn")
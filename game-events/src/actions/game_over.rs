@@ -0,0 +1,53 @@
+use std::fmt::Display;
+
+use crate::{
+    actions::compound_events::{CompoundEvent, GameAction},
+    atomic_events::AtomicEvent,
+};
+use derive_getters::Getters;
+use game_model::outcome::GameOutcome;
+use nanoserde::{DeJson, SerJson};
+
+/// Ends the game. Marks every team that didn't win as lost, unless it already is.
+#[derive(Debug, Clone, SerJson, DeJson, Getters)]
+pub struct GameOverCompoundEvent {
+    outcome: GameOutcome,
+    events: Vec<AtomicEvent>,
+}
+
+pub struct GameOverBuilder {
+    event: GameOverCompoundEvent,
+}
+
+impl GameOverBuilder {
+    pub(crate) fn new(outcome: GameOutcome) -> Self {
+        GameOverBuilder {
+            event: GameOverCompoundEvent {
+                outcome,
+                events: vec![],
+            },
+        }
+    }
+
+    pub fn eliminate_team(&mut self, team_id: usize) -> &mut Self {
+        self.event.events.push(AtomicEvent::EliminateTeam(team_id));
+
+        self
+    }
+
+    pub fn build(self) -> GameAction {
+        GameAction::GameOver(self.event)
+    }
+}
+
+impl CompoundEvent for GameOverCompoundEvent {
+    fn get_events(&self) -> Vec<AtomicEvent> {
+        self.events.clone()
+    }
+}
+
+impl Display for GameOverCompoundEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GameOver: {}", self.outcome)
+    }
+}
@@ -9,8 +9,8 @@ use crate::{
 };
 use derive_getters::Getters;
 use game_model::{
-    Point2,
-    piece::{EffectKind, Piece},
+    GameResult, Point2,
+    piece::{CellEffect, Piece},
 };
 use nanoserde::{DeJson, SerJson};
 
@@ -30,21 +30,32 @@ impl MergeBuilder {
         }
     }
 
-    pub fn remove_piece(&mut self, point: Point2, piece: Piece) {
-        self.event.removed_pieces.push((point, piece));
+    /// Records that the pieces in `merged_from` are combined into `piece` at `at`.
+    /// All merges of one builder happen simultaneously, so they must not share pieces or targets.
+    pub fn merge(&mut self, at: Point2, piece: Piece, merged_from: Vec<(Point2, Piece)>) {
+        self.event.merges.push(Merge {
+            at,
+            piece,
+            merged_from,
+        });
     }
+}
 
-    pub fn place_piece(&mut self, point: Point2, piece: Piece) {
-        self.event.placed_pieces.push((point, piece));
-    }
+/// A single pattern match: the matched pieces are removed and `piece` is placed at `at`.
+#[derive(Debug, Clone, SerJson, DeJson, Getters)]
+pub struct Merge {
+    at: Point2,
+    piece: Piece,
+    merged_from: Vec<(Point2, Piece)>,
 }
 
+/// One step of merge resolution. Merges found in the same step are resolved together,
+/// merges that only become possible because of them follow in `merge_events`.
 #[derive(Debug, Clone, SerJson, DeJson, Getters)]
 pub struct MergeCompoundEvent {
-    placed_pieces: Vec<(Point2, Piece)>,
-    removed_pieces: Vec<(Point2, Piece)>,
-    added_effects: Vec<Point2>,
-    removed_effects: Vec<Point2>,
+    merges: Vec<Merge>,
+    added_effects: Vec<(Point2, CellEffect)>,
+    removed_effects: Vec<(Point2, CellEffect)>,
 
     merge_events: Option<Box<MergeCompoundEvent>>,
 }
@@ -59,30 +70,40 @@ impl MergeCompoundEvent {
     pub fn new() -> Self {
         MergeCompoundEvent {
             merge_events: None,
-            placed_pieces: vec![],
-            removed_pieces: vec![],
+            merges: vec![],
             added_effects: vec![],
             removed_effects: vec![],
         }
     }
+
+    pub fn placed_pieces(&self) -> Vec<(Point2, Piece)> {
+        self.merges.iter().map(|m| (m.at, m.piece)).collect()
+    }
+
+    pub fn removed_pieces(&self) -> Vec<(Point2, Piece)> {
+        self.merges
+            .iter()
+            .flat_map(|m| m.merged_from.iter().copied())
+            .collect()
+    }
 }
 
 impl CompoundEvent for MergeCompoundEvent {
     fn get_events(&self) -> Vec<AtomicEvent> {
         let mut all_events: Vec<AtomicEvent> = vec![];
 
-        for (at, piece) in self.removed_pieces.iter() {
-            all_events.push(AtomicEvent::Remove(*at, *piece));
+        for (at, piece) in self.removed_pieces() {
+            all_events.push(AtomicEvent::Remove(at, piece));
         }
-        for (at, piece) in self.placed_pieces.iter() {
-            all_events.push(AtomicEvent::Place(*at, *piece));
+        for (at, piece) in self.placed_pieces() {
+            all_events.push(AtomicEvent::Place(at, piece));
         }
 
-        for effect in self.removed_effects.iter() {
-            all_events.push(AtomicEvent::RemoveEffect(EffectKind::Protection, *effect));
+        for (at, effect) in self.removed_effects.iter() {
+            all_events.push(AtomicEvent::RemoveEffect(*effect, *at));
         }
-        for effect in self.added_effects.iter() {
-            all_events.push(AtomicEvent::AddEffect(EffectKind::Protection, *effect));
+        for (at, effect) in self.added_effects.iter() {
+            all_events.push(AtomicEvent::AddEffect(*effect, *at));
         }
 
         if let Some(merge_events) = &self.merge_events {
@@ -93,12 +114,12 @@ impl CompoundEvent for MergeCompoundEvent {
 }
 
 impl EffectBuilder for MergeBuilder {
-    fn add_effect(&mut self, at: Point2) {
-        self.event.added_effects.push(at);
+    fn add_effect(&mut self, at: Point2, effect: CellEffect) {
+        self.event.added_effects.push((at, effect));
     }
 
-    fn remove_effect(&mut self, at: Point2) {
-        self.event.removed_effects.push(at);
+    fn remove_effect(&mut self, at: Point2, effect: CellEffect) {
+        self.event.removed_effects.push((at, effect));
     }
 }
 
@@ -112,15 +133,18 @@ impl CompoundEventBuilder for MergeBuilder {
         self.super_event.build_with_merge_event(self.event)
     }
 
-    fn flush(self: Box<Self>, consumer: &mut dyn FnMut(&AtomicEvent)) -> FlushResult {
+    fn flush(
+        self: Box<Self>,
+        consumer: &mut dyn FnMut(&AtomicEvent) -> GameResult<()>,
+    ) -> GameResult<FlushResult> {
         let events = self.event.get_events();
         if events.is_empty() {
-            return FlushResult::Build(self.build());
+            return Ok(FlushResult::Build(self.build()));
         }
 
-        events.iter().for_each(consumer);
+        events.iter().try_for_each(consumer)?;
 
-        FlushResult::Merge(MergeBuilder::new(self))
+        Ok(FlushResult::Merge(MergeBuilder::new(self)))
     }
 }
 
@@ -128,8 +152,8 @@ impl Display for MergeCompoundEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Merging to ")?;
 
-        self.placed_pieces.iter().for_each(|(point, piece)| {
-            _ = write!(f, "{} at {}; ", piece, point);
+        self.merges.iter().for_each(|merge| {
+            _ = write!(f, "{} at {}; ", merge.piece, merge.at);
         });
 
         Ok(())
@@ -1,6 +1,7 @@
 pub mod attack;
 pub mod compound_events;
 pub mod finish_turn;
+pub mod game_over;
 pub mod merge;
 pub mod moving;
 pub mod place;
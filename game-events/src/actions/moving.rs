@@ -10,8 +10,8 @@ use crate::{
 };
 use derive_getters::Getters;
 use game_model::{
-    Point2,
-    piece::{EffectKind, Exhaustion, Piece},
+    GameResult, Point2,
+    piece::{CellEffect, Exhaustion, Piece},
 };
 use nanoserde::{DeJson, SerJson};
 
@@ -24,8 +24,9 @@ pub struct MoveCompoundEvent {
     moved_piece: Piece,
     exhaustion_afterwards: Exhaustion,
     captured_piece: Option<Piece>,
-    added_effects: Vec<Point2>,
-    removed_effects: Vec<Point2>,
+    eliminated_team: Option<usize>,
+    added_effects: Vec<(Point2, CellEffect)>,
+    removed_effects: Vec<(Point2, CellEffect)>,
 
     merge_events: Option<MergeCompoundEvent>,
 }
@@ -46,6 +47,7 @@ impl MoveBuilder {
                 moved_piece,
                 exhaustion_afterwards,
                 captured_piece: None,
+                eliminated_team: None,
                 added_effects: vec![],
                 removed_effects: vec![],
 
@@ -59,6 +61,13 @@ impl MoveBuilder {
 
         self
     }
+
+    /// Marks a team as lost because the captured piece was its last one.
+    pub fn eliminate_team(&mut self, team_id: usize) -> &mut Self {
+        self.event.eliminated_team = Some(team_id);
+
+        self
+    }
 }
 
 impl CompoundEvent for MoveCompoundEvent {
@@ -68,6 +77,9 @@ impl CompoundEvent for MoveCompoundEvent {
         if let Some(captured_piece) = self.captured_piece {
             all_events.push(AtomicEvent::Remove(self.to, captured_piece));
         }
+        if let Some(team_id) = self.eliminated_team {
+            all_events.push(AtomicEvent::EliminateTeam(team_id));
+        }
 
         all_events.push(AtomicEvent::Remove(self.from, self.moved_piece));
         all_events.push(AtomicEvent::Place(self.to, self.moved_piece));
@@ -78,11 +90,11 @@ impl CompoundEvent for MoveCompoundEvent {
             self.to,
         ));
 
-        for effect in self.removed_effects.iter() {
-            all_events.push(AtomicEvent::RemoveEffect(EffectKind::Protection, *effect));
+        for (at, effect) in self.removed_effects.iter() {
+            all_events.push(AtomicEvent::RemoveEffect(*effect, *at));
         }
-        for effect in self.added_effects.iter() {
-            all_events.push(AtomicEvent::AddEffect(EffectKind::Protection, *effect));
+        for (at, effect) in self.added_effects.iter() {
+            all_events.push(AtomicEvent::AddEffect(*effect, *at));
         }
 
         if let Some(merge_events) = &self.merge_events {
@@ -102,20 +114,23 @@ impl CompoundEventBuilder for MoveBuilder {
         GameAction::Move(self.event)
     }
 
-    fn flush(self: Box<Self>, consumer: &mut dyn FnMut(&AtomicEvent)) -> FlushResult {
-        self.event.get_events().iter().for_each(consumer);
+    fn flush(
+        self: Box<Self>,
+        consumer: &mut dyn FnMut(&AtomicEvent) -> GameResult<()>,
+    ) -> GameResult<FlushResult> {
+        self.event.get_events().iter().try_for_each(consumer)?;
 
-        FlushResult::Merge(MergeBuilder::new(self))
+        Ok(FlushResult::Merge(MergeBuilder::new(self)))
     }
 }
 
 impl EffectBuilder for MoveBuilder {
-    fn add_effect(&mut self, at: Point2) {
-        self.event.added_effects.push(at);
+    fn add_effect(&mut self, at: Point2, effect: CellEffect) {
+        self.event.added_effects.push((at, effect));
     }
 
-    fn remove_effect(&mut self, at: Point2) {
-        self.event.removed_effects.push(at);
+    fn remove_effect(&mut self, at: Point2, effect: CellEffect) {
+        self.event.removed_effects.push((at, effect));
     }
 }
 
@@ -9,8 +9,8 @@ use crate::{
 };
 use derive_getters::Getters;
 use game_model::{
-    Point2,
-    piece::{EffectKind, Piece},
+    GameResult, Point2,
+    piece::{CellEffect, Piece},
 };
 use nanoserde::{DeJson, SerJson};
 
@@ -21,7 +21,7 @@ pub struct PlaceCompoundEvent {
     at: Point2,
     piece: Piece,
     team_id: usize,
-    added_effects: Vec<Point2>,
+    added_effects: Vec<(Point2, CellEffect)>,
 
     merge_events: Option<MergeCompoundEvent>,
 }
@@ -31,8 +31,8 @@ pub struct PlaceBuilder {
 }
 
 pub trait EffectBuilder {
-    fn add_effect(&mut self, at: Point2);
-    fn remove_effect(&mut self, at: Point2);
+    fn add_effect(&mut self, at: Point2, effect: CellEffect);
+    fn remove_effect(&mut self, at: Point2, effect: CellEffect);
 }
 
 impl CompoundEventBuilder for PlaceBuilder {
@@ -45,10 +45,13 @@ impl CompoundEventBuilder for PlaceBuilder {
         GameAction::Place(self.event)
     }
 
-    fn flush(self: Box<Self>, consumer: &mut dyn FnMut(&AtomicEvent)) -> FlushResult {
-        self.event.get_events().iter().for_each(consumer);
+    fn flush(
+        self: Box<Self>,
+        consumer: &mut dyn FnMut(&AtomicEvent) -> GameResult<()>,
+    ) -> GameResult<FlushResult> {
+        self.event.get_events().iter().try_for_each(consumer)?;
 
-        FlushResult::Merge(MergeBuilder::new(self))
+        Ok(FlushResult::Merge(MergeBuilder::new(self)))
     }
 }
 
@@ -67,11 +70,11 @@ impl PlaceBuilder {
 }
 
 impl EffectBuilder for PlaceBuilder {
-    fn add_effect(&mut self, at: Point2) {
-        self.event.added_effects.push(at);
+    fn add_effect(&mut self, at: Point2, effect: CellEffect) {
+        self.event.added_effects.push((at, effect));
     }
 
-    fn remove_effect(&mut self, _at: Point2) {
+    fn remove_effect(&mut self, _at: Point2, _effect: CellEffect) {
         panic!("No effect can be removed during 'Place'")
     }
 }
@@ -83,8 +86,8 @@ impl CompoundEvent for PlaceCompoundEvent {
         all_events.push(AtomicEvent::Place(self.at, self.piece));
         all_events.push(AtomicEvent::RemoveUnusedPiece(self.team_id));
 
-        for effect in self.added_effects.iter() {
-            all_events.push(AtomicEvent::AddEffect(EffectKind::Protection, *effect));
+        for (at, effect) in self.added_effects.iter() {
+            all_events.push(AtomicEvent::AddEffect(*effect, *at));
         }
 
         if let Some(merge_events) = &self.merge_events {
@@ -1,7 +1,7 @@
 use crate::atomic_events::AtomicEvent::*;
 use game_model::{
     Point2,
-    piece::{EffectKind, Exhaustion, Piece},
+    piece::{CellEffect, Exhaustion, Piece},
 };
 use nanoserde::{DeJson, SerJson};
 
@@ -12,9 +12,15 @@ pub enum AtomicEvent {
     AddUnusedPiece(usize),
     RemoveUnusedPiece(usize),
     ChangeExhaustion(Exhaustion, Exhaustion, Point2), // From, To, At
-    AddEffect(EffectKind, Point2),
-    RemoveEffect(EffectKind, Point2),
-    NextTurn,
+    AddEffect(CellEffect, Point2),
+    RemoveEffect(CellEffect, Point2),
+    /// The team lost its last piece and is out of the game
+    EliminateTeam(usize),
+    ReviveTeam(usize),
+    /// The turn passes on from the given team to the next one that is still in the game
+    NextTurn(usize),
+    /// The turn goes back to the given team
+    PreviousTurn(usize),
 }
 
 impl AtomicEvent {
@@ -26,11 +32,12 @@ impl AtomicEvent {
             AddUnusedPiece(team_id) => RemoveUnusedPiece(*team_id),
             RemoveUnusedPiece(team_id) => AddUnusedPiece(*team_id),
             ChangeExhaustion(from, to, point) => ChangeExhaustion(*to, *from, *point),
-            AddEffect(kind, at) => RemoveEffect(*kind, *at),
-            RemoveEffect(kind, at) => AddEffect(*kind, *at),
-            NextTurn => {
-                panic!("Cannot undo next turn");
-            }
+            AddEffect(effect, at) => RemoveEffect(*effect, *at),
+            RemoveEffect(effect, at) => AddEffect(*effect, *at),
+            EliminateTeam(team_id) => ReviveTeam(*team_id),
+            ReviveTeam(team_id) => EliminateTeam(*team_id),
+            NextTurn(team_id) => PreviousTurn(*team_id),
+            PreviousTurn(team_id) => NextTurn(*team_id),
         }
     }
 }
@@ -1,7 +1,10 @@
 use crate::actions::compound_events::GameAction;
+use game_model::GameResult;
 
 pub trait EventConsumer {
-    fn handle_event(&mut self, event: &GameAction);
+    /// Applies the event. A consumer that rejects an event must leave its state
+    /// as it was before the event.
+    fn handle_event(&mut self, event: &GameAction) -> GameResult<()>;
 }
 
 pub struct EventBroker {
@@ -25,9 +28,12 @@ impl EventBroker {
         self.subscribers.push(subscriber);
     }
 
-    pub fn dispatch(&mut self, event: &GameAction) {
+    /// Passes the event to all subscribers in the order they subscribed.
+    /// Stops at the first subscriber that rejects it, the ones before keep the event,
+    /// so check it against a copy of the game first.
+    pub fn dispatch(&mut self, event: &GameAction) -> GameResult<()> {
         self.subscribers
             .iter_mut()
-            .for_each(|s| (*s).handle_event(event));
+            .try_for_each(|s| (*s).handle_event(event))
     }
 }
@@ -3,7 +3,8 @@
 //! [`atomic_events::AtomicEvent`]s are the smallest state changes (place, remove, exhaust);
 //! [`actions`] compose them into compound [`actions::compound_events::GameAction`]s for moves,
 //! attacks, and merges. [`event_broker::EventBroker`] dispatches events to subscribers, and
-//! [`undo_manager::UndoManager`] provides per-turn undo by inverting events.
+//! [`undo_manager::UndoManager`] provides undo by inverting events, within the current turn or,
+//! for hot-seat games, across whole turns.
 //!
 //! Sits above `game-model` and below `game-core` in the architecture.
 
@@ -2,7 +2,11 @@ use crate::actions::compound_events::GameAction;
 
 pub struct UndoManager {
     past_events: Vec<GameAction>,
+    /// Undone actions, the last one is redone first
+    undone_events: Vec<GameAction>,
     turn_boundary: usize,
+    /// Whether undo may go back past the start of the current turn
+    rewind_turns: bool,
 }
 
 impl Default for UndoManager {
@@ -15,22 +19,60 @@ impl UndoManager {
     pub fn new() -> Self {
         UndoManager {
             past_events: vec![],
+            undone_events: vec![],
             turn_boundary: 0,
+            rewind_turns: false,
         }
     }
 
+    /// Lets undo rewind whole turns, finished turns included. Meant for games in which
+    /// every team plays on the same device.
+    pub fn with_turn_rewind(mut self) -> Self {
+        self.rewind_turns = true;
+        self
+    }
+
+    /// Records a new action, which makes the undone ones impossible to redo.
     pub fn push(&mut self, event: GameAction) {
         self.past_events.push(event);
+        self.undone_events.clear();
     }
 
     pub fn mark_turn_boundary(&mut self) {
         self.turn_boundary = self.past_events.len();
     }
 
+    /// Whether [`Self::undo`] has an action to take back.
+    pub fn can_undo(&self) -> bool {
+        let boundary = if self.rewind_turns {
+            0
+        } else {
+            self.turn_boundary
+        };
+        self.past_events.len() > boundary
+    }
+
+    /// Whether [`Self::redo`] has an undone action to execute again.
+    pub fn can_redo(&self) -> bool {
+        !self.undone_events.is_empty()
+    }
+
     pub fn undo(&mut self) -> Option<GameAction> {
-        if self.past_events.len() <= self.turn_boundary {
+        if !self.can_undo() {
             return None;
         }
-        self.past_events.pop().map(|e| e.anti_event())
+        let action = self.past_events.pop()?;
+        let anti_event = action.anti_event();
+        self.undone_events.push(action);
+
+        Some(anti_event)
+    }
+
+    /// The last undone action, to be executed again.
+    pub fn redo(&mut self) -> Option<GameAction> {
+        let action = self.undone_events.pop()?;
+        self.past_events.push(action.clone());
+
+        Some(action)
     }
 }
@@ -111,6 +111,16 @@ async fn setup_game_state() -> Box<dyn GameState> {
 
     let (canvas_w, canvas_h) = logical_canvas_size();
     let mut loading_state = LoadingState::new(canvas_w, canvas_h);
+
+    #[cfg(target_family = "wasm")]
+    if let Some(num_players) = getProperty("players")
+        .as_string()
+        .and_then(|p| p.parse::<usize>().ok())
+    {
+        info!("Was preconfigured with {} players", num_players);
+        loading_state.set_num_players(num_players);
+    }
+
     if let Some(room_id) = preconfigured_room_id.as_ref()
         && !room_id.is_empty()
     {
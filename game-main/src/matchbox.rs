@@ -9,20 +9,20 @@ use urlencoding::encode;
 
 /// Build the signaling server URL for a given room ID.
 /// When the room ID is "common" (the Find Opponent matchmaking pool),
-/// we add `?next=2` to tell the matchbox server to create a new room
-/// for every two players that connect.
+/// we add `?next=N` to tell the matchbox server to create a new room
+/// for every N players that connect.
 /// For explicit rooms (Create Game or direct invite), the URL is unchanged.
-fn build_url(room_id: &str) -> String {
+fn build_url(room_id: &str, num_players: usize) -> String {
     let encoded = encode(room_id);
     if room_id == "common" {
-        format!("wss://heartlabs.eu:3537/{}?next=2", encoded)
+        format!("wss://heartlabs.eu:3537/{}?next={}", encoded, num_players)
     } else {
         format!("wss://heartlabs.eu:3537/{}", encoded)
     }
 }
 
-fn connect(room_id: &str) -> MatchboxClient {
-    let url = build_url(room_id);
+fn connect(room_id: &str, num_players: usize) -> MatchboxClient {
+    let url = build_url(room_id, num_players);
     let (mut socket, loop_fut) = WebRtcSocket::builder(url)
         .ice_server(RtcIceServerConfig {
             urls: vec![
@@ -58,12 +58,14 @@ fn connect(room_id: &str) -> MatchboxClient {
         socket,
         is_ready: false,
         own_id: None,
+        disconnected: vec![],
     }
 }
 pub struct MatchboxClient {
     socket: WebRtcSocket,
     is_ready: bool,
     own_id: Option<String>,
+    disconnected: Vec<String>,
 }
 
 impl MultiplayerClient for MatchboxClient {
@@ -76,15 +78,22 @@ impl MultiplayerClient for MatchboxClient {
         if self.own_id.is_none() {
             self.own_id = self.socket.id().map(|id| id.0.to_string());
         }
-        self.socket
-            .update_peers()
-            .into_iter()
-            .filter(|(_, state)| *state == PeerState::Connected)
-            .map(|(i, _)| {
-                self.is_ready = true;
-                i.0.to_string()
-            })
-            .collect()
+        let mut connected = vec![];
+        for (peer, state) in self.socket.update_peers() {
+            match state {
+                PeerState::Connected => {
+                    self.is_ready = true;
+                    connected.push(peer.0.to_string());
+                }
+                PeerState::Disconnected => self.disconnected.push(peer.0.to_string()),
+            }
+        }
+
+        connected
+    }
+
+    fn disconnected_peers(&mut self) -> Vec<String> {
+        self.disconnected.drain(..).collect()
     }
 
     fn recieved_events(&mut self) -> Vec<GameEventObject> {
@@ -115,8 +124,8 @@ impl MultiplayerClient for MatchboxClient {
 }
 
 impl MatchboxClient {
-    pub fn new_connector(room_id: &str) -> MultiplayerConector {
-        let client = connect(room_id);
-        MultiplayerConector::new(Box::new(client))
+    pub fn new_connector(room_id: &str, num_players: usize) -> MultiplayerConector {
+        let client = connect(room_id, num_players);
+        MultiplayerConector::new(Box::new(client)).with_num_players(num_players)
     }
 }
@@ -1,32 +1,43 @@
 use std::{
     borrow::Borrow,
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fs::File,
     io::Write,
     rc::Rc,
     sync::{Arc, Mutex},
 };
 
-use game_core::{core_game::CoreGameSubstate, multiplayer_connector::MultiplayerConector};
-use game_model::game::Game;
+use game_core::{
+    ai::{Bot, BotSearch},
+    core_game::CoreGameSubstate,
+    multiplayer_connector::MultiplayerConector,
+};
+use game_model::{game::Game, outcome::GameOutcome};
 
 use game_render::{
     BoardRender, CustomRenderContext,
     constants::{
-        FONT_SIZE, PATTERN_CELL_SIZE, PATTERN_COL_GAP, PATTERN_ELEMENT_GAP, PATTERN_PIECE_SIZE,
-        PATTERN_ROW_GAP, TEXT_LINE_SPACING,
+        FONT_SIZE, HISTORY_FONT_SIZE, PATTERN_CELL_SIZE, PATTERN_COL_GAP, PATTERN_ELEMENT_GAP,
+        PATTERN_PIECE_SIZE, PATTERN_ROW_GAP, TEXT_LINE_SPACING,
     },
     layout::{LayoutConstants, compute_layout},
     sprite::{Colour, SpriteRender},
 };
 
 use crate::states::GameState;
-use game_core::{command_handler::CommandHandler, game_controller::GameCommand};
+use game_core::{
+    command_handler::{CommandHandler, UndoRequest},
+    game_controller::GameCommand,
+    record::{self, GameRecord},
+};
 use game_events::event_broker::EventBroker;
 
 use macroquad::prelude::*;
 use macroquad_canvas::Canvas2D;
-use nanoserde::SerJson;
+
+/// About how many positions a bot searches per frame, so the game keeps rendering while
+/// it thinks.
+const BOT_NODES_PER_FRAME: usize = 500;
 
 pub struct CoreGameState {
     pub game: Rc<RefCell<Game>>,
@@ -37,6 +48,14 @@ pub struct CoreGameState {
     own_player_team_id: Option<usize>,
     pub is_multi_player: bool,
     pub team_names: Vec<String>,
+    /// Computer players of an offline game
+    bots: Vec<Bot>,
+    /// The search of the bot whose turn it is, spread over several frames
+    bot_search: Option<BotSearch>,
+    /// How many rows the move history is scrolled back from the latest one
+    history_scroll: usize,
+    /// How many rows the move history could be scrolled back when it was last drawn
+    history_max_scroll: Cell<usize>,
 }
 
 impl CoreGameState {
@@ -51,6 +70,8 @@ impl CoreGameState {
     ) -> Self {
         let commands = Arc::new(Mutex::new(vec![]));
         let command_handler = CommandHandler::new(event_broker, commands.clone());
+        // The outcome isn't known once the game panicked
+        let record = GameRecord::new(&(*game).borrow(), team_names.clone(), vec![]);
 
         let once = std::sync::Once::new();
 
@@ -68,10 +89,15 @@ impl CoreGameState {
             error!("{} after commands: {:?}", panic_info, (*commands).lock());
 
             once.call_once(|| {
-                let commands = (*commands).lock().unwrap().borrow().to_vec();
+                let record = GameRecord {
+                    commands: (*commands).lock().unwrap().borrow().to_vec(),
+                    ..record.clone()
+                }
+                .with_event(message)
+                .with_date(today());
 
                 #[cfg(not(target_family = "wasm"))]
-                if let Err(e) = export_to_file(message, &commands) {
+                if let Err(e) = export_to_file(&record) {
                     println!("{:?}", e);
                 }
 
@@ -84,11 +110,7 @@ impl CoreGameState {
                         .and_then(|url| url::Url::parse(&url).ok())
                         .and_then(|url| Some(format!("{}://{}", url.scheme(), url.host_str()?)))
                         .unwrap();
-                    wasm_bindgen_futures::spawn_local(post_error_report(
-                        error_report_url,
-                        message.to_string(),
-                        commands,
-                    ))
+                    wasm_bindgen_futures::spawn_local(post_error_report(error_report_url, record))
                 }
             });
         }));
@@ -102,9 +124,24 @@ impl CoreGameState {
             own_player_team_id: None,
             is_multi_player,
             team_names,
+            bots: vec![],
+            bot_search: None,
+            history_scroll: 0,
+            history_max_scroll: Cell::new(0),
         }
     }
 
+    pub(crate) fn with_bots(mut self, bots: Vec<Bot>) -> Self {
+        self.bots = bots;
+        self
+    }
+
+    /// Lets undo go back through finished turns, for games where all teams share the device.
+    pub(crate) fn with_turn_rewind(mut self) -> Self {
+        self.command_handler = self.command_handler.with_turn_rewind();
+        self
+    }
+
     pub fn game_clone(&self) -> Game {
         (*self.game).borrow().clone()
     }
@@ -120,8 +157,12 @@ impl CoreGameState {
                 .try_recieve();
 
             recieved_events.iter().for_each(|e| {
-                self.command_handler
+                if let Err(error) = self
+                    .command_handler
                     .handle_remote_command(self.game_clone(), e)
+                {
+                    error!("Rejected {} from {}: {}", e, e.sender, error);
+                }
             });
 
             if self.own_player_team_id.is_none() {
@@ -132,17 +173,36 @@ impl CoreGameState {
             }
         }
 
+        self.play_bot_command();
+        self.scroll_history();
+        if is_key_pressed(KeyCode::D) {
+            self.export_game();
+        }
+
+        let undo_request = self.command_handler.undo_request();
+        self.render_context.show_undo_request =
+            matches!(undo_request, Some(UndoRequest::Received(_)));
+
         match self.render_context.game_state {
+            _ if self.render_context.show_undo_request => {
+                handle_undo_request_input(&mut self.command_handler, &self.render_context, canvas);
+            }
+            // Nothing happens until the others answered
+            _ if matches!(
+                undo_request,
+                Some(UndoRequest::Sent | UndoRequest::Accepted(_))
+            ) => {}
             CoreGameSubstate::Wait => {
                 if can_control_player(
                     &(*self.game).borrow(),
                     &self.own_player_team_id,
                     self.is_multi_player,
+                    &self.bots,
                 ) {
                     self.render_context.game_state = CoreGameSubstate::Place;
                 }
             }
-            CoreGameSubstate::Won(_) => {}
+            CoreGameSubstate::Over(_) => {}
             _ => {
                 let layout: LayoutConstants = *(*self.board_render).borrow().get_layout();
                 handle_player_input(
@@ -155,13 +215,83 @@ impl CoreGameState {
             }
         }
 
-        check_if_somebody_won(&(*self.game).borrow(), &mut self.render_context);
+        check_if_game_is_over(&(*self.board_render).borrow(), &mut self.render_context);
 
         (*self.board_render).borrow_mut().update();
 
         Option::None
     }
 
+    /// Writes the game to a record file, also once it is over so the record has the result.
+    fn export_game(&self) {
+        let record = GameRecord::new(
+            &(*self.game).borrow(),
+            self.team_names.clone(),
+            self.command_handler.get_past_commands(),
+        )
+        .with_event("exported_game")
+        .with_date(today())
+        .with_outcome(self.command_handler.outcome());
+
+        if let Err(e) = export_to_file(&record) {
+            error!("Could not export game to file: {:?}", e);
+        }
+    }
+
+    /// Scrolls the move history with the mouse wheel or PAGE UP and PAGE DOWN.
+    fn scroll_history(&mut self) {
+        let (_, wheel_y) = mouse_wheel();
+        if wheel_y > 0. || is_key_pressed(KeyCode::PageUp) {
+            self.history_scroll = (self.history_scroll + 1).min(self.history_max_scroll.get());
+        } else if wheel_y < 0. || is_key_pressed(KeyCode::PageDown) {
+            self.history_scroll = self
+                .history_scroll
+                .min(self.history_max_scroll.get())
+                .saturating_sub(1);
+        }
+    }
+
+    /// Lets the bot whose turn it is issue its next command once the previous one was animated.
+    fn play_bot_command(&mut self) {
+        let game = self.game_clone();
+        let Some(bot) = self
+            .bots
+            .iter()
+            .find(|bot| bot.team_id == game.current_team_index)
+        else {
+            return;
+        };
+
+        if let CoreGameSubstate::Over(_) = self.render_context.game_state {
+            return;
+        }
+
+        self.render_context.game_state = CoreGameSubstate::Wait;
+        if (*self.board_render).borrow().is_animating() {
+            return;
+        }
+
+        let search = match &mut self.bot_search {
+            Some(search) if search.is_for(&game) => search,
+            _ => self.bot_search.insert(bot.start_search(&game)),
+        };
+        let Some(command) = search.step(BOT_NODES_PER_FRAME) else {
+            return;
+        };
+        self.bot_search = None;
+
+        if let Err(e) = self.command_handler.handle_new_command(game, &command) {
+            // End the turn instead of trying the same command again every frame
+            error!("Bot of team {} could not {}: {}", bot.team_id, command, e);
+            if let Err(e) = self
+                .command_handler
+                .handle_new_command(self.game_clone(), &GameCommand::NextTurn)
+            {
+                error!("Bot of team {} could not end the turn: {}", bot.team_id, e);
+            }
+        }
+    }
+
     fn render_internal(&self, canvas: &Canvas2D) {
         let board_render = (*self.board_render).borrow();
         let game = (*self.game).borrow();
@@ -170,13 +300,13 @@ impl CoreGameState {
         let layout: LayoutConstants = *board_render.get_layout();
 
         if self.render_context.show_patterns {
-            draw_patterns(&self.render_context, &layout);
+            draw_patterns(&self.render_context, &layout, &game.patterns, &game.pieces);
         } else {
-            for (i, text) in description(&self.render_context, &game, &self.team_names)
-                .iter()
-                .enumerate()
-            {
-                let color: Colour = *board_render.get_team_color(game.current_team_index);
+            let mut lines = undo_request_description(&self.command_handler, &self.team_names);
+            lines.extend(description(&self.render_context, &game, &self.team_names));
+
+            let color: Colour = *board_render.get_team_color(game.current_team_index);
+            for (i, text) in lines.iter().enumerate() {
                 draw_text(
                     text.as_str(),
                     layout.text_x,
@@ -185,6 +315,15 @@ impl CoreGameState {
                     color.into(),
                 );
             }
+
+            let history_top = layout.text_y + (lines.len() as f32) * FONT_SIZE * TEXT_LINE_SPACING;
+            let max_scroll = draw_history(
+                &self.command_handler.history().lines(),
+                self.history_scroll,
+                history_top,
+                &layout,
+            );
+            self.history_max_scroll.set(max_scroll);
         }
     }
 }
@@ -230,30 +369,25 @@ impl GameState for CoreGameState {
     }
 
     fn handle_resize(&mut self, new_width: f32, new_height: f32) {
-        let new_layout = compute_layout(new_width, new_height);
+        let (board_w, board_h) = {
+            let game = (*self.game).borrow();
+            (game.board.w, game.board.h)
+        };
+        let new_layout = compute_layout(new_width, new_height, board_w, board_h);
         self.board_render.borrow_mut().set_layout(&new_layout);
         self.render_context.update_buttons(&new_layout);
     }
 }
 
-fn check_if_somebody_won(game: &Game, render_context: &mut CustomRenderContext) {
-    if let CoreGameSubstate::Won(_) = render_context.game_state {
+/// Switches to the end screen once the `GameOver` event reached the board render.
+fn check_if_game_is_over(board_render: &BoardRender, render_context: &mut CustomRenderContext) {
+    if let CoreGameSubstate::Over(_) = render_context.game_state {
         return;
     }
 
-    let board = &game.board;
-    let team_1_won = board.placed_pieces(0).is_empty() || game.num_unused_pieces_of(1) >= 20;
-    let team_0_won = board.placed_pieces(1).is_empty() || game.num_unused_pieces_of(0) >= 20;
-
-    if team_1_won && !team_0_won {
-        info!("Team 1 won");
-        render_context.game_state = CoreGameSubstate::Won(1);
-        #[cfg(target_family = "wasm")]
-        reportGameComplete();
-    }
-    if team_0_won && !team_1_won {
-        info!("Team 0 won");
-        render_context.game_state = CoreGameSubstate::Won(0);
+    if let Some(outcome) = board_render.outcome() {
+        info!("{}", outcome);
+        render_context.game_state = CoreGameSubstate::Over(outcome);
         #[cfg(target_family = "wasm")]
         reportGameComplete();
     }
@@ -268,6 +402,29 @@ extern "C" {
     fn reportGameComplete();
 }
 
+fn undo_request_description(
+    command_handler: &CommandHandler,
+    team_names: &[String],
+) -> Vec<String> {
+    let requested = match command_handler.requested_command() {
+        GameCommand::Redo => "redo",
+        _ => "undo",
+    };
+
+    match command_handler.undo_request() {
+        Some(UndoRequest::Received(team_id)) => vec![
+            format!("The {} team asks to {}", team_names[team_id], requested),
+            "Allow it? Press Y or N".to_string(),
+        ],
+        Some(UndoRequest::Sent) => vec![format!("Waiting for the {} to be accepted", requested)],
+        Some(UndoRequest::Accepted(_)) => {
+            vec!["Waiting for the other players to agree".to_string()]
+        }
+        Some(UndoRequest::Declined) => vec![format!("The {} was declined", requested)],
+        None => vec![],
+    }
+}
+
 fn description(
     render_context: &CustomRenderContext,
     game: &Game,
@@ -305,13 +462,16 @@ fn description(
         CoreGameSubstate::Activate(_) => {
             description.push("Click the target piece".parse().unwrap());
         }
-        CoreGameSubstate::Won(team) => {
+        CoreGameSubstate::Over(GameOutcome::Win { team_id, .. }) => {
             description.push(
-                format!("The {} team won", team_names[team])
+                format!("The {} team won", team_names[team_id])
                     .parse()
                     .unwrap(),
             );
         }
+        CoreGameSubstate::Over(GameOutcome::Draw(reason)) => {
+            description.push(format!("Draw {}", reason).parse().unwrap());
+        }
         CoreGameSubstate::Wait => {
             description.push("Please wait for opponent to finish".parse().unwrap());
         }
@@ -320,7 +480,19 @@ fn description(
     description
 }
 
-fn can_control_player(game: &Game, own_player_id: &Option<usize>, is_online: bool) -> bool {
+fn can_control_player(
+    game: &Game,
+    own_player_id: &Option<usize>,
+    is_online: bool,
+    bots: &[Bot],
+) -> bool {
+    if bots
+        .iter()
+        .any(|bot| bot.team_id == game.current_team_index)
+    {
+        return false;
+    }
+
     if !is_online {
         return true;
     }
@@ -332,6 +504,75 @@ fn can_control_player(game: &Game, own_player_id: &Option<usize>, is_online: boo
     }
 }
 
+/// Draws the move history from `top` to the bottom of the text area, latest turn last.
+/// Turns that don't fit into a row are wrapped between their actions, `scroll` counts
+/// these rows. Returns how many rows the history can be scrolled back.
+fn draw_history(turns: &[String], scroll: usize, top: f32, layout: &LayoutConstants) -> usize {
+    let text_width = if layout.shift_x > 0. {
+        layout.shift_x
+    } else {
+        layout.board_width
+    } - layout.text_x * 2.;
+    let fits =
+        |text: &str| measure_text(text, None, HISTORY_FONT_SIZE as u16, 1.).width <= text_width;
+
+    let mut rows = vec![];
+    for turn in turns {
+        let mut row = String::new();
+        for action in turn.split(", ") {
+            let extended = if row.is_empty() {
+                action.to_string()
+            } else {
+                format!("{}, {}", row, action)
+            };
+            if row.is_empty() || fits(&extended) {
+                row = extended;
+            } else {
+                rows.push(std::mem::replace(&mut row, format!("   {}", action)));
+            }
+        }
+        rows.push(row);
+    }
+
+    let row_height = HISTORY_FONT_SIZE * TEXT_LINE_SPACING;
+    let visible = ((layout.canvas_h - top) / row_height).max(0.) as usize;
+    let max_scroll = rows.len().saturating_sub(visible);
+    let end = rows.len() - scroll.min(max_scroll);
+    let start = end.saturating_sub(visible);
+
+    for (i, row) in rows[start..end].iter().enumerate() {
+        draw_text(
+            row,
+            layout.text_x,
+            top + (i as f32 + 1.) * row_height,
+            HISTORY_FONT_SIZE,
+            LIGHTGRAY,
+        );
+    }
+
+    max_scroll
+}
+
+/// Lets the local player accept or decline another player's undo request.
+fn handle_undo_request_input(
+    command_handler: &mut CommandHandler,
+    render_context: &CustomRenderContext,
+    canvas: &Canvas2D,
+) {
+    let accept = if is_key_pressed(KeyCode::Y) || render_context.button_accept_undo.clicked(canvas)
+    {
+        true
+    } else if is_key_pressed(KeyCode::N) || render_context.button_decline_undo.clicked(canvas) {
+        false
+    } else {
+        return;
+    };
+
+    if let Err(e) = command_handler.answer_undo_request(accept) {
+        error!("Could not answer the undo request: {}", e);
+    }
+}
+
 fn handle_player_input(
     game: &mut Rc<RefCell<Game>>,
     command_handler: &mut CommandHandler,
@@ -343,19 +584,24 @@ fn handle_player_input(
         render_context.show_patterns = !render_context.show_patterns;
     } else if is_key_pressed(KeyCode::U) || render_context.button_undo.clicked(canvas) {
         let game_clone = (**game).borrow().clone();
-        command_handler.handle_new_command(game_clone, &GameCommand::Undo);
+        if let Err(e) = command_handler.request_undo(game_clone) {
+            error!("Could not undo: {}", e);
+        }
+    } else if is_key_pressed(KeyCode::R) || render_context.button_redo.clicked(canvas) {
+        let game_clone = (**game).borrow().clone();
+        if let Err(e) = command_handler.request_redo(game_clone) {
+            error!("Could not redo: {}", e);
+        }
     } else if is_key_pressed(KeyCode::G) {
         render_context.show_debug_overlay = !render_context.show_debug_overlay;
-    } else if is_key_pressed(KeyCode::D) {
-        if let Err(e) = export_to_file("exported_game", &command_handler.get_past_commands()) {
-            error!("Could not export game to file: {:?}", e);
-        }
     } else if is_key_pressed(KeyCode::Enter)
         || is_key_pressed(KeyCode::KpEnter)
         || render_context.button_next.clicked(canvas)
     {
         let game_clone = (**game).borrow().clone();
-        command_handler.handle_new_command(game_clone, &GameCommand::NextTurn);
+        if let Err(e) = command_handler.handle_new_command(game_clone, &GameCommand::NextTurn) {
+            error!("Could not end the turn: {}", e);
+        }
 
         render_context.game_state = CoreGameSubstate::Wait;
         // BoardEventConsumer::flush_unsafe(game.as_ref().borrow_mut().borrow_mut(), &event_option);
@@ -374,13 +620,20 @@ fn handle_player_input(
 
 use game_model::{
     pattern::{Pattern, PatternComponent},
-    piece::PieceKind,
+    piece::PieceDefinition,
 };
 use macroquad::texture::DrawTextureParams;
 
 /// Draw the pattern infographic in the text area.
 /// Called from render_internal when show_patterns is true.
-fn draw_patterns(ctx: &CustomRenderContext, layout: &LayoutConstants) {
+fn draw_patterns(
+    ctx: &CustomRenderContext,
+    layout: &LayoutConstants,
+    patterns: &[Pattern],
+    pieces: &[PieceDefinition],
+) {
+    let patterns: Vec<Pattern> = patterns.iter().flat_map(Pattern::variants).collect();
+
     let cols = 3;
     let start_x = layout.text_x;
     let start_y = layout.text_y - FONT_SIZE / 2.;
@@ -389,8 +642,6 @@ fn draw_patterns(ctx: &CustomRenderContext, layout: &LayoutConstants) {
     let free_color = WHITE;
     let any_color = Color::from_rgba(160, 160, 160, 255);
 
-    let patterns = Pattern::all_patterns();
-
     // Precompute per-column max card widths so cards in each column are aligned
     let mut col_widths = vec![0.0f32; cols];
     for (i, p) in patterns.iter().enumerate() {
@@ -509,12 +760,9 @@ fn draw_patterns(ctx: &CustomRenderContext, layout: &LayoutConstants) {
         let sprite_x = card_x + grid_w + PATTERN_ELEMENT_GAP * 3.0;
         let sprite_y = grid_y + (grid_h - PATTERN_PIECE_SIZE) / 2.0;
 
-        let source_rect = SpriteRender::piece_sprite_rect(pattern.turn_into);
-        let rotation = if pattern.turn_into == PieceKind::HorizontalBar {
-            1.57
-        } else {
-            0.
-        };
+        let sprite = PieceDefinition::sprite(pieces, pattern.turn_into);
+        let source_rect = SpriteRender::piece_sprite_rect(sprite);
+        let rotation = SpriteRender::piece_rotation(sprite);
         draw_texture_ex(
             &ctx.pieces_texture,
             sprite_x,
@@ -532,33 +780,41 @@ fn draw_patterns(ctx: &CustomRenderContext, layout: &LayoutConstants) {
 
 const EXPORTED_GAMES_DIR: &str = "game-core/tests/exported_games";
 
-fn export_to_file(message: &str, content: &Vec<GameCommand>) -> Result<(), std::io::Error> {
+fn export_to_file(record: &GameRecord) -> Result<(), std::io::Error> {
     let num_games = std::fs::read_dir(EXPORTED_GAMES_DIR)?.count();
     let filename = format!(
-        "{}/{:04}_{}.json",
+        "{}/{:04}_{}.bgn",
         EXPORTED_GAMES_DIR,
         num_games + 1,
-        message.replace(['/', '\\'], "_").as_str()
+        record.event.replace(['/', '\\'], "_").as_str()
     );
 
     println!("Exporting to {}", filename);
     let mut file = File::create(filename)?;
-    file.write_all(format!("// {}\n", message).as_ref())?;
-    file.write_all(content.serialize_json().as_bytes())?;
+    file.write_all(record.to_string().as_bytes())?;
 
     Ok(())
 }
 
+/// Today's date for game records. The browser build doesn't know it.
+fn today() -> String {
+    #[cfg(not(target_family = "wasm"))]
+    if let Ok(now) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        return record::format_date(now.as_secs());
+    }
+
+    record::UNKNOWN_DATE.to_string()
+}
+
 #[cfg(target_family = "wasm")]
-async fn post_error_report(url: String, message: String, content: Vec<GameCommand>) {
+async fn post_error_report(url: String, record: GameRecord) {
     use wasm_bindgen::JsValue;
 
     let request_init = web_sys::RequestInit::new();
     request_init.set_method("POST");
     request_init.set_mode(web_sys::RequestMode::Cors);
 
-    let body = format!("// {}\n\n{}", message, content.serialize_json());
-    request_init.set_body(&JsValue::from_str(&body));
+    request_init.set_body(&JsValue::from_str(&record.to_string()));
 
     let request =
         match web_sys::Request::new_with_str_and_init(&(url + ":3030/error_report"), &request_init)
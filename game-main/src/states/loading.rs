@@ -8,12 +8,19 @@ use egui_macroquad::{
         Align, Color32, FontData, FontDefinitions, FontFamily, FontTweak, Layout, TextEdit, Visuals,
     },
 };
-use game_core::{core_game::CoreGameSubstate, multiplayer_connector::MultiplayerConector};
+use game_core::{
+    ai::{Bot, Difficulty},
+    core_game::CoreGameSubstate,
+    multiplayer_connector::MultiplayerConector,
+};
 
-use game_model::{Point2, game::*};
+use game_model::{
+    GameError, GameResult, board::Board, game::*, pattern::Pattern, piece::PieceDefinition,
+    rules::RuleSet,
+};
 use game_render::{
     BoardRender,
-    constants::{BOARD_HEIGHT, BOARD_WIDTH, CELL_WIDTH, FONT_SIZE},
+    constants::{BOARD_EXTENT, BOARD_SIZES, DEFAULT_BOARD_SIZE, FONT_SIZE},
     layout::compute_layout,
     render_events::RenderEventConsumer,
 };
@@ -29,8 +36,8 @@ use macroquad::prelude::*;
 use macroquad_canvas::Canvas2D;
 
 use game_core::{
-    game_controller::GameCommand,
     game_events::{Event, PlayerAction},
+    setup::set_up_pieces,
 };
 use game_events::event_broker::EventBroker;
 
@@ -39,6 +46,94 @@ pub struct LoadingState {
     sub_state: LoadingSubState,
     client: Option<MultiplayerConector>,
     room_id: String,
+    canvas_size: (f32, f32),
+    /// Setup selected in the menu
+    setup: GameSetup,
+    /// Setup the current game was created with
+    current_setup: GameSetup,
+    /// Peers that signaled they joined the match
+    connected_peers: Vec<String>,
+}
+
+/// Player counts offered in the loading menu.
+const PLAYER_COUNTS: [usize; 3] = [2, 3, 4];
+
+const TEAM_NAMES: [&str; 4] = ["Red", "Yellow", "Blue", "Green"];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct GameSetup {
+    board: BoardChoice,
+    pieces: PieceChoice,
+    num_players: usize,
+    /// Difficulty of the computer players, if all teams but the first are played by bots
+    bots: Option<Difficulty>,
+    /// Whether the game is played online, where local definitions are ignored
+    online: bool,
+}
+
+impl Default for GameSetup {
+    fn default() -> Self {
+        GameSetup {
+            board: BoardChoice::Square(DEFAULT_BOARD_SIZE),
+            pieces: PieceChoice::Classic,
+            num_players: 2,
+            bots: None,
+            online: false,
+        }
+    }
+}
+
+/// The boards offered in the loading menu.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BoardChoice {
+    Square(u8),
+    CrossArena,
+}
+
+impl BoardChoice {
+    fn create_board(&self) -> Board {
+        match self {
+            BoardChoice::Square(size) => Board::new(*size, *size),
+            BoardChoice::CrossArena => Board::cross_arena(),
+        }
+    }
+}
+
+impl Display for BoardChoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoardChoice::Square(size) => write!(f, "{size}x{size}"),
+            BoardChoice::CrossArena => write!(f, "Arena"),
+        }
+    }
+}
+
+/// The piece tables offered in the loading menu.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PieceChoice {
+    Classic,
+    /// See [`PieceDefinition::trickster_definitions`]
+    Tricksters,
+}
+
+impl PieceChoice {
+    const ALL: [PieceChoice; 2] = [PieceChoice::Classic, PieceChoice::Tricksters];
+
+    fn definitions(&self) -> Vec<PieceDefinition> {
+        match self {
+            PieceChoice::Classic => PieceDefinition::default_definitions(),
+            PieceChoice::Tricksters => PieceDefinition::trickster_definitions(),
+        }
+    }
+}
+
+impl Display for PieceChoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PieceChoice::Classic => write!(f, "Classic"),
+            PieceChoice::Tricksters => write!(f, "Tricksters"),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -68,29 +163,40 @@ impl Display for LoadingSubState {
 
 impl LoadingState {
     pub fn new(canvas_width: f32, canvas_height: f32) -> Self {
-        let game = Rc::new(RefCell::new(init_game()));
-        let mut event_broker = EventBroker::new();
-        event_broker.subscribe(Box::new(BoardEventConsumer::new(Rc::clone(&game))));
-
-        let layout = compute_layout(canvas_width, canvas_height);
-        let board_render = Rc::new(RefCell::new(BoardRender::new(&(*game).borrow(), &layout)));
-        event_broker.subscribe(Box::new(RenderEventConsumer::new(&board_render)));
-
         let room_id = "standard_room".to_string();
 
         LoadingState {
-            core_game_state: Option::Some(CoreGameState::new(
-                game,
-                event_broker,
-                board_render,
-                Option::None,
-                false,
-                vec!["Red".to_string(), "Yellow".to_string()],
-                layout,
+            core_game_state: Option::Some(create_core_game_state(
+                canvas_width,
+                canvas_height,
+                GameSetup::default(),
             )),
             sub_state: LoadingSubState::GameMode,
             client: Option::None,
             room_id,
+            canvas_size: (canvas_width, canvas_height),
+            setup: GameSetup::default(),
+            current_setup: GameSetup::default(),
+            connected_peers: vec![],
+        }
+    }
+
+    /// Sets the number of players, e.g. when preconfigured by the web page.
+    #[cfg(target_family = "wasm")]
+    pub fn set_num_players(&mut self, num_players: usize) {
+        self.setup.num_players = num_players.clamp(2, TEAM_NAMES.len());
+    }
+
+    /// Recreates the game if a different setup was chosen in the menu.
+    fn apply_setup(&mut self) {
+        if self.current_setup != self.setup {
+            let (canvas_width, canvas_height) = self.canvas_size;
+            self.core_game_state = Some(create_core_game_state(
+                canvas_width,
+                canvas_height,
+                self.setup,
+            ));
+            self.current_setup = self.setup;
         }
     }
 
@@ -112,7 +218,14 @@ impl LoadingState {
     }
 
     pub fn join_room(&mut self, room_id: &str) {
-        let client = MatchboxClient::new_connector(room_id);
+        // Online games are always played on the default board and pieces and without bots
+        self.setup.board = GameSetup::default().board;
+        self.setup.pieces = GameSetup::default().pieces;
+        self.setup.bots = None;
+        self.setup.online = true;
+        self.apply_setup();
+
+        let client = MatchboxClient::new_connector(room_id, self.setup.num_players);
         self.client = Some(client);
         self.core_game_state.as_mut().unwrap().is_multi_player = true;
 
@@ -121,6 +234,8 @@ impl LoadingState {
 
     #[cfg(target_family = "wasm")]
     pub fn offline_game(&mut self) {
+        self.setup.online = false;
+        self.apply_setup();
         self.sub_state = LoadingSubState::SetupGame;
         self.core_game_state.as_mut().unwrap().is_multi_player = false;
     }
@@ -132,7 +247,7 @@ impl LoadingState {
             egui::CentralPanel::default().show(egui_ctx, |ui| {
                 // Center a fixed-size child UI for the menu
                 let menu_width = 400.0;
-                let menu_height = 220.0;
+                let menu_height = 520.0;
                 let center = ui.max_rect().center();
                 let menu_rect =
                     egui::Rect::from_center_size(center, egui::vec2(menu_width, menu_height));
@@ -147,6 +262,63 @@ impl LoadingState {
                 child_ui.label(egui::RichText::new("Select game mode").heading().size(32.0));
                 child_ui.add_space(30.0);
 
+                child_ui.label(egui::RichText::new("Players").size(20.0));
+                child_ui.horizontal(|ui| {
+                    for num_players in PLAYER_COUNTS {
+                        ui.selectable_value(
+                            &mut self.setup.num_players,
+                            num_players,
+                            egui::RichText::new(num_players.to_string()).size(20.0),
+                        );
+                    }
+                });
+                child_ui.add_space(10.0);
+
+                child_ui.label(egui::RichText::new("Board (offline)").size(20.0));
+                child_ui.horizontal(|ui| {
+                    let choices = BOARD_SIZES
+                        .into_iter()
+                        .map(BoardChoice::Square)
+                        .chain([BoardChoice::CrossArena]);
+                    for choice in choices {
+                        ui.selectable_value(
+                            &mut self.setup.board,
+                            choice,
+                            egui::RichText::new(choice.to_string()).size(20.0),
+                        );
+                    }
+                });
+                child_ui.add_space(10.0);
+
+                child_ui.label(egui::RichText::new("Pieces (offline)").size(20.0));
+                child_ui.horizontal(|ui| {
+                    for choice in PieceChoice::ALL {
+                        ui.selectable_value(
+                            &mut self.setup.pieces,
+                            choice,
+                            egui::RichText::new(choice.to_string()).size(20.0),
+                        );
+                    }
+                });
+                child_ui.add_space(10.0);
+
+                child_ui.label(egui::RichText::new("Computer (offline)").size(20.0));
+                child_ui.horizontal(|ui| {
+                    ui.selectable_value(
+                        &mut self.setup.bots,
+                        None,
+                        egui::RichText::new("Off").size(20.0),
+                    );
+                    for difficulty in Difficulty::ALL {
+                        ui.selectable_value(
+                            &mut self.setup.bots,
+                            Some(difficulty),
+                            egui::RichText::new(difficulty.to_string()).size(20.0),
+                        );
+                    }
+                });
+                child_ui.add_space(20.0);
+
                 if child_ui
                     .add_sized(
                         [menu_width * 0.85, 56.0],
@@ -154,6 +326,8 @@ impl LoadingState {
                     )
                     .clicked()
                 {
+                    self.setup.online = false;
+                    self.apply_setup();
                     self.sub_state = LoadingSubState::SetupGame;
                 }
                 child_ui.add_space(10.0);
@@ -164,6 +338,11 @@ impl LoadingState {
                     )
                     .clicked()
                 {
+                    self.setup.board = GameSetup::default().board;
+                    self.setup.pieces = GameSetup::default().pieces;
+                    self.setup.bots = None;
+                    self.setup.online = true;
+                    self.apply_setup();
                     self.core_game_state.as_mut().unwrap().is_multi_player = true;
                     self.sub_state = LoadingSubState::Register;
                 }
@@ -223,34 +402,54 @@ impl GameState for LoadingState {
             LoadingSubState::WaitForOpponent => {
                 let core_game_state = self.core_game_state.as_mut().unwrap();
 
-                let (events, own_player_id) = {
+                let (events, own_player_id, own_player_index, num_players) = {
                     let mut client = core_game_state
                         .matchbox_events
                         .as_ref()
                         .unwrap()
                         .as_ref()
                         .borrow_mut();
-                    (client.try_recieve(), client.get_own_player_id().unwrap())
+                    (
+                        client.try_recieve(),
+                        client.get_own_player_id().unwrap(),
+                        client.get_own_player_index(),
+                        client.num_players(),
+                    )
                 };
 
-                let opponent_index = events
-                    .iter()
-                    .filter_map(|e| match &e.event {
-                        Event::PlayerAction(PlayerAction::Connect(_, i)) => Some((i, i == &1)),
-                        Event::PlayerAction(PlayerAction::NewGame((p1, _p2))) => {
-                            if p1 == &own_player_id {
-                                Some((&1, false))
-                            } else {
-                                Some((&0, false))
-                            }
+                let mut player_order = None;
+                let mut rules = None;
+                for event in &events {
+                    match &event.event {
+                        Event::PlayerAction(PlayerAction::Connect(name, _))
+                            if !self.connected_peers.contains(name) =>
+                        {
+                            self.connected_peers.push(name.clone());
+                        }
+                        Event::PlayerAction(PlayerAction::NewGame(order, initiator_rules)) => {
+                            player_order = Some(order.clone());
+                            rules = Some(*initiator_rules);
                         }
-                        _ => None,
-                    })
-                    .next();
+                        _ => {}
+                    }
+                }
 
-                if let Some((opponent_index, initiator)) = opponent_index {
+                // The first player starts the game once everybody joined,
+                // all others wait for the player order it sends
+                let own_index = if let Some(order) = &player_order {
+                    order.iter().position(|id| *id == own_player_id)
+                } else if own_player_index == Some(0)
+                    && self.connected_peers.len() + 1 == num_players
+                {
+                    Some(0)
+                } else {
+                    None
+                };
+
+                if let Some(own_index) = own_index {
+                    let initiator = player_order.is_none();
                     {
-                        debug!("opponent_index {}, initiator {}", opponent_index, initiator);
+                        debug!("own index {}, initiator {}", own_index, initiator);
                         let mut client = core_game_state
                             .matchbox_events
                             .as_ref()
@@ -258,26 +457,33 @@ impl GameState for LoadingState {
                             .as_ref()
                             .borrow_mut();
 
-                        if opponent_index == &1 {
-                            // The opponent says he is second - so we must be first
-                            client.override_own_player_index = Some(0); // we will always be this index - so lock it
-                        } else {
-                            client.override_own_player_index = Some(1);
-                        }
+                        // we will always be this index - so lock it
+                        client.override_own_player_index = Some(own_index);
 
                         if initiator {
-                            client.signal_new_game();
+                            client.signal_new_game((*core_game_state.game).borrow().rules);
                         }
                     }
 
+                    // Everybody plays by the rules of the player who started the game
+                    if let Some(rules) = rules {
+                        (*core_game_state.game).borrow_mut().rules = rules;
+                    }
+
                     if initiator {
-                        let num_teams = 2;
                         let set_up_actions =
-                            set_up_pieces(num_teams, &(*core_game_state.game).borrow());
+                            set_up_pieces(num_players, &(*core_game_state.game).borrow())
+                                .unwrap_or_else(|e| {
+                                    error!("Could not set up game: {}", e);
+                                    vec![]
+                                });
                         for start_event in &set_up_actions {
-                            core_game_state
+                            if let Err(e) = core_game_state
                                 .command_handler
-                                .handle_new_command(core_game_state.game_clone(), start_event);
+                                .handle_new_command(core_game_state.game_clone(), start_event)
+                            {
+                                error!("Could not set up game with {}: {}", start_event, e);
+                            }
                         }
                     } else {
                         core_game_state.set_sub_state(CoreGameSubstate::Wait);
@@ -287,12 +493,15 @@ impl GameState for LoadingState {
                         .iter()
                         .filter(|e| matches!(e.event, Event::GameCommand(_)))
                         .for_each(|e| {
-                            core_game_state
+                            if let Err(error) = core_game_state
                                 .command_handler
                                 .handle_remote_command(core_game_state.game_clone(), e)
+                            {
+                                error!("Rejected {} from {}: {}", e, e.sender, error);
+                            }
                         });
                 } else {
-                    debug!("waiting for opponent message");
+                    debug!("waiting for other players");
                     return None;
                 }
 
@@ -301,12 +510,19 @@ impl GameState for LoadingState {
 
             LoadingSubState::SetupGame => {
                 let core_game_state = self.core_game_state.as_mut().unwrap();
-                let num_teams = 2;
-                let set_up_actions = set_up_pieces(num_teams, &(*core_game_state.game).borrow());
+                let num_teams = self.current_setup.num_players;
+                let set_up_actions = set_up_pieces(num_teams, &(*core_game_state.game).borrow())
+                    .unwrap_or_else(|e| {
+                        error!("Could not set up game: {}", e);
+                        vec![]
+                    });
                 for start_event in &set_up_actions {
-                    core_game_state
+                    if let Err(e) = core_game_state
                         .command_handler
-                        .handle_new_command(core_game_state.game_clone(), start_event);
+                        .handle_new_command(core_game_state.game_clone(), start_event)
+                    {
+                        error!("Could not set up game with {}: {}", start_event, e);
+                    }
                 }
                 return Option::Some(Box::new(self.core_game_state.take().unwrap()));
             }
@@ -320,7 +536,7 @@ impl GameState for LoadingState {
             draw_text(
                 &format!("Loading: {}... ", self.sub_state),
                 10.,
-                CELL_WIDTH * BOARD_WIDTH as f32 / 2.,
+                BOARD_EXTENT / 2.,
                 FONT_SIZE * 1.5,
                 GREEN,
             );
@@ -358,49 +574,122 @@ fn egui_setup_fonts(egui_ctx: &egui::Context) {
     egui_ctx.set_visuals(visuals);
 }
 
-fn set_up_pieces(team_count: usize, _game_ref: &Game) -> Vec<GameCommand> {
-    let start_pieces = 6;
-
-    let mut events = vec![];
-
-    for _ in 0..team_count {
-        events.push(GameCommand::InitPlayer(start_pieces));
+fn create_core_game_state(
+    canvas_width: f32,
+    canvas_height: f32,
+    setup: GameSetup,
+) -> CoreGameState {
+    let board = setup.board.create_board();
+    let layout = compute_layout(canvas_width, canvas_height, board.w, board.h);
+
+    let game = Rc::new(RefCell::new(init_game(
+        board,
+        setup.num_players,
+        setup.pieces,
+        setup.online,
+    )));
+    let mut event_broker = EventBroker::new();
+    event_broker.subscribe(Box::new(BoardEventConsumer::new(Rc::clone(&game))));
+
+    let board_render = Rc::new(RefCell::new(BoardRender::new(&(*game).borrow(), &layout)));
+    event_broker.subscribe(Box::new(RenderEventConsumer::new(&board_render)));
+
+    let bots: Vec<Bot> = match setup.bots {
+        Some(difficulty) => (1..setup.num_players)
+            .map(|team_id| Bot::new(team_id, difficulty))
+            .collect(),
+        None => vec![],
+    };
+    // A bot would replay a turn right away once it is rewound to
+    let hot_seat = bots.is_empty();
+
+    let core_game_state = CoreGameState::new(
+        game,
+        event_broker,
+        board_render,
+        Option::None,
+        false,
+        TEAM_NAMES[..setup.num_players]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        layout,
+    )
+    .with_bots(bots);
+
+    if hot_seat {
+        core_game_state.with_turn_rewind()
+    } else {
+        core_game_state
     }
+}
 
-    for team_id in 0..team_count {
-        let target_point = Point2::new((2 + team_id * 3) as u8, (2 + team_id * 3) as u8);
-        events.push(GameCommand::PlacePiece(target_point));
-        events.push(GameCommand::NextTurn);
-
-        /*let mut piece = Piece::new(team_id, PieceKind::Simple);
-        piece.exhaustion.reset();
-        finish_turn.place_piece(target_point, piece);
-
-        for _ in 0..start_pieces {
-            finish_turn.add_unused_piece(team_id);
-        }
-
-        let compound_event = finish_turn.build();
-        BoardEventConsumer::flush_unsafe(&mut game_ref.clone(), &compound_event);
-        events.push(compound_event);*/
+/// A new game on `board` with the rules configured via an environment variable. Offline
+/// games also use the configured patterns and pieces, or else the `pieces` chosen in the menu.
+fn init_game(board: Board, num_teams: usize, pieces: PieceChoice, online: bool) -> Game {
+    let teams = (0..num_teams)
+        .map(|id| Team {
+            id,
+            lost: false,
+            unused_pieces: 0,
+        })
+        .collect();
+    // Online, the rules of the player who starts the game are sent to the others
+    let game = Game::new(teams, board.w, board.h)
+        .with_board(board)
+        .with_rules(load_definition(
+            RULES_ENV_VAR,
+            RuleSet::parse,
+            RuleSet::default,
+        ));
+
+    // The patterns and pieces aren't exchanged when connecting, so local ones would desync
+    // the peers
+    if online {
+        return game;
     }
 
-    events
+    game.with_patterns(load_definition(
+        PATTERNS_ENV_VAR,
+        Pattern::parse_all,
+        Pattern::default_patterns,
+    ))
+    .with_pieces(load_definition(
+        PIECES_ENV_VAR,
+        PieceDefinition::parse_all,
+        || pieces.definitions(),
+    ))
 }
 
-fn init_game() -> Game {
-    let teams = vec![
-        Team {
-            id: 0,
-            lost: false,
-            unused_pieces: 0,
-        },
-        Team {
-            id: 1,
-            lost: false,
-            unused_pieces: 0,
-        },
-    ];
+/// Environment variable pointing to an alternative pattern file (native builds only).
+const PATTERNS_ENV_VAR: &str = "BUGCHESS_PATTERNS";
+
+/// Environment variable pointing to an alternative piece file (native builds only).
+const PIECES_ENV_VAR: &str = "BUGCHESS_PIECES";
+
+/// Environment variable pointing to a rule file with house rules (native builds only).
+const RULES_ENV_VAR: &str = "BUGCHESS_RULES";
+
+/// Parses the file `env_var` points to. Falls back to `default` if no file is configured,
+/// e.g. in the browser, or it can't be loaded.
+fn load_definition<T>(
+    env_var: &str,
+    parse: impl FnOnce(&str) -> GameResult<T>,
+    default: impl FnOnce() -> T,
+) -> T {
+    if let Ok(path) = std::env::var(env_var) {
+        let definition = std::fs::read_to_string(&path)
+            .map_err(|e| GameError::InvalidDefinition(e.to_string()))
+            .and_then(|json| parse(&json));
+
+        match definition {
+            Ok(definition) => {
+                info!("Loaded {} from {}", env_var, path);
+                return definition;
+            }
+            Err(e) => error!("Could not load {} from {}: {}", env_var, path, e),
+        }
+    }
 
-    Game::new(teams, BOARD_WIDTH, BOARD_HEIGHT)
+    default()
 }
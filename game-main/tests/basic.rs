@@ -1,8 +1,12 @@
 use std::{cell::RefCell, rc::Rc};
 
-use game_core::{core_game::CoreGameSubstate, multiplayer_connector::MultiplayerConector};
+use game_core::{
+    command_handler::UndoRequest, core_game::CoreGameSubstate, game_controller::GameCommand,
+    multiplayer_connector::MultiplayerConector,
+};
 use game_model::{
     game::Game,
+    outcome::{GameOutcome, WinReason},
     piece::{EffectKind::Protection, PieceKind},
 };
 mod utils;
@@ -29,6 +33,8 @@ fn test_merge_piece_multiplayer() {
     test_game2.assert_has_game_state(CoreGameSubstate::Place);
     test_game2.assert_num_pieces(1, 0);
     test_game2.assert_piece_at((1, 2), PieceKind::VerticalBar);
+
+    test_game1.assert_in_sync_with(&test_game2);
 }
 
 #[test]
@@ -116,17 +122,79 @@ fn test_remove_effects() {
     game.board.for_each_cell(|c| assert!(c.effects.is_empty()));
 }
 
+#[test]
+fn test_game_over_multiplayer() {
+    let (mut test_game1, mut test_game2) = create_multiplayer_game();
+    test_game1.add_unused_pieces(16, 0);
+    test_game2.add_unused_pieces(16, 0);
+
+    // Ending the turn gives the 20th unused piece
+    test_game1.next_turn();
+    test_game2.recieve_multiplayer_events();
+
+    let outcome = GameOutcome::Win {
+        team_id: 0,
+        reason: WinReason::UnusedPieces,
+    };
+    for test_game in [&test_game1, &test_game2] {
+        test_game.assert_game_over(outcome);
+        assert!(test_game.game.borrow().teams[1].lost);
+    }
+    test_game1.assert_in_sync_with(&test_game2);
+}
+
+#[test]
+fn test_undo_needs_the_opponents_consent() {
+    let (mut test_game1, mut test_game2) = create_multiplayer_game();
+    test_game1.click_at_pos((0, 0));
+    test_game2.recieve_multiplayer_events();
+
+    let game = test_game1.game.borrow().clone();
+    assert!(
+        test_game1
+            .command_handler
+            .handle_new_command(game, &GameCommand::Undo)
+            .is_err(),
+        "undo without a request"
+    );
+
+    test_game1.request_undo();
+    test_game2.recieve_multiplayer_events();
+    assert_eq!(
+        test_game2.command_handler.undo_request(),
+        Some(UndoRequest::Received(0))
+    );
+    test_game2.answer_undo_request(false);
+    test_game1.recieve_multiplayer_events();
+    assert_eq!(
+        test_game1.command_handler.undo_request(),
+        Some(UndoRequest::Declined)
+    );
+    test_game1.assert_piece_at((0, 0), PieceKind::Simple);
+
+    test_game1.request_undo();
+    test_game2.recieve_multiplayer_events();
+    test_game2.answer_undo_request(true);
+    test_game1.recieve_multiplayer_events();
+    test_game2.recieve_multiplayer_events();
+
+    test_game1.assert_num_pieces(0, 0);
+    test_game2.assert_num_pieces(0, 0);
+    assert_eq!(test_game1.command_handler.undo_request(), None);
+    test_game1.assert_in_sync_with(&test_game2);
+}
+
 fn assert_protection_at(game: &Game, pos: (u8, u8)) {
     assert!(game.board.has_effect_at(&Protection, &pos.into()));
 }
 
 #[test]
-fn test_accept_reconnection_updates_opponent_id() {
+fn test_accept_reconnection_replaces_peer_id() {
     let (client1, _client2) = FakeboxClient::new_client_pair();
 
     let mut connector = MultiplayerConector::new(Box::new(client1.clone()));
-    connector.matchmaking(); // detects client2, sets opponent_id = "2"
-    assert_eq!(connector.opponent_id, Some("2".to_string()));
+    connector.matchmaking(); // detects client2, sets peer_ids = ["2"]
+    assert_eq!(connector.peer_ids, vec!["2".to_string()]);
 
     // Simulate client2 disconnecting and a new peer ("3") reconnecting
     client1.borrow_mut().disconnect();
@@ -135,10 +203,10 @@ fn test_accept_reconnection_updates_opponent_id() {
     let client3 = Rc::new(RefCell::new(client3));
     client1.borrow_mut().connect(client3.clone());
 
-    // accept_reconnection detects the new peer and updates opponent_id
+    // accept_reconnection detects the new peer and replaces the old one
     let reconnected = connector.accept_connection();
     assert!(reconnected, "expected new peer to be detected");
-    assert_eq!(connector.opponent_id, Some("3".to_string()));
+    assert_eq!(connector.peer_ids, vec!["3".to_string()]);
 }
 
 #[test]
@@ -16,7 +16,9 @@ use game_events::{
     event_broker::{EventBroker, EventConsumer},
 };
 use game_model::{
+    GameResult,
     game::{Game, Team},
+    outcome::GameOutcome,
     piece::PieceKind,
 };
 use game_render::{BoardRender, layout::compute_layout, render_events::RenderEventConsumer};
@@ -31,9 +33,10 @@ pub struct TestGame {
 
 impl TestGame {
     pub fn add_unused_pieces(&mut self, t1: u8, t2: u8) {
-        let teams = &mut (*self.game).borrow_mut().teams;
-        teams[0].unused_pieces += t1;
-        teams[1].unused_pieces += t2;
+        let game = &mut (*self.game).borrow_mut();
+        game.teams[0].unused_pieces += t1;
+        game.teams[1].unused_pieces += t2;
+        game.rehash();
     }
 
     pub fn recieve_multiplayer_events(&mut self) {
@@ -45,7 +48,9 @@ impl TestGame {
 
         recieved_events.iter().for_each(|e| {
             let game = (*self.game.borrow()).clone();
-            self.command_handler.handle_remote_command(game, e)
+            self.command_handler
+                .handle_remote_command(game, e)
+                .expect("Remote command was rejected")
         });
     }
 
@@ -59,7 +64,17 @@ impl TestGame {
     pub fn next_turn(&mut self) {
         let game = (*self.game).borrow().clone();
         self.command_handler
-            .handle_new_command(game, &GameCommand::NextTurn);
+            .handle_new_command(game, &GameCommand::NextTurn)
+            .unwrap();
+    }
+
+    pub fn request_undo(&mut self) {
+        let game = (*self.game).borrow().clone();
+        self.command_handler.request_undo(game).unwrap();
+    }
+
+    pub fn answer_undo_request(&mut self, accept: bool) {
+        self.command_handler.answer_undo_request(accept).unwrap();
     }
 
     pub fn signal_connect(&mut self) {
@@ -79,6 +94,19 @@ impl TestGame {
         assert_eq!(game.board.placed_pieces(1).len(), num_pieces_team_2);
     }
 
+    pub fn assert_game_over(&self, outcome: GameOutcome) {
+        assert_eq!(self.command_handler.outcome(), Some(outcome));
+
+        match self.logs.borrow().back() {
+            Some(GameAction::GameOver(game_over)) => assert_eq!(*game_over.outcome(), outcome),
+            event => panic!("Expected game over event but was {:?}", event),
+        }
+    }
+
+    pub fn assert_in_sync_with(&self, other: &TestGame) {
+        assert_eq!(self.game.borrow().hash, other.game.borrow().hash);
+    }
+
     pub fn assert_piece_at(&self, piece_pos: (u8, u8), piece_kind: PieceKind) {
         let game = &(*self.game.borrow());
         let placed_piece = game
@@ -120,7 +148,7 @@ pub fn create_singleplayer_game() -> TestGame {
     }));
     let game = Rc::new(RefCell::new(create_game_object()));
     event_broker.subscribe(Box::new(BoardEventConsumer::new(game.clone())));
-    let layout = compute_layout(1080.0, 1920.0);
+    let layout = compute_layout(1080.0, 1920.0, game.borrow().board.w, game.borrow().board.h);
     let board_render = BoardRender::new(&game.borrow(), &layout);
     event_broker.subscribe(Box::new(RenderEventConsumer::new(&Rc::new(RefCell::new(
         board_render,
@@ -156,7 +184,8 @@ pub struct EventLogger {
 }
 
 impl EventConsumer for EventLogger {
-    fn handle_event(&mut self, event: &GameAction) {
+    fn handle_event(&mut self, event: &GameAction) -> GameResult<()> {
         (*self.events).borrow_mut().push_back(event.clone());
+        Ok(())
     }
 }
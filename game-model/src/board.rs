@@ -3,11 +3,45 @@ use std::fmt::Display;
 use crate::{GameError, GameResult, Point2, piece::*};
 use nanoserde::{DeJson, SerJson};
 
+/// The cross shaped arena shipped with the game, see [`Board::from_map`] for the format.
+const CROSS_ARENA_MAP: &str = include_str!("../resources/maps/cross_arena.txt");
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, DeJson, SerJson)]
+pub enum CellKind {
+    #[default]
+    Floor,
+    /// Part of the board, but nothing can enter it and it blocks every range.
+    Wall,
+    /// Not part of the board at all.
+    Hole,
+}
+
+impl CellKind {
+    fn from_char(c: char) -> Option<CellKind> {
+        match c {
+            '.' => Some(CellKind::Floor),
+            '#' => Some(CellKind::Wall),
+            '~' => Some(CellKind::Hole),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            CellKind::Floor => '.',
+            CellKind::Wall => '#',
+            CellKind::Hole => '~',
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, DeJson, SerJson)]
 pub struct Cell {
     pub point: Point2,
     pub piece: Option<Piece>,
-    pub effects: Vec<EffectKind>,
+    pub effects: Vec<CellEffect>,
+    #[nserde(default)]
+    pub kind: CellKind,
 }
 
 #[derive(Clone, PartialEq, Debug, Eq, DeJson, SerJson)]
@@ -18,6 +52,10 @@ pub struct Board {
 }
 
 impl Board {
+    /// The smallest width and height a game can start on, so every team has a corner of
+    /// its own for its first piece.
+    pub const MIN_SIZE: u8 = 2;
+
     pub fn new(width: u8, height: u8) -> Board {
         let mut cells = vec![];
 
@@ -28,6 +66,7 @@ impl Board {
                     point: Point2::new(x, y),
                     piece: Option::None,
                     effects: vec![],
+                    kind: CellKind::Floor,
                 });
             }
             cells.push(column);
@@ -40,6 +79,86 @@ impl Board {
         }
     }
 
+    /// A board like [`Board::new`] that rejects sizes below [`Board::MIN_SIZE`].
+    pub fn with_size(width: u8, height: u8) -> GameResult<Board> {
+        if width < Board::MIN_SIZE || height < Board::MIN_SIZE {
+            return Err(GameError::BoardTooSmall(width, height));
+        }
+
+        Ok(Board::new(width, height))
+    }
+
+    /// Builds a board from a map with one line per row: `.` is a floor cell,
+    /// `#` a wall and `~` a hole.
+    pub fn from_map(map: &str) -> GameResult<Board> {
+        let rows = map
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.chars()
+                    .map(|c| {
+                        CellKind::from_char(c).ok_or(GameError::InvalidDefinition(format!(
+                            "Unknown cell '{}' in board map",
+                            c
+                        )))
+                    })
+                    .collect::<GameResult<Vec<CellKind>>>()
+            })
+            .collect::<GameResult<Vec<Vec<CellKind>>>>()?;
+
+        let width = rows.first().map(Vec::len).unwrap_or(0);
+        if width == 0 || rows.iter().any(|row| row.len() != width) {
+            return Err(GameError::InvalidDefinition(
+                "Board map must be a non-empty rectangle".to_string(),
+            ));
+        }
+        if width > u8::MAX as usize || rows.len() > u8::MAX as usize {
+            return Err(GameError::InvalidDefinition(
+                "Board map is too large".to_string(),
+            ));
+        }
+
+        let mut board = Board::new(width as u8, rows.len() as u8);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, kind) in row.iter().enumerate() {
+                board.cells[x][y].kind = *kind;
+            }
+        }
+
+        Ok(board)
+    }
+
+    /// The map of this board's cells as read by [`Board::from_map`]. Pieces and effects
+    /// aren't part of a map.
+    pub fn to_map(&self) -> String {
+        (0..self.h as usize)
+            .map(|y| {
+                let mut row: String = (0..self.w as usize)
+                    .map(|x| self.cells[x][y].kind.to_char())
+                    .collect();
+                row.push('\n');
+                row
+            })
+            .collect()
+    }
+
+    /// A copy of this board's cells without pieces or effects, like at the start of a game.
+    pub fn layout(&self) -> Board {
+        let mut board = self.clone();
+        board.for_each_cell_mut(|cell| {
+            cell.piece = None;
+            cell.effects.clear();
+        });
+        board
+    }
+
+    /// A 10x10 cross with its corners cut away and a wall in the middle.
+    pub fn cross_arena() -> Board {
+        Board::from_map(CROSS_ARENA_MAP)
+            .unwrap_or_else(|e| panic!("Built-in arena is invalid: {:?}", e))
+    }
+
     pub fn for_each_cell_mut<F>(&mut self, mut closure: F)
     where
         F: FnMut(&mut Cell),
@@ -95,12 +214,25 @@ impl Board {
         pieces
     }
 
-    pub fn has_effect_at(&self, effect: &EffectKind, pos: &Point2) -> bool {
+    pub fn has_effect_at(&self, kind: &EffectKind, pos: &Point2) -> bool {
         self.get_cell(pos)
-            .map(|cell| cell.effects.contains(effect))
+            .map(|cell| cell.effects.iter().any(|effect| effect.kind == *kind))
             .unwrap_or(false)
     }
 
+    /// Whether special abilities can't target the cell: it is protected and not revealed.
+    pub fn is_protected(&self, pos: &Point2) -> bool {
+        self.has_effect_at(&EffectKind::Protection, pos)
+            && !self.has_effect_at(&EffectKind::Reveal, pos)
+    }
+
+    /// How often the effect, with exactly these turns left, is stacked on the cell.
+    pub fn effect_count(&self, effect: &CellEffect, pos: &Point2) -> usize {
+        self.get_cell(pos)
+            .map(|cell| cell.effects.iter().filter(|e| *e == effect).count())
+            .unwrap_or(0)
+    }
+
     pub fn get_piece_at(&self, pos: &Point2) -> Option<&Piece> {
         if !self.has_cell(pos) {
             return Option::None;
@@ -120,17 +252,37 @@ impl Board {
     }
 
     pub fn has_cell(&self, point: &Point2) -> bool {
-        point.x < self.w && point.y < self.h
+        self.cell_kind(point)
+            .is_some_and(|kind| kind != CellKind::Hole)
+    }
+
+    /// The kind of the cell at `point`, or `None` if it lies outside of the board's bounds.
+    pub fn cell_kind(&self, point: &Point2) -> Option<CellKind> {
+        if point.x < self.w && point.y < self.h {
+            Some(self.cells[point.x as usize][point.y as usize].kind)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_wall(&self, point: &Point2) -> bool {
+        self.cell_kind(point) == Some(CellKind::Wall)
+    }
+
+    /// Whether pieces can stand on the cell at `point`.
+    pub fn is_floor(&self, point: &Point2) -> bool {
+        self.cell_kind(point) == Some(CellKind::Floor)
     }
 
     pub fn place_piece_at(&mut self, piece: Piece, pos: &Point2) -> GameResult<()> {
         let target_cell = self.get_cell_mut(pos)?;
 
-        if target_cell.piece.is_some() {
-            return Err(GameError::new(format!(
-                "Can't place on top of another piece at {:?}",
-                pos
-            )));
+        if target_cell.kind != CellKind::Floor {
+            return Err(GameError::NotFloor(*pos, target_cell.kind));
+        }
+
+        if let Some(present) = target_cell.piece {
+            return Err(GameError::CellOccupied(*pos, present));
         }
 
         target_cell.piece = Some(piece);
@@ -138,21 +290,18 @@ impl Board {
         Ok(())
     }
 
-    pub fn add_effect(&mut self, kind: EffectKind, pos: &Point2) -> GameResult<()> {
-        self.get_cell_mut(pos)?.effects.push(kind);
+    pub fn add_effect(&mut self, effect: CellEffect, pos: &Point2) -> GameResult<()> {
+        self.get_cell_mut(pos)?.effects.push(effect);
 
         Ok(())
     }
 
-    pub fn remove_effect(&mut self, kind: &EffectKind, pos: &Point2) -> GameResult<()> {
+    pub fn remove_effect(&mut self, effect: &CellEffect, pos: &Point2) -> GameResult<()> {
         let effects = &mut self.get_cell_mut(pos)?.effects;
         let index = effects
             .iter()
-            .position(|e| e == kind)
-            .ok_or(GameError::new(format!(
-                "Can't remove effect {:?} at {:?} because it doesn't exist",
-                kind, pos
-            )))?;
+            .position(|e| e == effect)
+            .ok_or(GameError::MissingEffect(*effect, *pos))?;
         effects.swap_remove(index);
 
         Ok(())
@@ -162,20 +311,14 @@ impl Board {
         self.cells
             .get_mut(pos.x as usize)
             .and_then(|cells| cells.get_mut(pos.y as usize))
-            .ok_or(GameError::new(format!(
-                "Can't get cell {} because it's not on the board",
-                pos
-            )))
+            .ok_or(GameError::OutOfBoard(*pos))
     }
 
     fn get_cell(&self, pos: &Point2) -> GameResult<&Cell> {
         self.cells
             .get(pos.x as usize)
             .and_then(|cells| cells.get(pos.y as usize))
-            .ok_or(GameError::new(format!(
-                "Can't get cell {} because it's not on the board",
-                pos
-            )))
+            .ok_or(GameError::OutOfBoard(*pos))
     }
 
     pub fn remove_piece_at(&mut self, pos: &Point2) -> GameResult<Option<Piece>> {
@@ -185,9 +328,12 @@ impl Board {
 
 impl Display for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "    0   1   2   3   4   5   6   7")?;
+        let label_width = self.h.saturating_sub(1).to_string().len();
+
+        let header: String = (0..self.w).map(|x| format!("{:<4}", x)).collect();
+        writeln!(f, "{}{}", " ".repeat(label_width + 3), header.trim_end())?;
         for y in 0..self.h as usize {
-            write!(f, "{}:|", y)?;
+            write!(f, "{:>label_width$}:|", y)?;
             for x in 0..self.w as usize {
                 write!(f, "{}", self.cells[x][y])?;
             }
@@ -213,7 +359,16 @@ impl Display for Cell {
         write!(f, "{}", cell)
         */
 
-        let effect = if !self.effects.is_empty() { "p" } else { " " };
+        match self.kind {
+            CellKind::Wall => return write!(f, "###|"),
+            CellKind::Hole => return write!(f, "~~~|"),
+            CellKind::Floor => {}
+        }
+
+        let effect = self
+            .effects
+            .first()
+            .map_or(' ', |effect| effect.kind.symbol());
 
         let piece = if let Some(piece) = self.piece {
             format!("{}{}", piece.team_id, piece)
@@ -224,3 +379,88 @@ impl Display for Cell {
         write!(f, "{}{}|", effect, piece)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_labels_fit_the_board_size() {
+        let board = Board::new(12, 12);
+        let text = board.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 13);
+        assert!(lines[0].starts_with("     0   1"));
+        assert!(lines[0].ends_with("10  11"));
+        assert!(lines[1].starts_with(" 0:|"));
+        assert!(lines[12].starts_with("11:|"));
+        assert_eq!(lines[1].len(), lines[12].len());
+    }
+
+    #[test]
+    fn maps_define_walls_and_holes() {
+        let board = Board::from_map("~..\n.#.\n").unwrap();
+
+        assert_eq!((board.w, board.h), (3, 2));
+        assert!(!board.has_cell(&Point2::new(0, 0)));
+        assert!(board.has_cell(&Point2::new(1, 1)));
+        assert!(board.is_wall(&Point2::new(1, 1)));
+        assert!(board.is_floor(&Point2::new(2, 1)));
+        assert!(!board.has_cell(&Point2::new(3, 1)));
+
+        let mut board = board;
+        let piece = Piece::new(0, PieceKind::Simple);
+        assert_eq!(
+            board.place_piece_at(piece, &Point2::new(1, 1)),
+            Err(GameError::NotFloor(Point2::new(1, 1), CellKind::Wall))
+        );
+        assert_eq!(
+            board.place_piece_at(piece, &Point2::new(0, 0)),
+            Err(GameError::NotFloor(Point2::new(0, 0), CellKind::Hole))
+        );
+        assert!(board.place_piece_at(piece, &Point2::new(1, 0)).is_ok());
+        assert_eq!(
+            board.place_piece_at(piece, &Point2::new(1, 0)),
+            Err(GameError::CellOccupied(Point2::new(1, 0), piece))
+        );
+        assert_eq!(
+            board.place_piece_at(piece, &Point2::new(5, 0)),
+            Err(GameError::OutOfBoard(Point2::new(5, 0)))
+        );
+
+        assert!(Board::from_map("..\n.").is_err());
+        assert!(Board::from_map(".x").is_err());
+        assert_eq!(Board::with_size(0, 8), Err(GameError::BoardTooSmall(0, 8)));
+        assert_eq!(Board::with_size(2, 1), Err(GameError::BoardTooSmall(2, 1)));
+        assert_eq!(Board::with_size(2, 2), Ok(Board::new(2, 2)));
+        assert_eq!(Board::cross_arena().w, 10);
+
+        assert_eq!(board.to_map(), "~..\n.#.\n");
+        assert_eq!(board.layout(), Board::from_map(&board.to_map()).unwrap());
+    }
+
+    #[test]
+    fn effects_are_matched_by_kind_and_duration() {
+        let mut board = Board::new(4, 4);
+        let at = Point2::new(1, 1);
+        board
+            .add_effect(CellEffect::timed(EffectKind::Protection, 2), &at)
+            .unwrap();
+        assert!(board.is_protected(&at));
+
+        assert_eq!(
+            board.remove_effect(&CellEffect::lasting(EffectKind::Protection), &at),
+            Err(GameError::MissingEffect(
+                CellEffect::lasting(EffectKind::Protection),
+                at
+            ))
+        );
+
+        board
+            .add_effect(CellEffect::lasting(EffectKind::Reveal), &at)
+            .unwrap();
+        assert!(board.has_effect_at(&EffectKind::Protection, &at));
+        assert!(!board.is_protected(&at), "revealed cells aren't protected");
+    }
+}
@@ -1,12 +1,75 @@
-use crate::board::Board;
+use crate::{
+    board::Board,
+    pattern::Pattern,
+    piece::{Piece, PieceDefinition, PieceKind},
+    rules::RuleSet,
+    zobrist,
+};
+use log::debug;
 use nanoserde::{DeJson, SerJson};
 use std::fmt::{Display, Formatter};
 
 #[derive(Clone, PartialEq, Eq, Debug, DeJson, SerJson)]
+#[nserde(proxy = "SavedGame")]
 pub struct Game {
     pub board: Board,
     pub teams: Vec<Team>,
     pub current_team_index: usize,
+    /// The merge recipes. Games saved before they were part of the game use the built-in ones.
+    #[nserde(default_with = "Pattern::default_patterns")]
+    pub patterns: Vec<Pattern>,
+    /// The stats of new pieces. Kinds missing here keep their built-in stats.
+    #[nserde(default)]
+    pub pieces: Vec<PieceDefinition>,
+    #[nserde(default)]
+    pub rules: RuleSet,
+    /// Zobrist hash of the position, see [`zobrist`]. Kept up to date by the
+    /// `BoardEventConsumer`, call [`Game::rehash`] after changing the game directly.
+    /// Not saved, deserializing a game computes it.
+    pub hash: u64,
+}
+
+/// The saved form of a [`Game`], everything but its hash.
+#[derive(DeJson, SerJson)]
+struct SavedGame {
+    board: Board,
+    teams: Vec<Team>,
+    current_team_index: usize,
+    #[nserde(default_with = "Pattern::default_patterns")]
+    patterns: Vec<Pattern>,
+    #[nserde(default)]
+    pieces: Vec<PieceDefinition>,
+    #[nserde(default)]
+    rules: RuleSet,
+}
+
+impl From<&Game> for SavedGame {
+    fn from(game: &Game) -> Self {
+        SavedGame {
+            board: game.board.clone(),
+            teams: game.teams.clone(),
+            current_team_index: game.current_team_index,
+            patterns: game.patterns.clone(),
+            pieces: game.pieces.clone(),
+            rules: game.rules,
+        }
+    }
+}
+
+impl From<&SavedGame> for Game {
+    fn from(saved: &SavedGame) -> Self {
+        let mut game = Game {
+            board: saved.board.clone(),
+            teams: saved.teams.clone(),
+            current_team_index: saved.current_team_index,
+            patterns: saved.patterns.clone(),
+            pieces: saved.pieces.clone(),
+            rules: saved.rules,
+            hash: 0,
+        };
+        game.rehash();
+        game
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, DeJson, SerJson)]
@@ -49,11 +112,52 @@ impl Display for Game {
 
 impl Game {
     pub fn new(teams: Vec<Team>, board_width: u8, board_height: u8) -> Self {
-        Game {
+        let mut game = Game {
             board: Board::new(board_width, board_height),
             teams,
             current_team_index: 0,
-        }
+            patterns: Pattern::default_patterns(),
+            pieces: PieceDefinition::default_definitions(),
+            rules: RuleSet::default(),
+            hash: 0,
+        };
+        game.rehash();
+        game
+    }
+
+    /// Replaces the board, e.g. with one built via [`Board::from_map`].
+    pub fn with_board(mut self, board: Board) -> Self {
+        self.board = board;
+        self.rehash();
+        self
+    }
+
+    /// Replaces the merge recipes, e.g. with a set loaded via [`Pattern::parse_all`].
+    pub fn with_patterns(mut self, patterns: Vec<Pattern>) -> Self {
+        self.patterns = patterns;
+        self
+    }
+
+    /// Replaces the piece stats, e.g. with a table loaded via [`PieceDefinition::parse_all`].
+    pub fn with_pieces(mut self, pieces: Vec<PieceDefinition>) -> Self {
+        self.pieces = pieces;
+        self
+    }
+
+    /// Replaces the rules, e.g. with house rules loaded via [`RuleSet::parse`].
+    pub fn with_rules(mut self, rules: RuleSet) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// A new piece of `kind` with the stats this game is played with.
+    pub fn new_piece(&self, team_id: usize, kind: PieceKind) -> Piece {
+        Piece::from_definition(team_id, PieceDefinition::find(&self.pieces, kind))
+    }
+
+    /// Recomputes [`Game::hash`] from scratch.
+    pub fn rehash(&mut self) {
+        self.hash = zobrist::hash(self);
     }
 
     pub fn num_unused_pieces_of(&self, team_id: usize) -> u8 {
@@ -77,13 +181,26 @@ impl Game {
     }
 
     pub fn mark_team_as_lost(&mut self, team_id: usize) {
-        println!("Team {} lost.", team_id);
+        debug!("Team {} lost.", team_id);
         self.teams[team_id].lost = true;
     }
 
+    pub fn revive_team(&mut self, team_id: usize) {
+        self.teams[team_id].lost = false;
+    }
+
+    /// The teams that have neither lost nor run out of pieces on the board.
+    pub fn active_teams(&self) -> Vec<usize> {
+        self.teams
+            .iter()
+            .filter(|team| !team.lost && !self.board.placed_pieces(team.id).is_empty())
+            .map(|team| team.id)
+            .collect()
+    }
+
     pub fn next_team(&mut self) -> Option<Team> {
         let initial_team_index = self.current_team_index;
-        println!("From team {} to next team.", self.current_team_index);
+        debug!("From team {} to next team.", self.current_team_index);
 
         loop {
             self.current_team_index += 1;
@@ -92,10 +209,10 @@ impl Game {
             }
 
             if self.current_team_index == initial_team_index {
-                println!("No next team. Current team {}", self.current_team_index);
+                debug!("No next team. Current team {}", self.current_team_index);
                 return None; // All (other) teams lost
             } else if !self.current_team().lost {
-                println!("Next team is {}", self.current_team_index);
+                debug!("Next team is {}", self.current_team_index);
                 return Some(self.current_team());
             }
         }
@@ -118,3 +235,38 @@ impl Game {
         self.current_team().unused_pieces > 0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn games_without_patterns_use_the_default_patterns() {
+        let game = Game::new(vec![], 4, 4).with_patterns(vec![]);
+        let json = game.serialize_json();
+        let start = json.find(r#""patterns":"#).unwrap();
+        let end = json.find(r#""pieces":"#).unwrap();
+        let old_json = format!("{}{}", &json[..start], &json[end..]);
+
+        let old_game: Game = DeJson::deserialize_json(&old_json).unwrap();
+
+        assert_eq!(old_game.patterns, Pattern::default_patterns());
+        assert_eq!(old_game.board, game.board);
+    }
+
+    #[test]
+    fn deserialized_games_are_hashed() {
+        let mut game = Game::new(vec![], 4, 4);
+        game.board
+            .place_piece_at(Piece::new(0, PieceKind::Simple), &(1, 2).into())
+            .unwrap();
+        game.rehash();
+
+        let json = game.serialize_json();
+        assert!(!json.contains(r#""hash":"#));
+
+        let loaded: Game = DeJson::deserialize_json(&json).unwrap();
+        assert_ne!(loaded.hash, 0);
+        assert_eq!(loaded, game);
+    }
+}
@@ -2,36 +2,114 @@
 //!
 //! Defines the core types: [`board::Board`] and [`board::Cell`] grid, [`piece::Piece`] with
 //! movement [`ranges::Range`]s and [`piece::Power`]s, [`game::Game`] and [`game::Team`] state,
-//! and [`pattern::Pattern`] for piece-merge recipes.
+//! [`pattern::Pattern`] for piece-merge recipes and the [`rules::RuleSet`] a game is played with.
+//! [`zobrist`] hashes positions and [`outcome::GameOutcome`] describes how a game ended.
 //!
 //! This is the foundational layer; all other crates depend on it.
 
 #![allow(clippy::question_mark)]
 
+use crate::{
+    board::CellKind,
+    piece::{CellEffect, Exhaustion, Piece},
+};
 use nanoserde::{DeJson, SerJson};
 use std::fmt::Display;
 
 pub mod board;
 pub mod game;
+pub mod outcome;
 pub mod pattern;
 pub mod piece;
 pub mod ranges;
+pub mod rules;
+pub mod zobrist;
 
 pub type GameResult<T> = Result<T, GameError>;
 
-#[derive(Debug, Clone)]
-pub struct GameError {
-    _description: String,
+/// Why an operation on the model failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    /// The point lies outside of the board
+    OutOfBoard(Point2),
+    /// The cell already holds this piece
+    CellOccupied(Point2, Piece),
+    /// The cell is a wall or a hole, so no piece can stand on it
+    NotFloor(Point2, CellKind),
+    /// There is no piece at the point
+    NoPiece(Point2),
+    /// The piece at `at` isn't the one an event expected to remove
+    PieceMismatch {
+        at: Point2,
+        expected: Piece,
+        actual: Piece,
+    },
+    /// The team has no unused piece left to take
+    NoUnusedPiece(usize),
+    /// The effect to remove doesn't exist at the point
+    MissingEffect(CellEffect, Point2),
+    /// The piece at `at` isn't in the exhaustion state an event expected
+    ExhaustionMismatch {
+        at: Point2,
+        expected: Exhaustion,
+        actual: Exhaustion,
+    },
+    /// A board map, pattern file or other definition couldn't be read
+    InvalidDefinition(String),
+    /// The event ends the turn of a team whose turn it isn't
+    NotTheTurnOf(usize),
+    /// A board of this width and height has no room for the first piece of every team
+    BoardTooSmall(u8, u8),
 }
 
-impl GameError {
-    pub fn new(description: String) -> Self {
-        Self {
-            _description: description,
+impl Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameError::OutOfBoard(at) => write!(f, "{} is not on the board", at),
+            GameError::CellOccupied(at, piece) => {
+                write!(f, "{} is already occupied by {:?}", at, piece.piece_kind)
+            }
+            GameError::NotFloor(at, kind) => write!(f, "Can't stand on {:?} cell at {}", kind, at),
+            GameError::NoPiece(at) => write!(f, "There is no piece at {}", at),
+            GameError::PieceMismatch {
+                at,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Expected {:?} at {} but found {:?}",
+                expected, at, actual
+            ),
+            GameError::NoUnusedPiece(team_id) => {
+                write!(f, "Team {} has no unused piece left", team_id)
+            }
+            GameError::MissingEffect(effect, at) => {
+                write!(f, "There is no {:?} effect at {}", effect.kind, at)
+            }
+            GameError::ExhaustionMismatch {
+                at,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Expected piece at {} to have exhaustion state {:?} but it had {:?}",
+                at, expected, actual
+            ),
+            GameError::InvalidDefinition(reason) => write!(f, "{}", reason),
+            GameError::NotTheTurnOf(team_id) => write!(f, "It isn't the turn of team {}", team_id),
+            GameError::BoardTooSmall(width, height) => {
+                write!(
+                    f,
+                    "A {}x{} board is too small to start a game on",
+                    width, height
+                )
+            }
         }
     }
 }
 
+impl std::error::Error for GameError {}
+
 #[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, SerJson, DeJson)]
 pub struct Point2 {
     pub x: u8,
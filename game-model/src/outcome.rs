@@ -0,0 +1,64 @@
+use nanoserde::{DeJson, SerJson};
+use std::fmt::{Display, Formatter};
+
+/// How a game ended. The rules that decide it live in `game_core::outcome`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
+pub enum GameOutcome {
+    Win { team_id: usize, reason: WinReason },
+    Draw(DrawReason),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
+pub enum WinReason {
+    /// The team collected enough unused pieces
+    UnusedPieces,
+    /// All other teams lost their last piece
+    LastTeamStanding,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
+pub enum DrawReason {
+    /// The same position occurred too often
+    Repetition,
+    /// Too many turns passed without a capture or a merge
+    NoProgress,
+    /// No team could place, move or attack even with all its pieces refreshed
+    Stalemate,
+}
+
+impl GameOutcome {
+    pub fn winner(&self) -> Option<usize> {
+        match self {
+            GameOutcome::Win { team_id, .. } => Some(*team_id),
+            GameOutcome::Draw(_) => None,
+        }
+    }
+}
+
+impl Display for GameOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameOutcome::Win { team_id, reason } => write!(f, "Team {} won {}", team_id, reason),
+            GameOutcome::Draw(reason) => write!(f, "Draw {}", reason),
+        }
+    }
+}
+
+impl Display for WinReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WinReason::UnusedPieces => write!(f, "by collecting unused pieces"),
+            WinReason::LastTeamStanding => write!(f, "as the last team standing"),
+        }
+    }
+}
+
+impl Display for DrawReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawReason::Repetition => write!(f, "by repetition"),
+            DrawReason::NoProgress => write!(f, "for lack of progress"),
+            DrawReason::Stalemate => write!(f, "by stalemate"),
+        }
+    }
+}
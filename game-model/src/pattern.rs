@@ -1,169 +1,287 @@
-use crate::{Point2, board::Board, piece::PieceKind};
+use std::sync::LazyLock;
 
-#[derive(Debug, PartialEq, Eq)]
+use crate::{GameError, GameResult, Point2, board::Board, piece::PieceKind};
+use nanoserde::{DeJson, SerJson};
+
+/// The built-in merge recipes, see `resources/patterns.json`.
+const DEFAULT_PATTERNS_JSON: &str = include_str!("../resources/patterns.json");
+
+static DEFAULT_PATTERNS: LazyLock<Vec<Pattern>> = LazyLock::new(|| {
+    Pattern::parse_all(DEFAULT_PATTERNS_JSON)
+        .unwrap_or_else(|e| panic!("Built-in patterns are invalid: {:?}", e))
+});
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
 pub enum PatternComponent {
     OwnPiece,
     Free,
     Any,
 }
 
+impl PatternComponent {
+    fn from_char(c: char) -> Option<PatternComponent> {
+        match c {
+            'o' => Some(PatternComponent::OwnPiece),
+            '.' => Some(PatternComponent::Free),
+            '?' => Some(PatternComponent::Any),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            PatternComponent::OwnPiece => 'o',
+            PatternComponent::Free => '.',
+            PatternComponent::Any => '?',
+        }
+    }
+}
+
+/// The orientations in which a pattern may appear on the board.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, SerJson, DeJson)]
+pub enum Symmetry {
+    /// Only exactly as written.
+    #[default]
+    None,
+    /// Turned by 0°, 90°, 180° or 270°.
+    Rotation,
+    /// As written or mirrored left to right.
+    Mirror,
+    /// All rotations of the pattern and of its mirror image.
+    All,
+}
+
+impl Symmetry {
+    fn transforms(&self) -> &'static [Transform] {
+        use Transform::*;
+        match self {
+            Symmetry::None => &[Identity],
+            Symmetry::Rotation => &[Identity, Rotate90, Rotate180, Rotate270],
+            Symmetry::Mirror => &[Identity, Mirror],
+            Symmetry::All => &[
+                Identity,
+                Rotate90,
+                Rotate180,
+                Rotate270,
+                Mirror,
+                MirrorRotate90,
+                MirrorRotate180,
+                MirrorRotate270,
+            ],
+        }
+    }
+}
+
+/// One element of the symmetry group of a rectangle. Rotations are clockwise,
+/// mirroring is applied before rotating.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Transform {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    Mirror,
+    MirrorRotate90,
+    MirrorRotate180,
+    MirrorRotate270,
+}
+
+impl Transform {
+    fn swaps_axes(&self) -> bool {
+        matches!(
+            self,
+            Transform::Rotate90
+                | Transform::Rotate270
+                | Transform::MirrorRotate90
+                | Transform::MirrorRotate270
+        )
+    }
+
+    /// Maps `(x, y)` of a `w` x `h` grid to its position in the transformed grid.
+    fn apply(&self, x: usize, y: usize, w: usize, h: usize) -> (usize, usize) {
+        let (x, y, rotation) = match self {
+            Transform::Identity => (x, y, 0),
+            Transform::Rotate90 => (x, y, 1),
+            Transform::Rotate180 => (x, y, 2),
+            Transform::Rotate270 => (x, y, 3),
+            Transform::Mirror => (w - 1 - x, y, 0),
+            Transform::MirrorRotate90 => (w - 1 - x, y, 1),
+            Transform::MirrorRotate180 => (w - 1 - x, y, 2),
+            Transform::MirrorRotate270 => (w - 1 - x, y, 3),
+        };
+
+        match rotation {
+            0 => (x, y),
+            1 => (h - 1 - y, x),
+            2 => (w - 1 - x, h - 1 - y),
+            _ => (y, w - 1 - x),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, SerJson, DeJson)]
 pub struct Pattern {
     pub components: Vec<Vec<PatternComponent>>,
     pub turn_into: PieceKind,
     pub new_piece_relative_position: Point2,
+    pub symmetry: Symmetry,
+}
+
+/// A merge recipe as written in a pattern file.
+///
+/// Each line of `grid` is one row of the pattern: `o` needs an own piece,
+/// `.` needs a free cell and `?` accepts anything. `result` is the cell
+/// (relative to the top left corner of the grid) the new piece is placed on.
+/// `symmetry` lists the other orientations the recipe also matches in.
+#[derive(Debug, Clone, DeJson, SerJson)]
+pub struct PatternDefinition {
+    pub turn_into: PieceKind,
+    pub result: Point2,
+    pub grid: Vec<String>,
+    #[nserde(default)]
+    pub symmetry: Symmetry,
 }
 
 impl Pattern {
-    pub fn all_patterns() -> [Pattern; 6] {
-        // TODO make static var instead
-        [
-            Pattern {
-                components: vec![
-                    vec![
-                        PatternComponent::Any,
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                        PatternComponent::Any,
-                    ],
-                    vec![
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Free,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                    ],
-                    vec![
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Free,
-                        PatternComponent::Free,
-                        PatternComponent::Free,
-                        PatternComponent::OwnPiece,
-                    ],
-                    vec![
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Free,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                    ],
-                    vec![
-                        PatternComponent::Any,
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                        PatternComponent::Any,
-                    ],
-                ],
-                turn_into: PieceKind::Queen,
-                new_piece_relative_position: Point2::new(2, 2),
-            },
-            Pattern {
-                components: vec![
-                    vec![
-                        PatternComponent::Free,
-                        PatternComponent::Free,
-                        PatternComponent::Free,
-                    ],
-                    vec![
-                        PatternComponent::OwnPiece,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::OwnPiece,
-                    ],
-                    vec![
-                        PatternComponent::Free,
-                        PatternComponent::Free,
-                        PatternComponent::Free,
-                    ],
-                ],
-                turn_into: PieceKind::HorizontalBar,
-                new_piece_relative_position: Point2::new(1, 1),
-            },
-            Pattern {
-                components: vec![
-                    vec![
-                        PatternComponent::Free,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Free,
-                    ],
-                    vec![
-                        PatternComponent::Free,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Free,
-                    ],
-                    vec![
-                        PatternComponent::Free,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Free,
-                    ],
-                ],
-                turn_into: PieceKind::VerticalBar,
-                new_piece_relative_position: Point2::new(1, 1),
-            },
-            Pattern {
-                components: vec![
-                    vec![
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                    ],
-                    vec![
-                        PatternComponent::OwnPiece,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::OwnPiece,
-                    ],
-                    vec![
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                    ],
-                ],
-                turn_into: PieceKind::Cross,
-                new_piece_relative_position: Point2::new(1, 1),
-            },
-            Pattern {
-                components: vec![
-                    vec![
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                    ],
-                    vec![
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                    ],
-                    vec![
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                    ],
-                ],
-                turn_into: PieceKind::Sniper,
-                new_piece_relative_position: Point2::new(1, 1),
-            },
-            Pattern {
-                components: vec![
-                    vec![
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                    ],
-                    vec![
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Free,
-                        PatternComponent::OwnPiece,
-                    ],
-                    vec![
-                        PatternComponent::Any,
-                        PatternComponent::OwnPiece,
-                        PatternComponent::Any,
-                    ],
-                ],
-                turn_into: PieceKind::Castle,
-                new_piece_relative_position: Point2::new(1, 1),
-            },
-        ]
+    /// The merge recipes the game ships with.
+    pub fn default_patterns() -> Vec<Pattern> {
+        DEFAULT_PATTERNS.clone()
+    }
+
+    /// Parses a JSON array of [`PatternDefinition`]s, e.g. the content of a pattern file.
+    pub fn parse_all(json: &str) -> GameResult<Vec<Pattern>> {
+        let definitions: Vec<PatternDefinition> = DeJson::deserialize_json(json)
+            .map_err(|e| GameError::InvalidDefinition(format!("Can't parse patterns: {}", e)))?;
+
+        definitions.iter().map(Pattern::from_definition).collect()
+    }
+
+    pub fn from_definition(definition: &PatternDefinition) -> GameResult<Pattern> {
+        let components = definition
+            .grid
+            .iter()
+            .map(|line| {
+                line.chars()
+                    .map(|c| {
+                        PatternComponent::from_char(c).ok_or(GameError::InvalidDefinition(format!(
+                            "Unknown pattern component '{}' in pattern for {:?}",
+                            c, definition.turn_into
+                        )))
+                    })
+                    .collect::<GameResult<Vec<PatternComponent>>>()
+            })
+            .collect::<GameResult<Vec<Vec<PatternComponent>>>>()?;
+
+        let width = components.first().map(Vec::len).unwrap_or(0);
+        if width == 0 {
+            return Err(GameError::InvalidDefinition(format!(
+                "Pattern for {:?} has an empty grid",
+                definition.turn_into
+            )));
+        }
+
+        if components.iter().any(|line| line.len() != width) {
+            return Err(GameError::InvalidDefinition(format!(
+                "Pattern for {:?} is not rectangular",
+                definition.turn_into
+            )));
+        }
+
+        if !components
+            .iter()
+            .flatten()
+            .any(|c| c == &PatternComponent::OwnPiece)
+        {
+            return Err(GameError::InvalidDefinition(format!(
+                "Pattern for {:?} doesn't contain any own piece",
+                definition.turn_into
+            )));
+        }
+
+        let result = definition.result;
+        let result_component = components
+            .get(result.y as usize)
+            .and_then(|line| line.get(result.x as usize))
+            .ok_or(GameError::InvalidDefinition(format!(
+                "Result {} of pattern for {:?} lies outside of the grid",
+                result, definition.turn_into
+            )))?;
+
+        if result_component == &PatternComponent::Any {
+            // Any cell might be occupied by a piece that doesn't take part in the merge
+            return Err(GameError::InvalidDefinition(format!(
+                "Result {} of pattern for {:?} must be an own piece or a free cell",
+                result, definition.turn_into
+            )));
+        }
+
+        Ok(Pattern {
+            components,
+            turn_into: definition.turn_into,
+            new_piece_relative_position: result,
+            symmetry: definition.symmetry,
+        })
+    }
+
+    /// The pattern as written in a pattern file, the inverse of [`Pattern::from_definition`].
+    pub fn definition(&self) -> PatternDefinition {
+        PatternDefinition {
+            turn_into: self.turn_into,
+            result: self.new_piece_relative_position,
+            grid: self
+                .components
+                .iter()
+                .map(|line| line.iter().map(|c| c.to_char()).collect())
+                .collect(),
+            symmetry: self.symmetry,
+        }
+    }
+
+    /// Every distinct orientation allowed by the pattern's [`Symmetry`], starting with the
+    /// pattern as written. Variants that are turned by 90° also turn the resulting piece,
+    /// so a horizontal bar becomes a vertical one.
+    pub fn variants(&self) -> Vec<Pattern> {
+        let h = self.components.len();
+        let w = self.components.first().map(Vec::len).unwrap_or(0);
+
+        let mut variants: Vec<Pattern> = vec![];
+        for transform in self.symmetry.transforms() {
+            let (new_w, new_h) = if transform.swaps_axes() {
+                (h, w)
+            } else {
+                (w, h)
+            };
+
+            let mut components = vec![vec![PatternComponent::Any; new_w]; new_h];
+            for (y, line) in self.components.iter().enumerate() {
+                for (x, component) in line.iter().enumerate() {
+                    let (new_x, new_y) = transform.apply(x, y, w, h);
+                    components[new_y][new_x] = *component;
+                }
+            }
+
+            let position = self.new_piece_relative_position;
+            let (new_x, new_y) = transform.apply(position.x as usize, position.y as usize, w, h);
+
+            let turn_into = if transform.swaps_axes() {
+                self.turn_into.rotated()
+            } else {
+                self.turn_into
+            };
+
+            let variant = Pattern {
+                components,
+                turn_into,
+                new_piece_relative_position: Point2::new(new_x as u8, new_y as u8),
+                symmetry: Symmetry::None,
+            };
+
+            if !variants.contains(&variant) {
+                variants.push(variant);
+            }
+        }
+
+        variants
     }
 
     pub fn match_board(&self, board: &Board, start_x: u8, start_y: u8) -> Option<Vec<Point2>> {
@@ -181,7 +299,10 @@ impl Pattern {
                     } else if p == &PatternComponent::OwnPiece {
                         matched_entities.push(board_point);
                     }
-                } else if p == &PatternComponent::OwnPiece {
+                } else if p == &PatternComponent::OwnPiece
+                    || (p == &PatternComponent::Free && !board.is_floor(&board_point))
+                {
+                    // Walls and holes are never free
                     return None;
                 }
             }
@@ -190,3 +311,98 @@ impl Pattern {
         Option::Some(matched_entities)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(grid: &[&str], result: (u8, u8)) -> PatternDefinition {
+        PatternDefinition {
+            turn_into: PieceKind::Cross,
+            result: result.into(),
+            grid: grid.iter().map(|l| l.to_string()).collect(),
+            symmetry: Symmetry::None,
+        }
+    }
+
+    #[test]
+    fn default_patterns_are_valid() {
+        let patterns = Pattern::parse_all(DEFAULT_PATTERNS_JSON).unwrap();
+        assert_eq!(patterns.len(), 5);
+        assert_eq!(patterns[0].turn_into, PieceKind::Queen);
+        assert_eq!(patterns[0].components[2][0], PatternComponent::OwnPiece);
+        assert_eq!(patterns[0].components[2][2], PatternComponent::Free);
+        assert_eq!(patterns[0].components[0][0], PatternComponent::Any);
+    }
+
+    #[test]
+    fn definitions_describe_their_pattern() {
+        for pattern in Pattern::default_patterns() {
+            assert_eq!(Pattern::from_definition(&pattern.definition()), Ok(pattern));
+        }
+    }
+
+    #[test]
+    fn malformed_grids_are_rejected() {
+        assert!(Pattern::from_definition(&definition(&[], (0, 0))).is_err());
+        assert!(Pattern::from_definition(&definition(&["oo", "o"], (0, 0))).is_err());
+        assert!(Pattern::from_definition(&definition(&["ox"], (0, 0))).is_err());
+        assert!(Pattern::from_definition(&definition(&["..", ".."], (0, 0))).is_err());
+        assert!(Pattern::parse_all("[{\"turn_into\": \"Cross\"}]").is_err());
+    }
+
+    #[test]
+    fn result_outside_of_grid_is_rejected() {
+        assert!(Pattern::from_definition(&definition(&["ooo"], (1, 0))).is_ok());
+        assert!(Pattern::from_definition(&definition(&["ooo"], (3, 0))).is_err());
+        assert!(Pattern::from_definition(&definition(&["ooo"], (1, 1))).is_err());
+        assert!(Pattern::from_definition(&definition(&["o?o"], (1, 0))).is_err());
+    }
+
+    #[test]
+    fn variants_follow_symmetry() {
+        let patterns = Pattern::default_patterns();
+        let bar = patterns
+            .iter()
+            .find(|p| p.turn_into == PieceKind::HorizontalBar)
+            .unwrap();
+        let bars = bar.variants();
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[1].turn_into, PieceKind::VerticalBar);
+        assert_eq!(bars[1].components[0][1], PatternComponent::OwnPiece);
+        assert_eq!(bars[1].components[1][0], PatternComponent::Free);
+
+        let queen = &patterns[0];
+        let queen_variants = Pattern {
+            symmetry: Symmetry::All,
+            ..queen.clone()
+        }
+        .variants();
+        assert_eq!(queen_variants, vec![queen.clone()]);
+
+        let mut corner = definition(&["oo", "o."], (1, 1));
+        corner.symmetry = Symmetry::All;
+        let corners = Pattern::from_definition(&corner).unwrap().variants();
+        assert_eq!(corners.len(), 4);
+        let positions: Vec<Point2> = corners
+            .iter()
+            .map(|p| p.new_piece_relative_position)
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                Point2::new(1, 1),
+                Point2::new(0, 1),
+                Point2::new(0, 0),
+                Point2::new(1, 0)
+            ]
+        );
+        corners.iter().for_each(|p| {
+            let position = p.new_piece_relative_position;
+            assert_eq!(
+                p.components[position.y as usize][position.x as usize],
+                PatternComponent::Free
+            )
+        });
+    }
+}
@@ -1,10 +1,62 @@
-use crate::ranges::*;
+use std::sync::LazyLock;
+
+use crate::{GameError, GameResult, ranges::*};
 use nanoserde::{DeJson, SerJson};
 use std::fmt::{Debug, Display};
 
+/// The built-in stats of every piece kind, see `resources/pieces.json`.
+const DEFAULT_PIECES_JSON: &str = include_str!("../resources/pieces.json");
+const TRICKSTER_PIECES_JSON: &str = include_str!("../resources/tricksters.json");
+
+static DEFAULT_PIECES: LazyLock<Vec<PieceDefinition>> = LazyLock::new(|| {
+    PieceDefinition::parse_all(DEFAULT_PIECES_JSON)
+        .unwrap_or_else(|e| panic!("Built-in pieces are invalid: {:?}", e))
+});
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
 pub enum EffectKind {
+    /// Blocks special abilities like the Sniper's shot
     Protection,
+    /// The piece on the cell can't move
+    Slow,
+    /// The piece on the cell dies when the effect wears off
+    Poison,
+    /// Protection on the same cell doesn't count
+    Reveal,
+}
+
+impl EffectKind {
+    /// How the effect shows up in the text rendering of a board.
+    pub fn symbol(&self) -> char {
+        match self {
+            EffectKind::Protection => 'p',
+            EffectKind::Slow => 's',
+            EffectKind::Poison => 'x',
+            EffectKind::Reveal => 'r',
+        }
+    }
+}
+
+/// An effect lying on a cell. Without `turns` it stays until its source takes it away again,
+/// like the protection around a Castle. Otherwise it wears off when `turns` more turns ended.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
+pub struct CellEffect {
+    pub kind: EffectKind,
+    #[nserde(default)]
+    pub turns: Option<u8>,
+}
+
+impl CellEffect {
+    pub fn lasting(kind: EffectKind) -> CellEffect {
+        CellEffect { kind, turns: None }
+    }
+
+    pub fn timed(kind: EffectKind, turns: u8) -> CellEffect {
+        CellEffect {
+            kind,
+            turns: Some(turns),
+        }
+    }
 }
 
 impl Move {
@@ -36,6 +88,9 @@ impl Move {
 pub struct ActivatablePower {
     pub kind: Power,
     pub range: Range,
+    /// What an `Enchant` lays on its target
+    #[nserde(default)]
+    pub effect: Option<CellEffect>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
@@ -52,6 +107,21 @@ pub struct TurnInto {
 pub enum Power {
     Blast,
     TargetedShoot,
+    /// Shoves an enemy piece one cell further away from the acting piece
+    Push,
+    /// Exchanges the positions of the acting piece and another own piece
+    Swap,
+    /// Turns an enemy Simple piece into one of the acting team
+    Convert,
+    /// Lays the power's effect on the cell of any piece, e.g. a Protection for a turn
+    Enchant,
+}
+
+impl Power {
+    /// Whether the power acts on one target cell rather than on everything in its range.
+    pub fn is_targeted(&self) -> bool {
+        *self != Power::Blast
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, SerJson, DeJson)]
@@ -65,100 +135,149 @@ pub enum PieceKind {
     Sniper,
 }
 
+impl PieceKind {
+    /// The kind this piece becomes when its shape is turned by 90°.
+    pub fn rotated(&self) -> PieceKind {
+        match self {
+            PieceKind::HorizontalBar => PieceKind::VerticalBar,
+            PieceKind::VerticalBar => PieceKind::HorizontalBar,
+            kind => *kind,
+        }
+    }
+}
+
+/// An aura a piece lays on the cells in `range` for as long as it stands there.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
 pub struct Effect {
     pub kind: EffectKind,
     pub range: Range,
 }
 
-impl Piece {
-    pub fn simple() -> Piece {
-        Self::new(0, PieceKind::Simple)
-    }
+/// The stats every piece of a kind starts with. The built-in table is
+/// `resources/pieces.json`, a game may bring its own, see [`Game::pieces`](crate::game::Game::pieces).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
+pub struct PieceDefinition {
+    pub kind: PieceKind,
+    pub movement: Option<Range>,
+    pub power: Option<ActivatablePower>,
+    pub effect: Option<Effect>,
+    /// Protects the piece from being captured by pieces that don't pierce
+    #[nserde(default)]
+    pub shield: bool,
+    /// Allows the piece to capture shielded pieces
+    #[nserde(default_with = "pierces_by_default")]
+    pub pierce: bool,
+    #[nserde(default)]
+    pub exhaustion: ExhaustionStrategy,
+    /// How the kind is drawn. Kinds without one keep their built-in sprite.
+    pub sprite: Option<SpriteCell>,
+}
 
-    pub fn new(team_id: usize, turn_into: PieceKind) -> Piece {
-        let mut piece = Piece {
-            piece_kind: PieceKind::Simple,
-            attack: true,
-            pierce: true,
-            shield: false,
-            movement: None,
-            activatable: None,
-            effect: None,
-            exhaustion: Exhaustion::new_exhausted(ExhaustionStrategy::Either),
-            team_id,
-        };
+fn pierces_by_default() -> bool {
+    true
+}
 
-        Piece::init_piece(&mut piece, turn_into);
+/// A cell of the piece sprite sheet, which is a grid of equally sized pieces.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
+pub struct SpriteCell {
+    pub x: u8,
+    pub y: u8,
+    /// Draws the sprite turned by a quarter, e.g. a vertical bar as a horizontal one
+    #[nserde(default)]
+    pub rotated: bool,
+}
 
-        piece
+impl PieceDefinition {
+    pub fn default_definitions() -> Vec<PieceDefinition> {
+        DEFAULT_PIECES.clone()
     }
 
-    fn init_piece(piece: &mut Piece, turn_into: PieceKind) {
-        piece.piece_kind = turn_into;
-        match turn_into {
-            PieceKind::HorizontalBar => {
-                piece.movement = Some(Move::new_unlimited(Direction::Horizontal));
-                piece.activatable = Some(ActivatablePower {
-                    range: Range::new_unlimited(Direction::Horizontal, RangeContext::Moving),
-                    kind: Power::Blast,
-                });
-            }
-            PieceKind::VerticalBar => {
-                piece.movement = Some(Move::new_unlimited(Direction::Vertical));
-                piece.activatable = Some(ActivatablePower {
-                    range: Range::new_unlimited(Direction::Vertical, RangeContext::Moving),
-                    kind: Power::Blast,
-                });
-            }
-            PieceKind::Cross => {
-                piece.movement = Some(Move::new_unlimited(Direction::Straight));
-                piece.shield = true;
-            }
-            PieceKind::Simple => {
-                piece.movement = Some(Move::new(Direction::Star, 1));
-                piece.pierce = false;
-            }
-            PieceKind::Queen => {
-                piece.movement = Some(Move::new_unlimited(Direction::Star));
-                piece.exhaustion = Exhaustion::new_exhausted(ExhaustionStrategy::Both);
-                piece.activatable = Some(ActivatablePower {
-                    range: Range {
-                        direction: Direction::Star,
-                        context: RangeContext::Special,
-                        steps: 1,
-                        jumps: false,
-                        include_self: false,
-                    },
-                    kind: Power::Blast,
-                });
-            }
-            PieceKind::Castle => {
-                piece.shield = true;
-                piece.effect = Some(Effect {
-                    kind: EffectKind::Protection,
-                    range: Range {
-                        direction: Direction::Star,
-                        context: RangeContext::Area,
-                        steps: 1,
-                        jumps: true,
-                        include_self: true,
-                    },
-                });
+    /// An example table, `resources/tricksters.json`, in which the Cross pushes, the Castle
+    /// swaps and the Sniper converts instead of shooting. The other kinds keep their stats.
+    pub fn trickster_definitions() -> Vec<PieceDefinition> {
+        PieceDefinition::parse_all(TRICKSTER_PIECES_JSON)
+            .unwrap_or_else(|e| panic!("Trickster pieces are invalid: {:?}", e))
+    }
+
+    /// Parses a JSON array of definitions, e.g. the content of a piece file. Each kind may
+    /// be defined at most once, kinds that are left out keep their built-in stats.
+    pub fn parse_all(json: &str) -> GameResult<Vec<PieceDefinition>> {
+        let definitions: Vec<PieceDefinition> = DeJson::deserialize_json(json)
+            .map_err(|e| GameError::InvalidDefinition(format!("Can't parse pieces: {}", e)))?;
+
+        for (i, definition) in definitions.iter().enumerate() {
+            if definitions[..i].iter().any(|d| d.kind == definition.kind) {
+                return Err(GameError::InvalidDefinition(format!(
+                    "{:?} is defined more than once",
+                    definition.kind
+                )));
             }
-            PieceKind::Sniper => {
-                piece.activatable = Some(ActivatablePower {
-                    range: Range {
-                        direction: Direction::Anywhere,
-                        context: RangeContext::Special,
-                        steps: 0,
-                        jumps: false,
-                        include_self: false,
-                    },
-                    kind: Power::TargetedShoot,
-                });
+
+            if let Some(power) = definition.power {
+                let enchants = power.kind == Power::Enchant;
+                let valid_effect = power.effect.is_some_and(|effect| effect.turns != Some(0));
+                if enchants != valid_effect {
+                    return Err(GameError::InvalidDefinition(format!(
+                        "The power of {:?} needs an effect that lasts at least a turn \
+                         if and only if it is an Enchant",
+                        definition.kind
+                    )));
+                }
+                // Only an area reaches own pieces
+                if power.kind == Power::Swap && power.range.context != RangeContext::Area {
+                    return Err(GameError::InvalidDefinition(format!(
+                        "The Swap of {:?} needs an Area range to reach own pieces",
+                        definition.kind
+                    )));
+                }
             }
         }
+
+        Ok(definitions)
+    }
+
+    /// The definition of `kind` in `definitions`, or the built-in one if it's missing.
+    pub fn find(definitions: &[PieceDefinition], kind: PieceKind) -> &PieceDefinition {
+        definitions
+            .iter()
+            .chain(DEFAULT_PIECES.iter())
+            .find(|definition| definition.kind == kind)
+            .unwrap_or_else(|| panic!("Built-in pieces lack a definition for {:?}", kind))
+    }
+
+    /// The sprite of `kind`, taken from the built-in table if `definitions` has none for it.
+    pub fn sprite(definitions: &[PieceDefinition], kind: PieceKind) -> SpriteCell {
+        definitions
+            .iter()
+            .chain(DEFAULT_PIECES.iter())
+            .filter(|definition| definition.kind == kind)
+            .find_map(|definition| definition.sprite)
+            .unwrap_or_else(|| panic!("Built-in pieces lack a sprite for {:?}", kind))
+    }
+}
+
+impl Piece {
+    pub fn simple() -> Piece {
+        Self::new(0, PieceKind::Simple)
+    }
+
+    /// A piece with the built-in stats of its kind.
+    pub fn new(team_id: usize, kind: PieceKind) -> Piece {
+        Self::from_definition(team_id, PieceDefinition::find(&[], kind))
+    }
+
+    pub fn from_definition(team_id: usize, definition: &PieceDefinition) -> Piece {
+        Piece {
+            piece_kind: definition.kind,
+            attack: true,
+            pierce: definition.pierce,
+            shield: definition.shield,
+            movement: definition.movement.map(|range| Move { range }),
+            activatable: definition.power,
+            effect: definition.effect,
+            exhaustion: Exhaustion::new_exhausted(definition.exhaustion),
+            team_id,
+        }
     }
 
     pub fn can_move(&self) -> bool {
@@ -183,8 +302,9 @@ pub struct Piece {
     pub team_id: usize,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, SerJson, DeJson)]
 pub enum ExhaustionStrategy {
+    #[default]
     Either,
     Both,
     Move,
@@ -227,6 +347,11 @@ impl Exhaustion {
         !self.can_move() && !self.can_attack()
     }
 
+    /// The state that changes during a game, the strategy follows from the piece kind.
+    pub(crate) fn as_bits(&self) -> u64 {
+        self.moved as u64 | (self.used_special as u64) << 1
+    }
+
     pub fn on_move(&mut self) {
         self.moved = true;
     }
@@ -262,3 +387,117 @@ impl Display for Piece {
         write!(f, "{}", kind)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_KINDS: [PieceKind; 7] = [
+        PieceKind::Simple,
+        PieceKind::HorizontalBar,
+        PieceKind::VerticalBar,
+        PieceKind::Cross,
+        PieceKind::Queen,
+        PieceKind::Castle,
+        PieceKind::Sniper,
+    ];
+
+    #[test]
+    fn default_pieces_define_every_kind() {
+        let definitions = PieceDefinition::parse_all(DEFAULT_PIECES_JSON).unwrap();
+        assert_eq!(definitions.len(), ALL_KINDS.len());
+        for kind in ALL_KINDS {
+            assert_eq!(PieceDefinition::find(&definitions, kind).kind, kind);
+            assert!(PieceDefinition::find(&definitions, kind).sprite.is_some());
+        }
+
+        let queen = Piece::new(0, PieceKind::Queen);
+        assert_eq!(queen.movement, Some(Move::new_unlimited(Direction::Star)));
+        assert_eq!(queen.exhaustion.strategy, ExhaustionStrategy::Both);
+        assert!(queen.pierce && !queen.shield);
+        let simple = Piece::new(0, PieceKind::Simple);
+        assert_eq!(simple.movement, Some(Move::new(Direction::Star, 1)));
+        assert!(!simple.pierce);
+        let castle = Piece::new(0, PieceKind::Castle);
+        assert!(castle.shield && castle.movement.is_none());
+        assert!(castle.effect.unwrap().range.include_self);
+    }
+
+    #[test]
+    fn modified_stats_replace_only_their_kind() {
+        let definitions = PieceDefinition::parse_all(
+            r#"[{
+                "kind": "Queen",
+                "movement": {"direction": "Star", "context": "Moving", "steps": 255},
+                "power": {
+                    "kind": "Blast",
+                    "range": {"direction": "Star", "context": "Special", "steps": 2}
+                }
+            }]"#,
+        )
+        .unwrap();
+
+        let queen =
+            Piece::from_definition(1, PieceDefinition::find(&definitions, PieceKind::Queen));
+        assert_eq!(queen.team_id, 1);
+        assert_eq!(queen.activatable.unwrap().range.steps, 2);
+        assert_eq!(queen.exhaustion.strategy, ExhaustionStrategy::Either);
+        assert_eq!(
+            *PieceDefinition::find(&definitions, PieceKind::Sniper),
+            *PieceDefinition::find(&[], PieceKind::Sniper)
+        );
+        assert_eq!(
+            PieceDefinition::sprite(&definitions, PieceKind::Queen),
+            SpriteCell {
+                x: 1,
+                y: 1,
+                rotated: false
+            }
+        );
+    }
+
+    #[test]
+    fn tricksters_use_the_new_powers() {
+        let definitions = PieceDefinition::trickster_definitions();
+        let power = |kind| {
+            PieceDefinition::find(&definitions, kind)
+                .power
+                .unwrap()
+                .kind
+        };
+
+        assert_eq!(power(PieceKind::Cross), Power::Push);
+        assert_eq!(power(PieceKind::Castle), Power::Swap);
+        assert_eq!(power(PieceKind::Sniper), Power::Convert);
+        assert_eq!(
+            PieceDefinition::sprite(&definitions, PieceKind::Sniper),
+            PieceDefinition::sprite(&[], PieceKind::Sniper)
+        );
+    }
+
+    #[test]
+    fn swap_needs_an_area_range() {
+        let swap = |context| {
+            PieceDefinition::parse_all(&format!(
+                r#"[{{
+                    "kind": "Castle",
+                    "power": {{
+                        "kind": "Swap",
+                        "range": {{"direction": "Star", "context": "{}", "steps": 2}}
+                    }}
+                }}]"#,
+                context
+            ))
+        };
+
+        assert!(swap("Area").is_ok());
+        assert!(swap("Special").is_err());
+        assert!(swap("Moving").is_err());
+    }
+
+    #[test]
+    fn kinds_may_be_defined_once() {
+        assert!(PieceDefinition::parse_all(r#"[{"kind": "Cross"}, {"kind": "Cross"}]"#).is_err());
+        assert!(PieceDefinition::parse_all(r#"[{"kind": "Dragon"}]"#).is_err());
+    }
+}
@@ -11,7 +11,9 @@ pub struct Range {
     pub direction: Direction,
     pub context: RangeContext,
     pub steps: u8,
+    #[nserde(default)]
     pub jumps: bool,
+    #[nserde(default)]
     pub include_self: bool,
 }
 
@@ -70,17 +72,24 @@ pub enum RangeContext {
 
 impl RangeContext {
     pub fn should_proceed(&self, point: &Point2, board: &Board) -> bool {
+        if board.is_wall(point) {
+            return false;
+        }
+
         match self {
             RangeContext::Moving => board.get_piece_at(point).is_none(),
             RangeContext::Special => {
-                board.get_piece_at(point).is_none()
-                    && !board.has_effect_at(&EffectKind::Protection, point)
+                board.get_piece_at(point).is_none() && !board.is_protected(point)
             }
             RangeContext::Area => true,
         }
     }
 
     pub fn should_include(&self, piece: &Piece, point: &Point2, board: &Board) -> bool {
+        if board.is_wall(point) {
+            return false;
+        }
+
         match self {
             RangeContext::Moving => {
                 if let Some(target_piece) = board.get_piece_at(point) {
@@ -91,8 +100,7 @@ impl RangeContext {
             }
             RangeContext::Special => {
                 if let Some(target_piece) = board.get_piece_at(point) {
-                    return target_piece.team_id != piece.team_id
-                        && !board.has_effect_at(&EffectKind::Protection, point);
+                    return target_piece.team_id != piece.team_id && !board.is_protected(point);
                 }
 
                 false
@@ -180,7 +188,10 @@ impl Range {
                     if self.context.should_include(piece, &point, board) {
                         cells.insert(point);
                     }
-                    if !self.jumps && !self.context.should_proceed(&point, board) {
+                    // Not even jumping pieces can pass a wall
+                    if (!self.jumps || board.is_wall(&point))
+                        && !self.context.should_proceed(&point, board)
+                    {
                         break;
                     }
                 } else {
@@ -0,0 +1,102 @@
+use crate::{GameError, GameResult};
+use nanoserde::{DeJson, SerJson};
+
+/// The numbers the rules are built on. Values missing in a rule file keep their default,
+/// so a house rule only needs to name what it changes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, SerJson, DeJson)]
+pub struct RuleSet {
+    /// Unused pieces every team starts with
+    #[nserde(default = 6)]
+    pub start_pieces: u8,
+    /// Unused pieces a team gains when it ends its turn
+    #[nserde(default = 2)]
+    pub pieces_per_turn: u8,
+    /// A team with this many unused pieces wins
+    #[nserde(default = 20)]
+    pub winning_unused_pieces: u8,
+    /// The game is drawn once the same position occurs this often at the end of a turn
+    #[nserde(default = 3)]
+    pub repetitions: u8,
+    /// The game is drawn after this many turns in a row without a capture or a merge
+    #[nserde(default = 40)]
+    pub no_progress_turns: u32,
+    /// Whether actions can be undone, e.g. switched off for rated games
+    #[nserde(default_with = "undo_allowed_by_default")]
+    pub allow_undo: bool,
+}
+
+fn undo_allowed_by_default() -> bool {
+    true
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            start_pieces: 6,
+            pieces_per_turn: 2,
+            winning_unused_pieces: 20,
+            repetitions: 3,
+            no_progress_turns: 40,
+            allow_undo: true,
+        }
+    }
+}
+
+impl RuleSet {
+    /// Parses a JSON object with some or all of the fields, e.g. the content of a rule file.
+    pub fn parse(json: &str) -> GameResult<RuleSet> {
+        let rules: RuleSet = DeJson::deserialize_json(json)
+            .map_err(|e| GameError::InvalidDefinition(format!("Can't parse rules: {}", e)))?;
+
+        if rules.start_pieces == 0 {
+            return Err(GameError::InvalidDefinition(
+                "Teams need at least one start piece".to_string(),
+            ));
+        }
+        if rules.winning_unused_pieces <= rules.start_pieces {
+            return Err(GameError::InvalidDefinition(format!(
+                "Winning with {} unused pieces would end the game before it starts",
+                rules.winning_unused_pieces
+            )));
+        }
+        if rules.repetitions < 2 || rules.no_progress_turns == 0 {
+            return Err(GameError::InvalidDefinition(
+                "Draw rules would end the game before it starts".to_string(),
+            ));
+        }
+
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_keep_their_default() {
+        let rules = RuleSet::parse(r#"{"pieces_per_turn": 3}"#).unwrap();
+
+        assert_eq!(
+            rules,
+            RuleSet {
+                pieces_per_turn: 3,
+                ..RuleSet::default()
+            }
+        );
+        assert_eq!(RuleSet::parse("{}"), Ok(RuleSet::default()));
+        assert!(
+            !RuleSet::parse(r#"{"allow_undo": false}"#)
+                .unwrap()
+                .allow_undo
+        );
+    }
+
+    #[test]
+    fn rules_that_end_the_game_right_away_are_rejected() {
+        assert!(RuleSet::parse(r#"{"winning_unused_pieces": 6}"#).is_err());
+        assert!(RuleSet::parse(r#"{"start_pieces": 0}"#).is_err());
+        assert!(RuleSet::parse(r#"{"repetitions": 1}"#).is_err());
+        assert!(RuleSet::parse(r#"{"no_progress_turns": "many"}"#).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Zobrist keys for [`Game::hash`].
+//!
+//! Every feature of a position has a fixed pseudo random key: a piece (kind, team and
+//! exhaustion) on a cell, the number of effects of a kind on a cell, a team's unused pieces,
+//! whether a team lost and which team's turn it is. The hash of a position is the xor of the
+//! keys of all its features, so changing one feature only xors its old key out and its new
+//! key in, and applying the same change again restores the previous hash.
+//!
+//! The keys are derived from the features instead of drawn from a random table, so every
+//! peer and every build agrees on them.
+
+use crate::{
+    Point2,
+    game::Game,
+    piece::{CellEffect, Piece},
+};
+
+const PIECE: u64 = 1;
+const EFFECTS: u64 = 2;
+const UNUSED_PIECES: u64 = 3;
+const LOST: u64 = 4;
+const CURRENT_TEAM: u64 = 5;
+
+/// The splitmix64 finalizer, spreads every input bit over the whole output.
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn key(feature: u64, values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(mix(feature), |key, value| mix(key ^ value))
+}
+
+pub fn piece_key(at: &Point2, piece: &Piece) -> u64 {
+    key(
+        PIECE,
+        &[
+            at.x as u64,
+            at.y as u64,
+            piece.piece_kind as u64,
+            piece.team_id as u64,
+            piece.exhaustion.as_bits(),
+        ],
+    )
+}
+
+/// Key for `count` equal effects on one cell, effects can stack. Effects of the same kind
+/// with different turns left are different features.
+pub fn effects_key(at: &Point2, effect: &CellEffect, count: usize) -> u64 {
+    if count == 0 {
+        return 0;
+    }
+
+    let values = [at.x as u64, at.y as u64, effect.kind as u64, count as u64];
+    match effect.turns {
+        None => key(EFFECTS, &values),
+        Some(turns) => key(EFFECTS, &[&values[..], &[turns as u64]].concat()),
+    }
+}
+
+pub fn unused_pieces_key(team_id: usize, count: u8) -> u64 {
+    if count == 0 {
+        return 0;
+    }
+
+    key(UNUSED_PIECES, &[team_id as u64, count as u64])
+}
+
+pub fn lost_key(team_id: usize, lost: bool) -> u64 {
+    if !lost {
+        return 0;
+    }
+
+    key(LOST, &[team_id as u64])
+}
+
+pub fn current_team_key(team_id: usize) -> u64 {
+    key(CURRENT_TEAM, &[team_id as u64])
+}
+
+/// Computes the hash of a game from scratch. The board's layout and the patterns
+/// are the same for the whole game and therefore not part of the hash.
+pub fn hash(game: &Game) -> u64 {
+    let mut hash = current_team_key(game.current_team_index);
+
+    for team in &game.teams {
+        hash ^= unused_pieces_key(team.id, team.unused_pieces) ^ lost_key(team.id, team.lost);
+    }
+
+    game.board.for_each_cell(|cell| {
+        if let Some(piece) = &cell.piece {
+            hash ^= piece_key(&cell.point, piece);
+        }
+
+        for (i, effect) in cell.effects.iter().enumerate() {
+            if cell.effects[..i].contains(effect) {
+                continue;
+            }
+            let count = cell.effects.iter().filter(|e| *e == effect).count();
+            hash ^= effects_key(&cell.point, effect, count);
+        }
+    });
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::Team,
+        piece::{EffectKind, PieceKind},
+    };
+
+    fn setup_game() -> Game {
+        let teams = (0..2)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 0,
+            })
+            .collect();
+
+        Game::new(teams, 8, 8)
+    }
+
+    #[test]
+    fn same_position_has_same_hash() {
+        let mut first = setup_game();
+        let mut second = setup_game();
+        let (a, b) = (Point2::new(1, 1), Point2::new(5, 2));
+
+        first.board.place_piece_at(Piece::simple(), &a).unwrap();
+        first
+            .board
+            .place_piece_at(Piece::new(1, PieceKind::Sniper), &b)
+            .unwrap();
+        second
+            .board
+            .place_piece_at(Piece::new(1, PieceKind::Sniper), &b)
+            .unwrap();
+        second.board.place_piece_at(Piece::simple(), &a).unwrap();
+
+        assert_eq!(hash(&first), hash(&second));
+        assert_ne!(hash(&first), hash(&setup_game()));
+    }
+
+    #[test]
+    fn exhaustion_and_stacked_effects_change_the_hash() {
+        let mut game = setup_game();
+        let at = Point2::new(3, 3);
+        game.board.place_piece_at(Piece::simple(), &at).unwrap();
+        let exhausted = hash(&game);
+
+        game.board.get_piece_mut_at(&at).unwrap().exhaustion.reset();
+        assert_ne!(hash(&game), exhausted);
+
+        let protection = CellEffect::lasting(EffectKind::Protection);
+        game.board.add_effect(protection, &at).unwrap();
+        let one_effect = hash(&game);
+        game.board.add_effect(protection, &at).unwrap();
+        assert_ne!(hash(&game), one_effect);
+
+        game.board.remove_effect(&protection, &at).unwrap();
+        assert_eq!(hash(&game), one_effect);
+
+        let timed = CellEffect::timed(EffectKind::Protection, 2);
+        game.board.add_effect(timed, &at).unwrap();
+        let with_timed = hash(&game);
+        game.board.remove_effect(&timed, &at).unwrap();
+        game.board
+            .add_effect(CellEffect::timed(EffectKind::Protection, 1), &at)
+            .unwrap();
+        assert_ne!(hash(&game), with_timed);
+    }
+}
@@ -1,7 +1,6 @@
 use crate::{
     constants::{
-        ADD_UNUSED_SPEED, ANIMATION_SPEED, BULLET_SPEED, CELL_WIDTH, MOVE_PIECE_SPEED, PIECE_SCALE,
-        PLACE_PIECE_SPEED,
+        ADD_UNUSED_SPEED, ANIMATION_SPEED, BULLET_SPEED, MOVE_PIECE_SPEED, PLACE_PIECE_SPEED,
     },
     rendering::{BoardRender, EffectRender},
     sprite::*,
@@ -103,10 +102,7 @@ impl Animation {
             duration: Duration::from_millis(0),
             finished_at: Instant::now(),
             next_animations: vec![],
-            expert: Box::new(AddEffectAnimation {
-                _effect: effect,
-                at,
-            }),
+            expert: Box::new(AddEffectAnimation { effect, at }),
         }
     }
 
@@ -115,10 +111,7 @@ impl Animation {
             duration: Duration::from_millis(0),
             finished_at: Instant::now(),
             next_animations: vec![],
-            expert: Box::new(RemoveEffectAnimation {
-                _effect: effect,
-                at,
-            }),
+            expert: Box::new(RemoveEffectAnimation { effect, at }),
         }
     }
 
@@ -140,6 +133,16 @@ impl Animation {
         }
     }
 
+    /// Takes back an unused piece a finished turn handed out, e.g. when the turn is undone.
+    pub fn new_remove_unused(team_id: usize) -> Self {
+        Animation {
+            duration: Duration::from_millis(0),
+            finished_at: Instant::now(),
+            next_animations: vec![],
+            expert: Box::new(RemoveUnusedAnimation { team_id }),
+        }
+    }
+
     pub fn new_die(at: Point2) -> Self {
         let id = rand();
         Animation {
@@ -181,6 +184,15 @@ impl Animation {
         }
     }
 
+    pub fn new_swap(a: Point2, b: Point2) -> Self {
+        Animation {
+            duration: Duration::from_millis(MOVE_PIECE_SPEED),
+            finished_at: Instant::now(),
+            next_animations: vec![],
+            expert: Box::new(SwapPiecesAnimation { a, b }),
+        }
+    }
+
     pub fn new_move_towards(from: Point2, to: Point2) -> Self {
         Animation {
             duration: Duration::from_millis(MOVE_PIECE_SPEED),
@@ -237,13 +249,13 @@ pub trait AnimationExpert: Debug {
 
 #[derive(Debug, Clone)]
 pub struct AddEffectAnimation {
-    pub(crate) _effect: EffectKind,
+    pub(crate) effect: EffectKind,
     pub at: Point2,
 }
 
 #[derive(Debug, Clone)]
 pub struct RemoveEffectAnimation {
-    pub(crate) _effect: EffectKind,
+    pub(crate) effect: EffectKind,
     pub at: Point2,
 }
 
@@ -273,6 +285,12 @@ pub struct MovePieceAnimation {
     pub(crate) to: Point2,
 }
 
+#[derive(Debug, Clone)]
+pub struct SwapPiecesAnimation {
+    pub(crate) a: Point2,
+    pub(crate) b: Point2,
+}
+
 #[derive(Debug, Clone)]
 pub struct SwooshPieceAnimation {
     pub(crate) from: Point2,
@@ -314,6 +332,11 @@ pub struct AddUnusedAnimation {
     pub team_id: usize,
 }
 
+#[derive(Debug, Clone)]
+pub struct RemoveUnusedAnimation {
+    pub team_id: usize,
+}
+
 impl AnimationExpert for AddUnusedAnimation {
     fn start(&self, board_render: &mut BoardRender) {
         board_render.add_unused_piece(self.team_id);
@@ -329,6 +352,14 @@ impl AnimationExpert for AddUnusedAnimation {
     }
 }
 
+impl AnimationExpert for RemoveUnusedAnimation {
+    fn start(&self, board_render: &mut BoardRender) {
+        board_render.unused_pieces[self.team_id]
+            .pop()
+            .expect("No unused piece left in BoardRender");
+    }
+}
+
 impl AnimationExpert for NewPieceAnimation {
     fn start(&self, board_render: &mut BoardRender) {
         board_render.add_placed_piece(&self.to, self.piece_kind, self.team, self.exhausted)
@@ -367,7 +398,8 @@ impl AnimationExpert for PlacePieceAnimation {
         let mut unused = board_render.unused_pieces[self.team]
             .pop()
             .expect("No unused piece left in BoardRender");
-        unused.move_towards(&self.to, PLACE_PIECE_SPEED, &board_render.layout);
+        let layout = &board_render.layout;
+        unused.move_and_scale_towards(&self.to, layout.piece_scale, PLACE_PIECE_SPEED, layout);
         unused.override_color = Some(SpriteRender::greyed_out(&unused.color));
 
         //let color = board_render.team_colors[piece.team_id];
@@ -388,6 +420,25 @@ impl AnimationExpert for MovePieceAnimation {
         board_render.placed_pieces.insert(self.to, piece_render);
     }
 }
+impl AnimationExpert for SwapPiecesAnimation {
+    fn start(&self, board_render: &mut BoardRender) {
+        let mut remove = |at: &Point2| {
+            board_render
+                .placed_pieces
+                .remove(at)
+                .unwrap_or_else(|| panic!("No piece found at {:?}", at))
+        };
+        let (mut piece_a, mut piece_b) = (remove(&self.a), remove(&self.b));
+
+        let layout = &board_render.layout;
+        piece_a.move_towards(&self.b, MOVE_PIECE_SPEED, layout);
+        piece_b.move_towards(&self.a, MOVE_PIECE_SPEED, layout);
+
+        board_render.placed_pieces.insert(self.b, piece_a);
+        board_render.placed_pieces.insert(self.a, piece_b);
+    }
+}
+
 impl AnimationExpert for SwooshPieceAnimation {
     fn start(&self, board_render: &mut BoardRender) {
         let piece_render = board_render
@@ -409,22 +460,23 @@ impl AnimationExpert for AddEffectAnimation {
             .effects
             .get_mut(&self.at)
             .unwrap()
-            .push(EffectRender::new());
+            .push(EffectRender::new(self.effect));
     }
 }
 
 impl AnimationExpert for RemoveEffectAnimation {
     fn start(&self, board_render: &mut BoardRender) {
-        board_render
-            .effects
-            .get_mut(&self.at)
-            .unwrap_or_else(|| {
-                panic!(
-                    "Can't remove effect at {:?} because that position doesn't exist",
-                    self.at
-                )
-            })
-            .remove(0);
+        let effects = board_render.effects.get_mut(&self.at).unwrap_or_else(|| {
+            panic!(
+                "Can't remove effect at {:?} because that position doesn't exist",
+                self.at
+            )
+        });
+        let index = effects
+            .iter()
+            .position(|e| e.kind == self.effect)
+            .unwrap_or_else(|| panic!("No {:?} effect at {:?}", self.effect, self.at));
+        effects.remove(index);
     }
 }
 
@@ -448,7 +500,7 @@ impl AnimationExpert for BulletAnimation {
         let layout = &board_render.layout;
         let mut sprite_render = SpriteRender::new_at_point(
             &self.from,
-            PIECE_SCALE,
+            layout.piece_scale,
             Colour::WHITE,
             SpriteKind::Special,
             Rect::new(0., 0., SPRITE_WIDTH, SPRITE_WIDTH),
@@ -471,7 +523,7 @@ impl AnimationExpert for BlastAnimation {
             layout,
         );
 
-        sprite_render.scale(self.span_cells * CELL_WIDTH, BULLET_SPEED);
+        sprite_render.scale(self.span_cells * layout.cell_width, BULLET_SPEED);
 
         board_render.special_sprites.insert(self.id, sprite_render);
     }
@@ -19,10 +19,12 @@ const PORTRAIT_MIN_DIM: f32 = if PORTRAIT_CANVAS_W < PORTRAIT_CANVAS_H {
     PORTRAIT_CANVAS_H
 };
 
-/// Board cell size derived from canvas dimensions (smaller dimension ÷ 8).
+/// Base UI unit derived from canvas dimensions (smaller dimension ÷ 8).
+/// This is the cell size of an 8x8 board; the actual board cell size is
+/// [`crate::layout::LayoutConstants::cell_width`].
 pub const CELL_WIDTH: f32 = PORTRAIT_MIN_DIM / 8.0;
 
-/// Piece sprite diameter.
+/// Piece sprite diameter of spare pieces (and of placed pieces on an 8x8 board).
 pub const PIECE_SCALE: f32 = CELL_WIDTH * 1.2;
 
 /// Font size for UI text
@@ -44,8 +46,12 @@ pub const ADD_UNUSED_SPEED: u64 = ANIMATION_SPEED / 3;
 
 // ── Board geometry ─────────────────────────────────────────────────────────
 
-pub const BOARD_WIDTH: u8 = 8;
-pub const BOARD_HEIGHT: u8 = 8;
+/// Board size used when none is chosen, e.g. for online games.
+pub const DEFAULT_BOARD_SIZE: u8 = 8;
+/// Board sizes offered in the loading menu.
+pub const BOARD_SIZES: [u8; 3] = [6, 8, 10];
+/// Side length of the square area the board is fitted into, whatever its size.
+pub const BOARD_EXTENT: f32 = CELL_WIDTH * DEFAULT_BOARD_SIZE as f32;
 
 // ── Pattern infographic ──────────────────────────────────────────────────
 
@@ -62,3 +68,8 @@ pub const PATTERN_ROW_GAP: f32 = 0.80 * CELL_WIDTH;
 pub const PATTERN_COL_GAP: f32 = 1.30 * CELL_WIDTH;
 /// Size of the resulting piece sprite in the infographic.
 pub const PATTERN_PIECE_SIZE: f32 = 0.55 * CELL_WIDTH;
+
+// ── Move history ─────────────────────────────────────────────────────────
+
+/// Font size of the move history below the description text.
+pub const HISTORY_FONT_SIZE: f32 = 0.7 * FONT_SIZE;
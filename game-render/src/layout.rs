@@ -1,4 +1,4 @@
-use crate::constants::{CELL_WIDTH, PIECE_SCALE, ROW_HEIGHT};
+use crate::constants::{BOARD_EXTENT, CELL_WIDTH, PIECE_SCALE, ROW_HEIGHT};
 
 // ── Orientation-specific layout constants ─────────────────────────────────
 
@@ -8,9 +8,11 @@ const PORTRAIT_GAP_FACTOR: f32 = 0.4;
 const PORTRAIT_BTN_HEIGHT: f32 = 0.5 * CELL_WIDTH;
 /// Width of the "End Turn" button in portrait mode
 const PORTRAIT_BTN0_WIDTH: f32 = 2.6 * CELL_WIDTH;
-/// Width of the "Undo" button in portrait mode
-const PORTRAIT_BTN1_WIDTH: f32 = 2.2 * CELL_WIDTH;
-/// Gap between the two side-by-side buttons in portrait mode
+/// Width of the "Undo" and "Redo" buttons in portrait mode
+const PORTRAIT_BTN1_WIDTH: f32 = 1.3 * CELL_WIDTH;
+/// Width of the "Patterns" button in portrait mode
+const PORTRAIT_BTN2_WIDTH: f32 = 2.0 * CELL_WIDTH;
+/// Gap between the side-by-side buttons in portrait mode
 const PORTRAIT_BTN_GAP: f32 = 0.2 * CELL_WIDTH;
 /// Number of spare-piece columns in portrait (pieces spread across entire width)
 const PORTRAIT_SPARE_COLS: u32 = 20;
@@ -41,9 +43,18 @@ use macroquad_canvas::Canvas2D;
 ///
 /// Layout-independent constants (`CELL_WIDTH`, `PIECE_SCALE`, `FONT_SIZE`,
 /// `ROW_HEIGHT`) live in [`crate::constants`] and are shared by both
-/// portrait and landscape.
+/// portrait and landscape. Sizes that depend on the number of board cells
+/// live here.
 #[derive(Debug, Clone, Copy)]
 pub struct LayoutConstants {
+    /// Size of one board cell
+    pub cell_width: f32,
+    /// Diameter of a piece sprite on the board
+    pub piece_scale: f32,
+    /// Board width (pixels)
+    pub board_width: f32,
+    /// Board height (pixels)
+    pub board_height: f32,
     /// Board x offset (0 portrait, left_col landscape)
     pub shift_x: f32,
     /// Board y offset (always 0)
@@ -68,6 +79,8 @@ pub struct LayoutConstants {
     pub button_end_turn: Rect,
     /// "Undo" button rectangle
     pub button_undo: Rect,
+    /// "Redo" button rectangle, right of "Undo"
+    pub button_redo: Rect,
     /// "Patterns" button rectangle
     pub button_patterns: Rect,
 }
@@ -75,8 +88,8 @@ pub struct LayoutConstants {
 impl LayoutConstants {
     /// World-space (x, y) of the top-left corner of a board cell.
     pub fn cell_coords(&self, x: u8, y: u8) -> (f32, f32) {
-        let x_pos = x as f32 * CELL_WIDTH + self.shift_x;
-        let y_pos = y as f32 * CELL_WIDTH + self.shift_y;
+        let x_pos = x as f32 * self.cell_width + self.shift_x;
+        let y_pos = y as f32 * self.cell_width + self.shift_y;
         (x_pos, y_pos)
     }
 
@@ -87,8 +100,8 @@ impl LayoutConstants {
 
     /// Board-cell from world-space position (for mouse hit-testing).
     pub fn coords_to_cell(&self, x_pos: f32, y_pos: f32) -> Point2 {
-        let x = ((x_pos - self.shift_x) / CELL_WIDTH) as u8;
-        let y = ((y_pos - self.shift_y) / CELL_WIDTH) as u8;
+        let x = ((x_pos - self.shift_x) / self.cell_width) as u8;
+        let y = ((y_pos - self.shift_y) / self.cell_width) as u8;
         (x, y).into()
     }
 
@@ -101,17 +114,43 @@ impl LayoutConstants {
     /// Center a sprite inside a board cell.
     pub fn sprite_render_pos(&self, sprite_width: f32, point: &Point2) -> (f32, f32) {
         let (cx, cy) = self.cell_coords(point.x, point.y);
-        let shift = (CELL_WIDTH - sprite_width) / 2.0;
+        let shift = (self.cell_width - sprite_width) / 2.0;
         (cx + shift, cy + shift)
     }
 
+    /// World-space (x, y) of the `index`-th spare slot of a team.
+    /// With more than two teams, teams 2 and 3 share the spare rows of
+    /// teams 0 and 1, each team getting half of the columns.
+    pub fn spare_slot(&self, team_id: usize, num_teams: usize, index: usize) -> (f32, f32) {
+        let (start_x, start_y) = if team_id.is_multiple_of(2) {
+            self.spare_start_team0
+        } else {
+            self.spare_start_team1
+        };
+
+        let (cols, first_col) = if num_teams > 2 {
+            let half = (self.spare_cols / 2).max(1);
+            (half, (team_id / 2) as u32 * half)
+        } else {
+            (self.spare_cols, 0)
+        };
+
+        let index = index as u32;
+        let col = first_col + index % cols;
+        let row = index / cols;
+        (
+            start_x + col as f32 * self.spare_step.0,
+            start_y + row as f32 * self.spare_step.1,
+        )
+    }
+
     /// Return debug overlay rectangles: `(x, y, w, h, is_blue)`.
     /// `is_blue = true` means the blue color (canvas boundary),
     /// `is_blue = false` means the green color (structural regions).
     pub fn debug_regions(&self) -> Vec<(f32, f32, f32, f32, bool)> {
         let mut regions = Vec::new();
-        let bw = CELL_WIDTH * 8.0;
-        let bh = CELL_WIDTH * 8.0;
+        let bw = self.board_width;
+        let bh = self.board_height;
 
         // ── Canvas outline (blue) ──
         regions.push((0.0, 0.0, self.canvas_w, self.canvas_h, true));
@@ -178,30 +217,53 @@ impl LayoutConstants {
     }
 }
 
-/// Derive full layout from canvas logical size.
+/// Derive full layout from canvas logical size and the number of board cells.
+/// The board is scaled so its longer side fills [`BOARD_EXTENT`].
 /// Pure: same inputs always return the same output.
-pub fn compute_layout(canvas_width: f32, canvas_height: f32) -> LayoutConstants {
+pub fn compute_layout(
+    canvas_width: f32,
+    canvas_height: f32,
+    board_w: u8,
+    board_h: u8,
+) -> LayoutConstants {
+    let cell_width = BOARD_EXTENT / board_w.max(board_h).max(1) as f32;
+    let board = BoardMetrics {
+        cell_width,
+        piece_scale: cell_width / CELL_WIDTH * PIECE_SCALE,
+        width: cell_width * board_w as f32,
+        height: cell_width * board_h as f32,
+    };
+
     if canvas_height > canvas_width {
-        compute_portrait_layout(canvas_width, canvas_height)
+        compute_portrait_layout(canvas_width, canvas_height, board)
     } else {
-        compute_landscape_layout(canvas_width, canvas_height)
+        compute_landscape_layout(canvas_width, canvas_height, board)
     }
 }
 
+/// Board sizes shared by both orientations.
+#[derive(Debug, Clone, Copy)]
+struct BoardMetrics {
+    cell_width: f32,
+    piece_scale: f32,
+    width: f32,
+    height: f32,
+}
+
 // ── Portrait ───────────────────────────────────────────────────────────────
 
-fn compute_portrait_layout(canvas_w: f32, canvas_h: f32) -> LayoutConstants {
+fn compute_portrait_layout(canvas_w: f32, canvas_h: f32, board: BoardMetrics) -> LayoutConstants {
     let gap = CELL_WIDTH * PORTRAIT_GAP_FACTOR;
 
     let board_top = ROW_HEIGHT + gap;
-    let board_bot = board_top + CELL_WIDTH * 8.0;
+    let board_bot = board_top + board.height;
     let spare1_top = board_bot + gap;
     let spare1_bot = spare1_top + ROW_HEIGHT;
     let btn_top = spare1_bot + gap;
     let text_y = btn_top + PORTRAIT_BTN_HEIGHT + gap * 2.0;
 
     // Two buttons centered side-by-side
-    let w = CELL_WIDTH * 8.0; // total board width
+    let w = BOARD_EXTENT; // total board area width
     let btn_start = 0.0;
 
     // Spare step: PORTRAIT_SPARE_COLS pieces across full width
@@ -209,6 +271,10 @@ fn compute_portrait_layout(canvas_w: f32, canvas_h: f32) -> LayoutConstants {
     let spare_off = (CELL_WIDTH - PIECE_SCALE) / 2.0;
 
     LayoutConstants {
+        cell_width: board.cell_width,
+        piece_scale: board.piece_scale,
+        board_width: board.width,
+        board_height: board.height,
         shift_x: 0.0,
         shift_y: board_top,
         text_x: 10.0,
@@ -224,16 +290,24 @@ fn compute_portrait_layout(canvas_w: f32, canvas_h: f32) -> LayoutConstants {
             PORTRAIT_BTN1_WIDTH,
             PORTRAIT_BTN_HEIGHT,
         ),
-        button_patterns: Rect::new(
+        button_redo: Rect::new(
             btn_start
                 + PORTRAIT_BTN0_WIDTH
-                + PORTRAIT_BTN_GAP
-                + PORTRAIT_BTN1_WIDTH
+                + (PORTRAIT_BTN_GAP + PORTRAIT_BTN1_WIDTH)
                 + PORTRAIT_BTN_GAP,
             btn_top,
             PORTRAIT_BTN1_WIDTH,
             PORTRAIT_BTN_HEIGHT,
         ),
+        button_patterns: Rect::new(
+            btn_start
+                + PORTRAIT_BTN0_WIDTH
+                + (PORTRAIT_BTN_GAP + PORTRAIT_BTN1_WIDTH) * 2.0
+                + PORTRAIT_BTN_GAP,
+            btn_top,
+            PORTRAIT_BTN2_WIDTH,
+            PORTRAIT_BTN_HEIGHT,
+        ),
         canvas_w,
         canvas_h,
     }
@@ -241,13 +315,13 @@ fn compute_portrait_layout(canvas_w: f32, canvas_h: f32) -> LayoutConstants {
 
 // ── Landscape ──────────────────────────────────────────────────────────────
 
-fn compute_landscape_layout(w: f32, _h: f32) -> LayoutConstants {
-    let left_col = w - CELL_WIDTH * 8.0;
+fn compute_landscape_layout(w: f32, _h: f32, board: BoardMetrics) -> LayoutConstants {
+    let left_col = w - board.width;
     let step_x = left_col / LANDSCAPE_SPARE_COLS as f32;
 
     // Buttons side-by-side in one row in the left column
     let btn_h = LANDSCAPE_BTN_HEIGHT;
-    let btn_w = (left_col - LANDSCAPE_BTN_PAD * 2.0 - LANDSCAPE_BTN_GAP * 2.0) / 3.0;
+    let btn_w = (left_col - LANDSCAPE_BTN_PAD * 2.0 - LANDSCAPE_BTN_GAP * 3.0) / 4.0;
     let btn_end_turn = Rect::new(LANDSCAPE_BTN_PAD, LANDSCAPE_BTN_TOP, btn_w, btn_h);
     let btn_undo = Rect::new(
         LANDSCAPE_BTN_PAD + btn_w + LANDSCAPE_BTN_GAP,
@@ -255,12 +329,18 @@ fn compute_landscape_layout(w: f32, _h: f32) -> LayoutConstants {
         btn_w,
         btn_h,
     );
-    let btn_patterns = Rect::new(
+    let btn_redo = Rect::new(
         LANDSCAPE_BTN_PAD + (btn_w + LANDSCAPE_BTN_GAP) * 2.0,
         LANDSCAPE_BTN_TOP,
         btn_w,
         btn_h,
     );
+    let btn_patterns = Rect::new(
+        LANDSCAPE_BTN_PAD + (btn_w + LANDSCAPE_BTN_GAP) * 3.0,
+        LANDSCAPE_BTN_TOP,
+        btn_w,
+        btn_h,
+    );
     let buttons_bot = LANDSCAPE_BTN_TOP + btn_h;
 
     let spare0_top = buttons_bot + LANDSCAPE_SPARE0_GAP;
@@ -271,6 +351,10 @@ fn compute_landscape_layout(w: f32, _h: f32) -> LayoutConstants {
     let spare_off = (CELL_WIDTH - PIECE_SCALE) / 2.0;
 
     LayoutConstants {
+        cell_width: board.cell_width,
+        piece_scale: board.piece_scale,
+        board_width: board.width,
+        board_height: board.height,
         shift_x: left_col,
         shift_y: 0.0,
         text_x: LANDSCAPE_BTN_PAD,
@@ -281,6 +365,7 @@ fn compute_landscape_layout(w: f32, _h: f32) -> LayoutConstants {
         spare_cols: LANDSCAPE_SPARE_COLS,
         button_end_turn: btn_end_turn,
         button_undo: btn_undo,
+        button_redo: btn_redo,
         button_patterns: btn_patterns,
         canvas_w: w,
         canvas_h: _h,
@@ -298,7 +383,7 @@ mod tests {
 
     #[test]
     fn portrait_layout() {
-        let l = compute_layout(PORTRAIT_CANVAS_W, PORTRAIT_CANVAS_H);
+        let l = compute_layout(PORTRAIT_CANVAS_W, PORTRAIT_CANVAS_H, 8, 8);
         assert_eq!(l.shift_x, 0.0, "portrait: shift_x should be 0");
         // spare0 starts offset by half the piece overhang
         let off = (CELL_WIDTH - PIECE_SCALE) / 2.0;
@@ -311,11 +396,29 @@ mod tests {
         assert!(l.button_end_turn.y > l.spare_start_team1.1 + ROW_HEIGHT * 0.5);
         // text below buttons
         assert!(l.text_y > l.button_end_turn.y + PORTRAIT_BTN_HEIGHT * 0.5);
+        // the buttons fit side by side into the board width
+        assert!(l.button_redo.x > l.button_undo.right());
+        assert!(l.button_patterns.x > l.button_redo.right());
+        assert!(l.button_patterns.right() <= BOARD_EXTENT);
+    }
+
+    #[test]
+    fn four_teams_share_spare_rows() {
+        let l = compute_layout(PORTRAIT_CANVAS_W, PORTRAIT_CANVAS_H, 8, 8);
+        assert_eq!(l.spare_slot(0, 2, 0), l.spare_start_team0);
+        assert_eq!(l.spare_slot(0, 4, 0), l.spare_start_team0);
+
+        let (x2, y2) = l.spare_slot(2, 4, 0);
+        assert_eq!(y2, l.spare_start_team0.1);
+        assert!(x2 > l.spare_slot(0, 4, (l.spare_cols / 2 - 1) as usize).0);
+
+        let (_, y3) = l.spare_slot(3, 4, 0);
+        assert_eq!(y3, l.spare_start_team1.1);
     }
 
     #[test]
     fn landscape_layout() {
-        let l = compute_layout(LANDSCAPE_CANVAS_W, LANDSCAPE_CANVAS_H);
+        let l = compute_layout(LANDSCAPE_CANVAS_W, LANDSCAPE_CANVAS_H, 8, 8);
         assert!(l.shift_x > 0.0, "landscape: shift_x should be > 0");
         // left_col = w - board_width = LANDSCAPE_CANVAS_W - CELL_WIDTH * 8
         let expected_shift_x = LANDSCAPE_CANVAS_W - CELL_WIDTH * 8.0;
@@ -324,7 +427,7 @@ mod tests {
             "shift_x should be {}",
             expected_shift_x
         );
-        // spare starts below the single-row buttons (End Turn, Undo, Patterns side-by-side), offset by half the piece overhang
+        // spare starts below the single-row buttons (End Turn, Undo, Redo, Patterns side-by-side), offset by half the piece overhang
         let btn_bot = LANDSCAPE_BTN_TOP + LANDSCAPE_BTN_HEIGHT;
         let expected_spare0_top = btn_bot + LANDSCAPE_SPARE0_GAP;
         let off = (CELL_WIDTH - PIECE_SCALE) / 2.0;
@@ -333,9 +436,23 @@ mod tests {
 
     #[test]
     fn cell_roundtrip() {
-        let l = compute_layout(PORTRAIT_CANVAS_W, PORTRAIT_CANVAS_H);
+        let l = compute_layout(PORTRAIT_CANVAS_W, PORTRAIT_CANVAS_H, 8, 8);
         let (x, y) = l.cell_coords(3, 5);
         let p = l.coords_to_cell(x + 1.0, y + 1.0);
         assert_eq!(p, Point2::new(3, 5));
     }
+
+    #[test]
+    fn board_size_scales_cells() {
+        let small = compute_layout(PORTRAIT_CANVAS_W, PORTRAIT_CANVAS_H, 6, 6);
+        let large = compute_layout(PORTRAIT_CANVAS_W, PORTRAIT_CANVAS_H, 10, 10);
+        assert!((small.board_width - BOARD_EXTENT).abs() < 0.1);
+        assert!((large.board_width - BOARD_EXTENT).abs() < 0.1);
+        assert!(small.cell_width > CELL_WIDTH && large.cell_width < CELL_WIDTH);
+        assert!(large.piece_scale < PIECE_SCALE);
+
+        let (x, y) = large.cell_coords(9, 9);
+        let p = large.coords_to_cell(x + 1.0, y + 1.0);
+        assert_eq!(p, Point2::new(9, 9));
+    }
 }
@@ -3,8 +3,8 @@ use game_events::{
     atomic_events::AtomicEvent,
 };
 use game_model::{
-    Point2,
-    piece::{EffectKind, PieceKind},
+    GameResult, Point2,
+    piece::{PieceKind, Power},
 };
 
 use crate::{
@@ -12,7 +12,11 @@ use crate::{
     animation::{Animation, PlacePieceAnimation},
 };
 use game_events::{
-    actions::{attack::AttackCompoundEvent, moving::MoveCompoundEvent, place::PlaceCompoundEvent},
+    actions::{
+        attack::{AttackCompoundEvent, Displacement},
+        moving::MoveCompoundEvent,
+        place::PlaceCompoundEvent,
+    },
     event_broker::EventConsumer,
 };
 use std::{cell::RefCell, rc::Rc};
@@ -50,10 +54,19 @@ impl RenderEventConsumer {
                         piece.exhaustion.is_done(),
                     ));
                 }
-                AtomicEvent::NextTurn => {}
+                AtomicEvent::NextTurn(_) | AtomicEvent::EliminateTeam(_) => {}
                 AtomicEvent::ChangeExhaustion(_, to, at) => {
                     animations.push(Animation::new_exhaustion(*to, *at));
                 }
+                AtomicEvent::Remove(point, _piece) => {
+                    animations.push(Animation::new_die(*point));
+                }
+                AtomicEvent::AddEffect(effect, at) => {
+                    animations.push(Animation::new_add_effect(effect.kind, *at));
+                }
+                AtomicEvent::RemoveEffect(effect, at) => {
+                    animations.push(Animation::new_remove_effect(effect.kind, *at));
+                }
                 e => panic!(
                     "Unexpected subevent of CompoundEventType::FinishTurn: {:?}",
                     e
@@ -70,6 +83,9 @@ impl RenderEventConsumer {
                 AtomicEvent::AddUnusedPiece(team_id) => {
                     animations.push(Animation::new_add_unused(*team_id));
                 }
+                AtomicEvent::RemoveUnusedPiece(team_id) => {
+                    animations.push(Animation::new_remove_unused(*team_id));
+                }
                 AtomicEvent::Place(point, piece) => {
                     animations.push(Animation::new_piece(
                         piece.team_id,
@@ -84,13 +100,17 @@ impl RenderEventConsumer {
                 AtomicEvent::ChangeExhaustion(_, to, point) => {
                     animations.push(Animation::new_exhaustion(*to, *point));
                 }
-                AtomicEvent::AddEffect(kind, pos) => {
-                    animations.push(Animation::new_add_effect(*kind, *pos))
+                AtomicEvent::AddEffect(effect, pos) => {
+                    animations.push(Animation::new_add_effect(effect.kind, *pos))
                 }
-                AtomicEvent::RemoveEffect(kind, pos) => {
-                    animations.push(Animation::new_remove_effect(*kind, *pos))
+                AtomicEvent::RemoveEffect(effect, pos) => {
+                    animations.push(Animation::new_remove_effect(effect.kind, *pos))
                 }
-                e => panic!("Unexpected subevent of CompoundEventType::Undo: {:?}", e),
+                // Undoing a finished turn hands the turn back
+                AtomicEvent::EliminateTeam(_)
+                | AtomicEvent::ReviveTeam(_)
+                | AtomicEvent::NextTurn(_)
+                | AtomicEvent::PreviousTurn(_) => {}
             };
         }
         animations
@@ -102,10 +122,10 @@ impl RenderEventConsumer {
         if move_event.captured_piece().is_some() {
             let mut remove_animation = Animation::new_die(*move_event.to());
 
-            for pos in move_event.removed_effects() {
+            for (pos, effect) in move_event.removed_effects() {
                 remove_animation
                     .next_animations
-                    .push(Animation::new_remove_effect(EffectKind::Protection, *pos));
+                    .push(Animation::new_remove_effect(effect.kind, *pos));
             }
 
             animations.push(remove_animation);
@@ -113,10 +133,10 @@ impl RenderEventConsumer {
 
         let mut move_animation = Animation::new_move(*move_event.from(), *move_event.to());
 
-        for pos in move_event.added_effects() {
+        for (pos, effect) in move_event.added_effects() {
             move_animation
                 .next_animations
-                .push(Animation::new_add_effect(EffectKind::Protection, *pos));
+                .push(Animation::new_add_effect(effect.kind, *pos));
         }
 
         move_animation
@@ -149,20 +169,37 @@ impl RenderEventConsumer {
     }
 
     fn handle_attack(attack_event: &AttackCompoundEvent) -> Vec<Animation> {
+        let pos = *attack_event.attacking_piece_pos();
         let mut animations: Vec<Animation> = vec![];
 
         for (target, _) in attack_event.removed_pieces() {
-            animations.push(attack_animation(
-                attack_event.piece_kind(),
-                *attack_event.attacking_piece_pos(),
-                *target,
-            ));
+            animations.push(attack_animation(attack_event.piece_kind(), pos, *target));
         }
 
-        let exhaustion_animation = Animation::new_exhaustion(
-            *attack_event.exhaustion_afterwards(),
-            *attack_event.attacking_piece_pos(),
-        );
+        // An enchantment neither removes nor displaces pieces, it only sends off its effect
+        if *attack_event.power() == Power::Enchant
+            && let Some((target, _)) = attack_event.added_effects().first()
+        {
+            animations.push(Animation::new_bullet(pos, *target));
+        }
+
+        if *attack_event.power() == Power::Swap {
+            let swapped_with = attack_event.displaced_pieces()[0].to();
+            animations.push(Animation::new_swap(pos, *swapped_with));
+        } else {
+            for displacement in attack_event.displaced_pieces() {
+                animations.push(displacement_animation(pos, displacement));
+            }
+        }
+
+        // The acting piece may have been displaced itself
+        let acting_piece_pos = attack_event
+            .displaced_pieces()
+            .iter()
+            .find(|displacement| *displacement.from() == pos)
+            .map_or(pos, |displacement| *displacement.to());
+        let exhaustion_animation =
+            Animation::new_exhaustion(*attack_event.exhaustion_afterwards(), acting_piece_pos);
 
         let mut merge_animations = attack_event
             .merge_events()
@@ -172,6 +209,16 @@ impl RenderEventConsumer {
 
         let first_animation = &mut animations[0];
         first_animation.next_animations.push(exhaustion_animation);
+        for (pos, effect) in attack_event.removed_effects() {
+            first_animation
+                .next_animations
+                .push(Animation::new_remove_effect(effect.kind, *pos));
+        }
+        for (pos, effect) in attack_event.added_effects() {
+            first_animation
+                .next_animations
+                .push(Animation::new_add_effect(effect.kind, *pos));
+        }
         first_animation
             .next_animations
             .append(&mut merge_animations);
@@ -182,8 +229,10 @@ impl RenderEventConsumer {
     fn handle_merge_events(merge_events: &MergeCompoundEvent) -> Vec<Animation> {
         let mut animations = vec![];
 
-        for (merged_towards, piece) in merge_events.placed_pieces() {
-            for (merged_from, _) in merge_events.removed_pieces().iter() {
+        for merge in merge_events.merges() {
+            let merged_towards = merge.at();
+            let piece = merge.piece();
+            for (merged_from, _) in merge.merged_from().iter() {
                 let mut a = Animation::new_move_towards(*merged_from, *merged_towards);
                 a.next_animations.push(Animation::new_remove(*merged_from));
 
@@ -204,7 +253,7 @@ impl RenderEventConsumer {
             ));
         }
 
-        for pos in merge_events.added_effects() {
+        for (pos, effect) in merge_events.added_effects() {
             let last_remove = animations
                 .last_mut()
                 .unwrap()
@@ -214,11 +263,11 @@ impl RenderEventConsumer {
 
             last_remove
                 .next_animations
-                .push(Animation::new_add_effect(EffectKind::Protection, *pos));
+                .push(Animation::new_add_effect(effect.kind, *pos));
         }
 
-        for pos in merge_events.removed_effects() {
-            animations.push(Animation::new_remove_effect(EffectKind::Protection, *pos));
+        for (pos, effect) in merge_events.removed_effects() {
+            animations.push(Animation::new_remove_effect(effect.kind, *pos));
         }
 
         if let Some(merge_event) = merge_events.merge_events() {
@@ -234,10 +283,7 @@ impl RenderEventConsumer {
 fn attack_animation(piece_kind: &PieceKind, pos: Point2, target: Point2) -> Animation {
     let mut bullet_animation = match piece_kind {
         PieceKind::Queen => Animation::new_blast(pos),
-        PieceKind::HorizontalBar | PieceKind::VerticalBar | PieceKind::Sniper => {
-            Animation::new_bullet(pos, target)
-        }
-        _ => panic!("Unknown piece_kind - can't generate bullet animation"),
+        _ => Animation::new_bullet(pos, target),
     };
     bullet_animation
         .next_animations
@@ -246,7 +292,7 @@ fn attack_animation(piece_kind: &PieceKind, pos: Point2, target: Point2) -> Anim
 }
 
 impl EventConsumer for RenderEventConsumer {
-    fn handle_event(&mut self, event: &GameAction) {
+    fn handle_event(&mut self, event: &GameAction) -> GameResult<()> {
         let mut board_render = (*self.board_render).borrow_mut();
         let animations = match event {
             GameAction::Attack(attack_event) => Self::handle_attack(attack_event),
@@ -254,8 +300,39 @@ impl EventConsumer for RenderEventConsumer {
             GameAction::Move(move_event) => Self::handle_move(move_event),
             GameAction::Undo(_) => Self::handle_undo(event),
             GameAction::FinishTurn(_) => Self::handle_finish_turn(event),
+            GameAction::GameOver(game_over) => {
+                board_render.set_outcome(*game_over.outcome());
+                return Ok(());
+            }
         };
 
         board_render.add_animation_sequence(animations);
+
+        Ok(())
     }
 }
+
+/// A bullet towards the displaced piece, which then slides to its new cell or, if it stays
+/// where it is, changes in place.
+fn displacement_animation(pos: Point2, displacement: &Displacement) -> Animation {
+    let (from, to) = (*displacement.from(), *displacement.to());
+    let mut bullet_animation = Animation::new_bullet(pos, from);
+
+    if from == to {
+        let piece = displacement.piece_afterwards();
+        let mut remove_animation = Animation::new_remove(from);
+        remove_animation.next_animations.push(Animation::new_piece(
+            piece.team_id,
+            to,
+            piece.piece_kind,
+            piece.exhaustion.is_done(),
+        ));
+        bullet_animation.next_animations.push(remove_animation);
+    } else {
+        bullet_animation
+            .next_animations
+            .push(Animation::new_move(from, to));
+    }
+
+    bullet_animation
+}
@@ -1,12 +1,6 @@
-use crate::{
-    animation::*,
-    constants::{CELL_WIDTH, PIECE_SCALE},
-    layout::LayoutConstants,
-    sprite::*,
-    ui::Button,
-};
+use crate::{animation::*, constants::PIECE_SCALE, layout::LayoutConstants, sprite::*, ui::Button};
 use game_core::core_game::CoreGameSubstate;
-use game_model::{Point2, board::*, game::*, piece::*, ranges::*};
+use game_model::{Point2, board::*, game::*, outcome::GameOutcome, piece::*, ranges::*};
 use instant::{Duration, Instant};
 use macroquad::{
     prelude::{Color, Vec2, WHITE},
@@ -24,7 +18,12 @@ pub struct CustomRenderContext {
     pub game_state: CoreGameSubstate,
     pub button_next: Button,
     pub button_undo: Button,
+    pub button_redo: Button,
     pub button_patterns: Button,
+    /// Shown instead of "Undo" and "Redo" while another player's undo request waits for an answer
+    pub button_accept_undo: Button,
+    pub button_decline_undo: Button,
+    pub show_undo_request: bool,
     pub show_patterns: bool,
     pub animation_speed_factor: f32, // smaller = faster
     pub show_debug_overlay: bool,
@@ -48,7 +47,11 @@ impl CustomRenderContext {
             game_state: CoreGameSubstate::Place,
             button_next: Button::new(layout.button_end_turn, "End Turn".to_string()),
             button_undo: Button::new(layout.button_undo, "Undo".to_string()),
+            button_redo: Button::new(layout.button_redo, "Redo".to_string()),
             button_patterns: Button::new(layout.button_patterns, "Patterns".to_string()),
+            button_accept_undo: Button::new(layout.button_undo, "Yes".to_string()),
+            button_decline_undo: Button::new(layout.button_redo, "No".to_string()),
+            show_undo_request: false,
             show_patterns: false,
             animation_speed_factor: 0.,
             show_debug_overlay: false,
@@ -59,7 +62,10 @@ impl CustomRenderContext {
     pub fn update_buttons(&mut self, layout: &LayoutConstants) {
         self.button_next = Button::new(layout.button_end_turn, "End Turn".to_string());
         self.button_undo = Button::new(layout.button_undo, "Undo".to_string());
+        self.button_redo = Button::new(layout.button_redo, "Redo".to_string());
         self.button_patterns = Button::new(layout.button_patterns, "Patterns".to_string());
+        self.button_accept_undo = Button::new(layout.button_undo, "Yes".to_string());
+        self.button_decline_undo = Button::new(layout.button_redo, "No".to_string());
     }
 }
 
@@ -70,13 +76,16 @@ pub struct BoardRender {
     pub(crate) placed_pieces: HashMap<Point2, SpriteRender>,
     pub(crate) effects: HashMap<Point2, Vec<EffectRender>>,
     pub(crate) team_colors: Vec<Colour>,
+    /// The piece table of the game, which tells the sprite of each kind
+    pieces: Vec<PieceDefinition>,
     next_animations: VecDeque<Vec<Animation>>,
     current_animations: Vec<Animation>,
+    outcome: Option<GameOutcome>,
 }
 
 impl BoardRender {
     pub fn new(game: &Game, layout: &LayoutConstants) -> Self {
-        let unused_pieces = vec![vec![], vec![]];
+        let unused_pieces = vec![vec![]; game.num_teams()];
         let mut placed_pieces = HashMap::new();
 
         let board = &game.board;
@@ -84,6 +93,8 @@ impl BoardRender {
         let team_colors = vec![
             Colour::new(0.96, 0.27, 0.20, 1.),
             Colour::new(0.90, 0.68, 0.15, 1.),
+            Colour::new(0.25, 0.45, 0.95, 1.),
+            Colour::new(0.30, 0.75, 0.30, 1.),
         ];
 
         board.for_each_placed_piece(|point, piece| {
@@ -91,7 +102,7 @@ impl BoardRender {
                 point,
                 SpriteRender::for_piece(
                     &point,
-                    piece.piece_kind,
+                    PieceDefinition::sprite(&game.pieces, piece.piece_kind),
                     team_colors[piece.team_id],
                     layout,
                 ),
@@ -103,10 +114,12 @@ impl BoardRender {
             unused_pieces,
             placed_pieces,
             team_colors,
+            pieces: game.pieces.clone(),
             special_sprites: HashMap::new(),
             next_animations: VecDeque::new(),
             effects: HashMap::new(),
             current_animations: vec![],
+            outcome: None,
         }
     }
 
@@ -164,21 +177,12 @@ impl BoardRender {
     }
 
     pub fn add_unused_piece(&mut self, team_id: usize) {
+        let num_teams = self.unused_pieces.len();
         let unused_pieces = &mut self.unused_pieces[team_id];
-        let layout = &self.layout;
 
-        // Compute next spare slot position
-        let (start_x, start_y) = if team_id == 0 {
-            layout.spare_start_team0
-        } else {
-            layout.spare_start_team1
-        };
-
-        let count = unused_pieces.len() as u32;
-        let col = count % layout.spare_cols;
-        let row = count / layout.spare_cols;
-        let x_pos = start_x + col as f32 * layout.spare_step.0;
-        let y_pos = start_y + row as f32 * layout.spare_step.1;
+        let (x_pos, y_pos) = self
+            .layout
+            .spare_slot(team_id, num_teams, unused_pieces.len());
 
         unused_pieces.push(SpriteRender::new(
             x_pos,
@@ -186,7 +190,10 @@ impl BoardRender {
             PIECE_SCALE,
             self.team_colors[team_id],
             SpriteKind::Piece,
-            SpriteRender::piece_sprite_rect(PieceKind::Simple),
+            SpriteRender::piece_sprite_rect(PieceDefinition::sprite(
+                &self.pieces,
+                PieceKind::Simple,
+            )),
         ));
     }
 
@@ -201,14 +208,32 @@ impl BoardRender {
         team_id: usize,
         exhausted: bool,
     ) {
-        let mut piece_render =
-            SpriteRender::for_piece(point, piece_kind, self.team_colors[team_id], &self.layout);
+        let mut piece_render = SpriteRender::for_piece(
+            point,
+            PieceDefinition::sprite(&self.pieces, piece_kind),
+            self.team_colors[team_id],
+            &self.layout,
+        );
         if exhausted {
             piece_render.override_color = Some(SpriteRender::greyed_out(&piece_render.color));
         }
         self.placed_pieces.insert(*point, piece_render);
     }
 
+    /// How the game ended, once the `GameOver` event arrived.
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        self.outcome
+    }
+
+    pub fn set_outcome(&mut self, outcome: GameOutcome) {
+        self.outcome = Some(outcome);
+    }
+
+    /// Whether animations are still running or waiting to be started.
+    pub fn is_animating(&self) -> bool {
+        !self.current_animations.is_empty() || !self.next_animations.is_empty()
+    }
+
     pub fn update(&mut self) {
         let animation_speed_factor = self.calculate_animation_speed_factor();
 
@@ -279,15 +304,14 @@ impl BoardRender {
             layout.shift_y,
             WHITE,
             DrawTextureParams {
-                dest_size: Some(Vec2::new(
-                    CELL_WIDTH * crate::constants::BOARD_WIDTH as f32,
-                    CELL_WIDTH * crate::constants::BOARD_HEIGHT as f32,
-                )),
+                dest_size: Some(Vec2::new(layout.board_width, layout.board_height)),
                 source: None,
                 ..Default::default()
             },
         );
 
+        Self::render_cell_kinds(board, layout);
+
         for (point, effects) in &self.effects {
             effects.iter().for_each(|e| e.render(point, layout));
         }
@@ -310,7 +334,13 @@ impl BoardRender {
             .for_each(|s| s.render(render_context));
 
         render_context.button_next.render(canvas);
-        render_context.button_undo.render(canvas);
+        if render_context.show_undo_request {
+            render_context.button_accept_undo.render(canvas);
+            render_context.button_decline_undo.render(canvas);
+        } else {
+            render_context.button_undo.render(canvas);
+            render_context.button_redo.render(canvas);
+        }
         render_context.button_patterns.render(canvas);
 
         if render_context.show_debug_overlay {
@@ -318,6 +348,31 @@ impl BoardRender {
         }
     }
 
+    fn render_cell_kinds(board: &Board, layout: &LayoutConstants) {
+        board.for_each_cell(|cell| {
+            let (x_pos, y_pos) = layout.cell_coords_point(&cell.point);
+            let size = layout.cell_width;
+
+            match cell.kind {
+                CellKind::Floor => {}
+                CellKind::Wall => {
+                    draw_rectangle(x_pos, y_pos, size, size, Color::from_rgba(90, 90, 90, 230));
+                    draw_rectangle_lines(
+                        x_pos,
+                        y_pos,
+                        size,
+                        size,
+                        4.,
+                        Color::from_rgba(200, 200, 200, 200),
+                    );
+                }
+                CellKind::Hole => {
+                    draw_rectangle(x_pos, y_pos, size, size, Color::from_rgba(0, 0, 0, 255));
+                }
+            }
+        });
+    }
+
     fn render_debug_overlay(layout: &LayoutConstants) {
         let blue = Color::from_rgba(0, 119, 255, 200);
         let green = Color::from_rgba(0, 255, 68, 150);
@@ -425,8 +480,8 @@ impl BoardRender {
                 draw_rectangle_lines(
                     x_pos - 2.5,
                     y_pos - 2.5,
-                    CELL_WIDTH + 2.5,
-                    CELL_WIDTH + 2.5,
+                    layout.cell_width + 2.5,
+                    layout.cell_width + 2.5,
                     5.,
                     Color::from_rgba(250, 130, 90, 255),
                 );
@@ -437,30 +492,39 @@ impl BoardRender {
                 }
             }
 
-            draw_rectangle(x_pos, y_pos, CELL_WIDTH, CELL_WIDTH, used_color.into());
+            draw_rectangle(
+                x_pos,
+                y_pos,
+                layout.cell_width,
+                layout.cell_width,
+                used_color.into(),
+            );
         }
     }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct EffectRender {
+    pub kind: EffectKind,
     pub from_color: Colour,
     pub towards_color: Colour,
     pub from_instant: Instant,
     pub towards_instant: Instant,
 }
 
-impl Default for EffectRender {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 impl EffectRender {
-    pub fn new() -> Self {
+    pub fn new(kind: EffectKind) -> Self {
+        let colour = match kind {
+            EffectKind::Protection => Colour::new(80., 0., 100., 0.6),
+            EffectKind::Slow => Colour::new(0., 0.4, 1., 0.5),
+            EffectKind::Poison => Colour::new(0.2, 0.8, 0., 0.5),
+            EffectKind::Reveal => Colour::new(1., 0.9, 0., 0.4),
+        };
+
         EffectRender {
-            from_color: Colour::new(80., 0., 100., 0.0),
-            towards_color: Colour::new(80., 0., 100., 0.6),
+            kind,
+            from_color: Colour { a: 0.0, ..colour },
+            towards_color: colour,
             from_instant: Instant::now(),
             towards_instant: Instant::now()
                 + Duration::from_millis(crate::constants::ANIMATION_SPEED * 3),
@@ -477,8 +541,8 @@ impl EffectRender {
         draw_rectangle(
             x_pos,
             y_pos,
-            CELL_WIDTH,
-            CELL_WIDTH,
+            layout.cell_width,
+            layout.cell_width,
             Color {
                 r: AnimationPoint::interpolate_value(
                     self.from_color.r,
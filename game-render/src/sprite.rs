@@ -1,9 +1,4 @@
-use crate::{
-    CustomRenderContext,
-    animation::*,
-    constants::{CELL_WIDTH, PIECE_SCALE},
-    layout::LayoutConstants,
-};
+use crate::{CustomRenderContext, animation::*, constants::CELL_WIDTH, layout::LayoutConstants};
 use game_model::{Point2, piece::*};
 use instant::{Duration, Instant};
 use macroquad::{
@@ -115,22 +110,19 @@ impl SpriteRender {
 
     pub(crate) fn for_piece(
         point: &Point2,
-        piece_kind: PieceKind,
+        sprite: SpriteCell,
         color: Colour,
         layout: &LayoutConstants,
     ) -> SpriteRender {
         let mut sprite_render = SpriteRender::new_at_point(
             point,
-            PIECE_SCALE,
+            layout.piece_scale,
             color,
             SpriteKind::Piece,
-            Self::piece_sprite_rect(piece_kind),
+            Self::piece_sprite_rect(sprite),
             layout,
         );
-
-        if piece_kind == PieceKind::HorizontalBar {
-            sprite_render.rotation = 1.57;
-        }
+        sprite_render.rotation = Self::piece_rotation(sprite);
 
         sprite_render
     }
@@ -153,25 +145,20 @@ impl SpriteRender {
         }
     }
 
-    pub fn piece_sprite_rect(piece_kind: PieceKind) -> Rect {
-        let (sprite_x, sprite_y) = match piece_kind {
-            PieceKind::Simple => (0, 0),
-            PieceKind::HorizontalBar => (1, 0),
-            PieceKind::VerticalBar => (1, 0),
-            PieceKind::Sniper => (2, 0),
-            PieceKind::Castle => (0, 1),
-            PieceKind::Queen => (1, 1),
-            PieceKind::Cross => (2, 1),
-        };
-
+    /// Where a piece is in the sprite sheet, see [`PieceDefinition::sprite`].
+    pub fn piece_sprite_rect(sprite: SpriteCell) -> Rect {
         Rect {
-            x: sprite_x as f32 * 400.,
-            y: sprite_y as f32 * 400.,
+            x: sprite.x as f32 * 400.,
+            y: sprite.y as f32 * 400.,
             w: 400.,
             h: 400.,
         }
     }
 
+    pub fn piece_rotation(sprite: SpriteCell) -> f32 {
+        if sprite.rotated { 1.57 } else { 0. }
+    }
+
     pub fn greyed_out(color: &Colour) -> Colour {
         Colour::new(
             (color.r + WHITE.r * 2.) / 3.,
@@ -182,15 +169,27 @@ impl SpriteRender {
     }
 
     pub fn move_towards(&mut self, point: &Point2, speed_ms: u64, layout: &LayoutConstants) {
+        self.move_and_scale_towards(point, self.to.sprite_width, speed_ms, layout);
+    }
+
+    /// Like [`Self::move_towards`], but also changes the sprite size, e.g. when a
+    /// spare piece is placed on a board whose pieces are drawn smaller.
+    pub fn move_and_scale_towards(
+        &mut self,
+        point: &Point2,
+        sprite_width: f32,
+        speed_ms: u64,
+        layout: &LayoutConstants,
+    ) {
         self.from = self.to;
         self.from.instant = Instant::now();
 
-        let (x_pos, y_pos) = layout.sprite_render_pos(self.from.sprite_width, point);
+        let (x_pos, y_pos) = layout.sprite_render_pos(sprite_width, point);
 
         self.to = AnimationPoint {
             x_pos,
             y_pos,
-            sprite_width: self.from.sprite_width,
+            sprite_width,
             instant: Instant::now() + Duration::from_millis(speed_ms),
         };
     }
@@ -0,0 +1,212 @@
+//! Headless replay of exported Bugchess games.
+//!
+//! Reads a [`GameRecord`] as written by the game's export, or the JSON array of
+//! [`GameCommand`](game_core::game_controller::GameCommand)s older versions exported, feeds
+//! its commands through a [`CommandHandler`] and prints the game after every command.
+//! The teams, board, rules, pieces and patterns are taken from the record, the teams and the
+//! board can be overridden with options.
+//! Stops at the first command that is rejected and exits with a failure code.
+
+use std::{
+    cell::RefCell,
+    path::PathBuf,
+    process::ExitCode,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use game_core::{
+    board_event_consumer::BoardEventConsumer, command_handler::CommandHandler, record::GameRecord,
+    setup::parse_board,
+};
+use game_events::event_broker::EventBroker;
+use game_model::{
+    board::Board,
+    game::{Game, Team},
+};
+
+const USAGE: &str =
+    "Usage: game-replay <exported game> [--teams <count>] [--board <width>x<height>|arena]";
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    path: PathBuf,
+    teams: Option<usize>,
+    board: Option<Board>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut path = None;
+    let mut teams = None;
+    let mut board = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--teams" => {
+                teams = Some(
+                    args.next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|teams| *teams > 0)
+                        .ok_or("--teams expects a positive number")?,
+                );
+            }
+            "--board" => {
+                let value = args.next().ok_or("--board expects a value")?;
+                board = Some(parse_board(&value).map_err(|e| e.to_string())?);
+            }
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    Ok(Options {
+        path: path.ok_or("No exported game given")?,
+        teams,
+        board,
+    })
+}
+
+fn read_record(path: &PathBuf) -> Result<GameRecord, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+
+    GameRecord::parse(&text)
+        .map_err(|e| format!("{} is not an exported game: {}", path.display(), e))
+}
+
+fn create_game(options: &Options, record: &GameRecord) -> (CommandHandler, Rc<RefCell<Game>>) {
+    let mut game = record.game();
+    if let Some(board) = &options.board {
+        game = game.with_board(board.clone());
+    }
+    if let Some(teams) = options.teams {
+        game.teams = (0..teams)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 0,
+            })
+            .collect();
+        game.rehash();
+    }
+    let game = Rc::new(RefCell::new(game));
+
+    let mut event_broker = EventBroker::new();
+    event_broker.subscribe(Box::new(BoardEventConsumer::new(game.clone())));
+    let command_handler = CommandHandler::new(event_broker, Arc::new(Mutex::new(vec![])));
+
+    (command_handler, game)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let record = match read_record(&options.path) {
+        Ok(record) => record,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let commands = &record.commands;
+
+    let (mut command_handler, game) = create_game(&options, &record);
+
+    for (index, command) in commands.iter().enumerate() {
+        let game_clone = (*game).borrow().clone();
+        if let Err(e) = command_handler.handle_new_command(game_clone, command) {
+            eprintln!(
+                "Command #{} ({}) of {} failed: {}",
+                index + 1,
+                command,
+                commands.len(),
+                e
+            );
+            return ExitCode::FAILURE;
+        }
+
+        println!("#{} {}\n{}", index + 1, command, (*game).borrow());
+    }
+
+    println!("Replayed all {} commands", commands.len());
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_model::Point2;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn parses_options() {
+        assert_eq!(
+            parse_args(args(&["game.json", "--teams", "3", "--board", "10x12"])),
+            Ok(Options {
+                path: PathBuf::from("game.json"),
+                teams: Some(3),
+                board: Some(Board::new(10, 12)),
+            })
+        );
+        assert!(parse_args(args(&["--board", "arena"])).is_err());
+        assert!(parse_args(args(&["game.json", "--board", "big"])).is_err());
+        assert!(parse_args(args(&["game.json", "--board", "0x8"])).is_err());
+    }
+
+    #[test]
+    fn replays_exported_games_with_comments() {
+        let path = PathBuf::from("../game-core/tests/exported_games/0002_Error on Purpose.json");
+        let record = read_record(&path).unwrap();
+        assert_eq!(record.commands.len(), 6);
+
+        let options = parse_args(args(&["game.json"])).unwrap();
+        let (mut command_handler, game) = create_game(&options, &record);
+        for command in &record.commands {
+            let game_clone = (*game).borrow().clone();
+            command_handler
+                .handle_new_command(game_clone, command)
+                .unwrap();
+        }
+        assert_eq!((*game).borrow().board.placed_pieces(1).len(), 1);
+    }
+
+    #[test]
+    fn records_describe_their_game() {
+        let record = GameRecord::parse(
+            "[Team0 \"Red\"]\n[Team1 \"Yellow\"]\n[Team2 \"Blue\"]\n[Board \"10x6\"]\n\n1. I 6, I 6, I 6",
+        )
+        .unwrap();
+
+        let options = parse_args(args(&["game.bgn"])).unwrap();
+        let (_, game) = create_game(&options, &record);
+        assert_eq!((*game).borrow().num_teams(), 3);
+        assert_eq!(
+            ((*game).borrow().board.w, (*game).borrow().board.h),
+            (10, 6)
+        );
+
+        let record = GameRecord::parse(
+            "[Teams \"4\"]\n[Board \"3x2\"]\n[Map \"~../.#.\"]\n\n1. I 6, I 6, I 6, I 6",
+        )
+        .unwrap();
+        let (_, game) = create_game(&options, &record);
+        assert_eq!((*game).borrow().num_teams(), 4);
+        assert!((*game).borrow().board.is_wall(&Point2::new(1, 1)));
+
+        let options = parse_args(args(&["game.bgn", "--board", "8x8"])).unwrap();
+        let (_, game) = create_game(&options, &record);
+        assert_eq!((*game).borrow().board.w, 8);
+    }
+}
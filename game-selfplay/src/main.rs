@@ -0,0 +1,320 @@
+//! Headless self-play between two bots to collect balance statistics.
+//!
+//! Plays a number of games between two [`Bot`] strategies directly on [`GameController`],
+//! without rendering, and prints a JSON report with win rates, game lengths and the merges
+//! and kills of every piece kind. The same seed always gives the same report, so a change in
+//! the numbers can be bisected.
+
+mod report;
+
+use std::process::ExitCode;
+
+use game_core::{
+    ai::{Bot, Difficulty},
+    game_controller::{GameCommand, GameController},
+    outcome::OutcomeTracker,
+    setup::{parse_board, set_up_pieces},
+};
+use game_model::{
+    board::Board,
+    game::{Game, Team},
+    outcome::GameOutcome,
+    piece::PieceDefinition,
+    rules::RuleSet,
+};
+use nanoserde::SerJson;
+use report::{GameRecord, PieceStats, Report};
+
+const USAGE: &str = "Usage: game-selfplay [--games <count>] [--seed <seed>] \
+                     [--bot-a easy|medium|hard] [--bot-b easy|medium|hard] \
+                     [--board <width>x<height>|arena] [--max-turns <count>] \
+                     [--rules <file>] [--pieces <file>]";
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    games: u32,
+    seed: u64,
+    bot_a: Difficulty,
+    bot_b: Difficulty,
+    board: Board,
+    /// Games that last longer are counted as a draw
+    max_turns: u32,
+    rules: RuleSet,
+    pieces: Vec<PieceDefinition>,
+}
+
+fn parse_difficulty(value: Option<String>) -> Result<Difficulty, String> {
+    let value = value.ok_or("Expected a difficulty")?;
+    Difficulty::parse(&value).map_err(|e| e.to_string())
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        games: 10,
+        seed: 0,
+        bot_a: Difficulty::Medium,
+        bot_b: Difficulty::Easy,
+        board: Board::new(8, 8),
+        max_turns: 200,
+        rules: RuleSet::default(),
+        pieces: PieceDefinition::default_definitions(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => {
+                options.games = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|games| *games > 0)
+                    .ok_or("--games expects a positive number")?;
+            }
+            "--seed" => {
+                options.seed = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--seed expects a number")?;
+            }
+            "--bot-a" => options.bot_a = parse_difficulty(args.next())?,
+            "--bot-b" => options.bot_b = parse_difficulty(args.next())?,
+            "--board" => {
+                let value = args.next().ok_or("--board expects a value")?;
+                options.board = parse_board(&value).map_err(|e| e.to_string())?;
+            }
+            "--max-turns" => {
+                options.max_turns = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--max-turns expects a number")?;
+            }
+            "--rules" => {
+                let path = args.next().ok_or("--rules expects a file")?;
+                let json = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Can't read rules from {}: {}", path, e))?;
+                options.rules = RuleSet::parse(&json).map_err(|e| format!("{}: {}", path, e))?;
+            }
+            "--pieces" => {
+                let path = args.next().ok_or("--pieces expects a file")?;
+                let json = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Can't read pieces from {}: {}", path, e))?;
+                options.pieces =
+                    PieceDefinition::parse_all(&json).map_err(|e| format!("{}: {}", path, e))?;
+            }
+            _ => return Err(format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Plays game number `index` of the run. Bot A plays the first team in even games
+/// and the second one in odd games, so neither profits from always starting.
+fn play_game(options: &Options, index: u32, pieces: &mut PieceStats) -> Result<GameRecord, String> {
+    let seed = options.seed.wrapping_add(index as u64);
+    quad_rand::srand(seed);
+
+    let teams = (0..2)
+        .map(|id| Team {
+            id,
+            lost: false,
+            unused_pieces: 0,
+        })
+        .collect();
+    let board = options.board.clone();
+    let mut game = Game::new(teams, board.w, board.h)
+        .with_board(board)
+        .with_pieces(options.pieces.clone())
+        .with_rules(options.rules);
+
+    let mut tracker = OutcomeTracker::new();
+    let set_up_commands =
+        set_up_pieces(2, &game).map_err(|e| format!("Could not set up the game: {}", e))?;
+    for command in set_up_commands {
+        let action = GameController::execute(&mut game, &command)
+            .map_err(|e| format!("Could not set up the game with {}: {}", command, e))?;
+        tracker.record(&game, &action);
+    }
+
+    let team_a = (index % 2) as usize;
+    let bots = if team_a == 0 {
+        [Bot::new(0, options.bot_a), Bot::new(1, options.bot_b)]
+    } else {
+        [Bot::new(0, options.bot_b), Bot::new(1, options.bot_a)]
+    };
+
+    let mut turns = 0;
+    let mut commands = 0;
+    let (winner, reason) = loop {
+        match tracker.outcome() {
+            Some(GameOutcome::Win { team_id, reason }) => {
+                break (
+                    if team_id == team_a { "A" } else { "B" },
+                    reason.to_string(),
+                );
+            }
+            Some(GameOutcome::Draw(reason)) => break ("draw", reason.to_string()),
+            None => {}
+        }
+
+        if turns >= options.max_turns {
+            break ("draw", "by turn limit".to_string());
+        }
+
+        let team_id = game.current_team_index;
+        let command = bots[team_id].next_command(&game);
+        let action = GameController::execute(&mut game, &command)
+            .map_err(|e| format!("Bot of team {} could not {}: {}", team_id, command, e))?;
+
+        tracker.record(&game, &action);
+        pieces.record(&action, team_id);
+        commands += 1;
+        if command == GameCommand::NextTurn {
+            turns += 1;
+        }
+    };
+
+    Ok(GameRecord {
+        seed,
+        team_a,
+        winner: winner.to_string(),
+        reason,
+        turns,
+        commands,
+    })
+}
+
+fn run(options: &Options) -> Result<Report, String> {
+    let mut report = Report::new(
+        options.seed,
+        options.bot_a.to_string(),
+        options.bot_b.to_string(),
+        options.rules,
+    );
+
+    for index in 0..options.games {
+        let record = play_game(options, index, &mut report.pieces)?;
+        eprintln!(
+            "Game {}/{}: {} {} after {} turns",
+            index + 1,
+            options.games,
+            record.winner,
+            record.reason,
+            record.turns
+        );
+        report.add(record);
+    }
+
+    Ok(report)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&options) {
+        Ok(report) => {
+            println!("{}", report.serialize_json());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_model::{
+        Point2,
+        piece::{Piece, PieceKind},
+    };
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn parses_options() {
+        let options = parse_args(args(&[
+            "--games", "4", "--seed", "42", "--bot-a", "hard", "--board", "6x6",
+        ]))
+        .unwrap();
+        assert_eq!(options.games, 4);
+        assert_eq!(options.seed, 42);
+        assert_eq!(options.bot_a, Difficulty::Hard);
+        assert_eq!(options.bot_b, Difficulty::Easy);
+        assert_eq!(options.board, Board::new(6, 6));
+
+        assert!(parse_args(args(&["--games", "0"])).is_err());
+        assert!(parse_args(args(&["--bot-b", "impossible"])).is_err());
+        assert!(parse_args(args(&["--board", "0x8"])).is_err());
+    }
+
+    #[test]
+    fn same_seed_gives_same_report() {
+        let options = parse_args(args(&[
+            "--games",
+            "2",
+            "--seed",
+            "3",
+            "--bot-a",
+            "easy",
+            "--board",
+            "6x6",
+            "--max-turns",
+            "8",
+        ]))
+        .unwrap();
+
+        let report = run(&options).unwrap();
+        assert_eq!(report.games, 2);
+        assert_eq!(report.results[1].team_a, 1);
+        assert_eq!(
+            report.serialize_json(),
+            run(&options).unwrap().serialize_json()
+        );
+    }
+
+    #[test]
+    fn counts_kills_of_the_attacking_piece() {
+        let teams = (0..2)
+            .map(|id| Team {
+                id,
+                lost: false,
+                unused_pieces: 0,
+            })
+            .collect();
+        let mut game = Game::new(teams, 8, 8);
+        let mut queen = Piece::new(0, PieceKind::Queen);
+        queen.exhaustion.reset();
+        game.board
+            .place_piece_at(queen, &Point2::new(1, 1))
+            .unwrap();
+        for point in [(2, 2), (6, 6)] {
+            game.board
+                .place_piece_at(Piece::new(1, PieceKind::Simple), &point.into())
+                .unwrap();
+        }
+
+        let action = GameController::execute(
+            &mut game,
+            &GameCommand::MovePiece(Point2::new(1, 1), Point2::new(2, 2)),
+        )
+        .unwrap();
+
+        let mut pieces = PieceStats::default();
+        pieces.record(&action, 0);
+        assert_eq!(pieces.kills.get("Queen"), Some(&1));
+        assert!(pieces.merged.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+
+use game_events::actions::{compound_events::GameAction, merge::MergeCompoundEvent};
+use game_model::{piece::PieceKind, rules::RuleSet};
+use nanoserde::SerJson;
+
+/// Outcome of a single game.
+#[derive(Debug, Clone, PartialEq, SerJson)]
+pub struct GameRecord {
+    /// Seed the game was played with
+    pub seed: u64,
+    /// Team played by bot A, bot B plays the other one
+    pub team_a: usize,
+    /// "A", "B", or "draw" if the game was drawn or the turn limit was reached first
+    pub winner: String,
+    /// How the game ended, e.g. "by repetition"
+    pub reason: String,
+    pub turns: u32,
+    pub commands: u32,
+}
+
+/// Pieces created and taken, keyed by piece kind.
+#[derive(Debug, Clone, Default, PartialEq, SerJson)]
+pub struct PieceStats {
+    /// How often a piece kind was created by a merge
+    pub merged: BTreeMap<String, u32>,
+    /// Opponent pieces removed by a piece kind, by moving onto or attacking them
+    pub kills: BTreeMap<String, u32>,
+}
+
+impl PieceStats {
+    /// Counts the merges and kills of an action executed by `team_id`.
+    pub fn record(&mut self, action: &GameAction, team_id: usize) {
+        let merge_events = match action {
+            GameAction::Place(place) => place.merge_events().as_ref(),
+            GameAction::Move(moving) => {
+                if let Some(captured) = moving.captured_piece()
+                    && captured.team_id != team_id
+                {
+                    add(&mut self.kills, moving.moved_piece().piece_kind, 1);
+                }
+                moving.merge_events().as_ref()
+            }
+            GameAction::Attack(attack) => {
+                let kills = attack
+                    .removed_pieces()
+                    .iter()
+                    .filter(|(_, piece)| piece.team_id != team_id)
+                    .count();
+                add(&mut self.kills, *attack.piece_kind(), kills as u32);
+                attack.merge_events().as_ref()
+            }
+            GameAction::Undo(_) | GameAction::FinishTurn(_) | GameAction::GameOver(_) => None,
+        };
+
+        let mut merge_event: Option<&MergeCompoundEvent> = merge_events;
+        while let Some(event) = merge_event {
+            for merge in event.merges() {
+                add(&mut self.merged, merge.piece().piece_kind, 1);
+            }
+            merge_event = event.merge_events().as_deref();
+        }
+    }
+}
+
+fn add(counts: &mut BTreeMap<String, u32>, kind: PieceKind, count: u32) {
+    if count > 0 {
+        *counts.entry(format!("{:?}", kind)).or_default() += count;
+    }
+}
+
+/// Everything collected during a run.
+#[derive(Debug, Clone, PartialEq, SerJson)]
+pub struct Report {
+    pub seed: u64,
+    pub bot_a: String,
+    pub bot_b: String,
+    /// Rules all games were played with
+    pub rules: RuleSet,
+    pub games: u32,
+    pub wins_a: u32,
+    pub wins_b: u32,
+    pub draws: u32,
+    pub win_rate_a: f32,
+    pub win_rate_b: f32,
+    pub average_turns: f32,
+    pub average_commands: f32,
+    pub pieces: PieceStats,
+    pub results: Vec<GameRecord>,
+}
+
+impl Report {
+    pub fn new(seed: u64, bot_a: String, bot_b: String, rules: RuleSet) -> Self {
+        Report {
+            seed,
+            bot_a,
+            bot_b,
+            rules,
+            games: 0,
+            wins_a: 0,
+            wins_b: 0,
+            draws: 0,
+            win_rate_a: 0.,
+            win_rate_b: 0.,
+            average_turns: 0.,
+            average_commands: 0.,
+            pieces: PieceStats::default(),
+            results: vec![],
+        }
+    }
+
+    pub fn add(&mut self, record: GameRecord) {
+        match record.winner.as_str() {
+            "A" => self.wins_a += 1,
+            "B" => self.wins_b += 1,
+            _ => self.draws += 1,
+        }
+        self.results.push(record);
+        self.games = self.results.len() as u32;
+
+        let games = self.games as f32;
+        self.win_rate_a = self.wins_a as f32 / games;
+        self.win_rate_b = self.wins_b as f32 / games;
+        self.average_turns = self.results.iter().map(|r| r.turns as f32).sum::<f32>() / games;
+        self.average_commands = self.results.iter().map(|r| r.commands as f32).sum::<f32>() / games;
+    }
+}
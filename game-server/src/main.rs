@@ -8,6 +8,7 @@ struct NoString;
 
 impl reject::Reject for NoString {}
 
+/// Each report is the record of the failed game in the BGN text format of `game_core::record`
 const ERROR_REPORTS_DIR: &str = "error_reports";
 const MAX_ERROR_REPORTS: usize = 50;
 
@@ -28,7 +29,7 @@ async fn main() {
                             panic!("Too many reports")
                         }
                     })
-                    .map(|()| format!("{}/{}.json", ERROR_REPORTS_DIR, Utc::now()))
+                    .map(|()| format!("{}/{}.bgn", ERROR_REPORTS_DIR, Utc::now()))
                     .and_then(File::create)
                     .and_then(|mut file| file.write(body_string.clone().into_bytes().as_slice()))
                     .map(|_| body_string)
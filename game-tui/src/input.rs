@@ -0,0 +1,111 @@
+use game_core::game_controller::GameCommand;
+use game_model::Point2;
+
+pub const HELP: &str = "\
+Commands (coordinates are column then row):
+  place <x> <y>              place an unused piece
+  move <x> <y> <to x> <to y> move or capture with a piece
+  blast <x> <y>              use a bar's blast
+  shoot <x> <y> <at x> <at y> shoot with a sniper or queen
+  power <x> <y> <at x> <at y> use a piece's power on a target
+  end                        end your turn
+  undo                       undo your last action, only this turn's against a bot
+  redo                       redo the last undone action
+  help                       show this help
+  quit                       leave the game";
+
+/// A line the player typed.
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Command(GameCommand),
+    Help,
+    Quit,
+}
+
+impl Input {
+    pub fn parse(line: &str) -> Result<Input, String> {
+        let mut words = line.split_whitespace();
+        let Some(verb) = words.next() else {
+            return Ok(Input::Help);
+        };
+        let numbers = words
+            .map(|w| {
+                w.parse::<u8>()
+                    .map_err(|_| format!("'{}' is not a coordinate", w))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+
+        let input = match (verb, numbers.as_slice()) {
+            ("place" | "p", [x, y]) => Input::Command(GameCommand::PlacePiece(Point2::new(*x, *y))),
+            ("move" | "m", [x, y, to_x, to_y]) => Input::Command(GameCommand::MovePiece(
+                Point2::new(*x, *y),
+                Point2::new(*to_x, *to_y),
+            )),
+            ("blast" | "b", [x, y]) => Input::Command(GameCommand::Blast(Point2::new(*x, *y))),
+            ("shoot" | "s", [x, y, at_x, at_y]) => Input::Command(GameCommand::TargetedShoot(
+                Point2::new(*x, *y),
+                Point2::new(*at_x, *at_y),
+            )),
+            ("power", [x, y, at_x, at_y]) => Input::Command(GameCommand::UsePower(
+                Point2::new(*x, *y),
+                Point2::new(*at_x, *at_y),
+            )),
+            ("end" | "e", []) => Input::Command(GameCommand::NextTurn),
+            ("undo" | "u", []) => Input::Command(GameCommand::Undo),
+            ("redo" | "r", []) => Input::Command(GameCommand::Redo),
+            ("help" | "h" | "?", []) => Input::Help,
+            ("quit" | "q", []) => Input::Quit,
+            (
+                "place" | "p" | "move" | "m" | "blast" | "b" | "shoot" | "s" | "power" | "end"
+                | "e" | "undo" | "u" | "redo" | "r" | "help" | "h" | "?" | "quit" | "q",
+                _,
+            ) => {
+                return Err(format!("Wrong number of coordinates for '{}'", verb));
+            }
+            _ => return Err(format!("Unknown command '{}'", verb)),
+        };
+
+        Ok(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            Input::parse("place 3 4"),
+            Ok(Input::Command(GameCommand::PlacePiece(Point2::new(3, 4))))
+        );
+        assert_eq!(
+            Input::parse("  m 1 1 1 3 "),
+            Ok(Input::Command(GameCommand::MovePiece(
+                Point2::new(1, 1),
+                Point2::new(1, 3)
+            )))
+        );
+        assert_eq!(
+            Input::parse("power 3 3 4 4"),
+            Ok(Input::Command(GameCommand::UsePower(
+                Point2::new(3, 3),
+                Point2::new(4, 4)
+            )))
+        );
+        assert_eq!(
+            Input::parse("end"),
+            Ok(Input::Command(GameCommand::NextTurn))
+        );
+        assert_eq!(Input::parse("r"), Ok(Input::Command(GameCommand::Redo)));
+        assert_eq!(Input::parse("quit"), Ok(Input::Quit));
+    }
+
+    #[test]
+    fn rejects_malformed_commands() {
+        assert!(Input::parse("place 3").is_err());
+        assert!(Input::parse("blast a b").is_err());
+        assert!(Input::parse("castle 1 1").is_err());
+        assert!(Input::parse("end 1").is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Terminal front-end for Bugchess.
+//!
+//! Renders the board with its text [`Display`](std::fmt::Display) and reads commands such as
+//! `place 3 4` from stdin. Commands run through the same [`CommandHandler`] as the graphical
+//! game, so hot-seat games behave exactly like they do there. Teams can also be played by a
+//! [`Bot`], e.g. to practise against it or to watch bots play each other.
+
+mod input;
+
+use std::{
+    cell::RefCell,
+    io::{BufRead, Write},
+    process::ExitCode,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use game_core::{
+    ai::{Bot, Difficulty},
+    board_event_consumer::BoardEventConsumer,
+    command_handler::CommandHandler,
+    game_controller::GameCommand,
+    setup::{parse_board, set_up_pieces},
+};
+use game_events::event_broker::EventBroker;
+use game_model::{
+    board::Board,
+    game::{Game, Team},
+};
+use input::{HELP, Input};
+
+const USAGE: &str = "Usage: game-tui [--teams <count>] [--board <width>x<height>|arena] \
+                     [--bot <team>]... [--difficulty easy|medium|hard]";
+
+struct Options {
+    teams: usize,
+    board: Board,
+    /// Teams played by the computer
+    bots: Vec<usize>,
+    difficulty: Difficulty,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        teams: 2,
+        board: Board::new(8, 8),
+        bots: vec![],
+        difficulty: Difficulty::Medium,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--teams" => {
+                options.teams = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|teams| (2..=4).contains(teams))
+                    .ok_or("--teams expects a number from 2 to 4")?;
+            }
+            "--board" => {
+                let value = args.next().ok_or("--board expects a value")?;
+                options.board = parse_board(&value).map_err(|e| e.to_string())?;
+            }
+            "--bot" => {
+                let team = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--bot expects a team number")?;
+                options.bots.push(team);
+            }
+            "--difficulty" => {
+                let value = args.next().ok_or("--difficulty expects a value")?;
+                options.difficulty = Difficulty::parse(&value).map_err(|e| e.to_string())?;
+            }
+            _ => return Err(format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    if let Some(team) = options.bots.iter().find(|team| **team >= options.teams) {
+        return Err(format!("There is no team {} to be played by a bot", team));
+    }
+
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let teams = (0..options.teams)
+        .map(|id| Team {
+            id,
+            lost: false,
+            unused_pieces: 0,
+        })
+        .collect();
+    let board = options.board;
+    let game = Rc::new(RefCell::new(
+        Game::new(teams, board.w, board.h).with_board(board),
+    ));
+
+    let mut event_broker = EventBroker::new();
+    event_broker.subscribe(Box::new(BoardEventConsumer::new(game.clone())));
+    let mut command_handler = CommandHandler::new(event_broker, Arc::new(Mutex::new(vec![])));
+    // Everybody plays at the same terminal, but a bot would replay a rewound turn at once
+    if options.bots.is_empty() {
+        command_handler = command_handler.with_turn_rewind();
+    }
+
+    let set_up_commands = match set_up_pieces(options.teams, &(*game).borrow()) {
+        Ok(commands) => commands,
+        Err(e) => {
+            eprintln!("Could not set up the game: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    for command in &set_up_commands {
+        let game_clone = (*game).borrow().clone();
+        if let Err(e) = command_handler.handle_new_command(game_clone, command) {
+            eprintln!("Could not set up the game with {}: {}", command, e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let bots: Vec<Bot> = options
+        .bots
+        .iter()
+        .map(|team| Bot::new(*team, options.difficulty))
+        .collect();
+
+    println!("{}\n\n{}", HELP, (*game).borrow());
+
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        let current_team = (*game).borrow().current_team_index;
+        print!("Team {}> ", current_team);
+
+        let input = if let Some(bot) = bots.iter().find(|bot| bot.team_id == current_team) {
+            let command = bot.next_command(&(*game).borrow());
+            println!("{}", command);
+            Ok(Input::Command(command))
+        } else {
+            let _ = std::io::stdout().flush();
+
+            let Some(Ok(line)) = lines.next() else {
+                break;
+            };
+            Input::parse(&line)
+        };
+
+        match input {
+            Ok(Input::Command(GameCommand::Undo)) if !command_handler.can_undo() => {
+                println!("Nothing to undo");
+            }
+            Ok(Input::Command(GameCommand::Redo)) if !command_handler.can_redo() => {
+                println!("Nothing to redo");
+            }
+            Ok(Input::Command(command)) => {
+                let game_clone = (*game).borrow().clone();
+                match command_handler.handle_new_command(game_clone, &command) {
+                    Ok(()) => println!("{}", (*game).borrow()),
+                    Err(e) => println!("Can't {}: {}", command, e),
+                }
+
+                if let Some(outcome) = command_handler.outcome() {
+                    println!("{}!", outcome);
+                    break;
+                }
+            }
+            Ok(Input::Help) => println!("{}", HELP),
+            Ok(Input::Quit) => break,
+            Err(e) => println!("{} - type 'help' for a list of commands", e),
+        }
+    }
+
+    ExitCode::SUCCESS
+}